@@ -7,7 +7,7 @@ def recursive_upload(client, directory):
     for sub in os.listdir(directory):
         sub = f'{directory}/{sub}'
         if os.path.isdir(sub):
-            if sub[-1] in ('A','B','C','D','E', 'G'):
+            if sub[-1] in ('A','B','C','D','E', '7'):
                 recursive_upload(client, sub)
         else:
             client.fput_object(
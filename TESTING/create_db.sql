@@ -37,7 +37,14 @@ CREATE TABLE public.game (
     upload_date date NOT NULL,
     name character varying(128) NOT NULL,
     hash character varying(255),
-    description character varying(1500) NOT NULL
+    description character varying(4096) NOT NULL,
+    updated_at timestamp with time zone DEFAULT now() NOT NULL,
+    downloads bigint DEFAULT 0 NOT NULL,
+    published boolean DEFAULT false NOT NULL,
+    deleted_at timestamp with time zone,
+    size_bytes bigint,
+    play_count bigint DEFAULT 0 NOT NULL,
+    last_played_at timestamp with time zone
 );
 
 
@@ -55,6 +62,65 @@ CREATE TABLE public.game_tags (
 
 ALTER TABLE public.game_tags OWNER TO devcade;
 
+--
+-- Name: game_versions; Type: TABLE; Schema: devcade; Owner: devcade
+--
+
+CREATE TABLE public.game_versions (
+    game_id character(36) NOT NULL,
+    version integer NOT NULL,
+    hash character varying(255) NOT NULL,
+    uploaded_at timestamp with time zone DEFAULT now() NOT NULL,
+    key character varying(255) NOT NULL,
+    arch character varying(32) DEFAULT 'x86_64' NOT NULL,
+    branch character varying(255) DEFAULT 'master' NOT NULL
+);
+
+
+ALTER TABLE public.game_versions OWNER TO devcade;
+
+--
+-- Name: blobs; Type: TABLE; Schema: devcade; Owner: devcade
+--
+
+CREATE TABLE public.blobs (
+    key character varying(255) NOT NULL,
+    digest character(64),
+    size_bytes bigint
+);
+
+
+ALTER TABLE public.blobs OWNER TO devcade;
+
+--
+-- Name: game_builds; Type: TABLE; Schema: devcade; Owner: devcade
+--
+
+CREATE TABLE public.game_builds (
+    game_id character(36) NOT NULL,
+    arch character varying(32) NOT NULL,
+    hash character varying(255) NOT NULL,
+    size_bytes bigint
+);
+
+
+ALTER TABLE public.game_builds OWNER TO devcade;
+
+--
+-- Name: game_ratings; Type: TABLE; Schema: devcade; Owner: devcade
+--
+
+CREATE TABLE public.game_ratings (
+    game_id character(36) NOT NULL,
+    device_id character varying(64),
+    score smallint NOT NULL,
+    rated_at timestamp with time zone DEFAULT now() NOT NULL,
+    CONSTRAINT game_ratings_score_check CHECK (((score >= 1) AND (score <= 5)))
+);
+
+
+ALTER TABLE public.game_ratings OWNER TO devcade;
+
 --
 -- Name: saves_user; Type: TABLE; Schema: devcade; Owner: devcade
 --
@@ -73,7 +139,8 @@ ALTER TABLE public.saves_user OWNER TO devcade;
 
 CREATE TABLE public.tags (
     name character varying(32) NOT NULL,
-    description text
+    description text,
+    color character varying(7) DEFAULT '#b0197e'::character varying NOT NULL
 );
 
 
@@ -128,6 +195,38 @@ ALTER TABLE ONLY public.game_tags
     ADD CONSTRAINT game_tags_pkey PRIMARY KEY (game_id, tag_name);
 
 
+--
+-- Name: game_versions game_versions_pkey; Type: CONSTRAINT; Schema: devcade; Owner: devcade
+--
+
+ALTER TABLE ONLY public.game_versions
+    ADD CONSTRAINT game_versions_pkey PRIMARY KEY (game_id, version);
+
+
+--
+-- Name: game_builds game_builds_pkey; Type: CONSTRAINT; Schema: devcade; Owner: devcade
+--
+
+ALTER TABLE ONLY public.game_builds
+    ADD CONSTRAINT game_builds_pkey PRIMARY KEY (game_id, arch);
+
+
+--
+-- Name: blobs blobs_pkey; Type: CONSTRAINT; Schema: devcade; Owner: devcade
+--
+
+ALTER TABLE ONLY public.blobs
+    ADD CONSTRAINT blobs_pkey PRIMARY KEY (key);
+
+
+--
+-- Name: blobs blobs_digest_key; Type: CONSTRAINT; Schema: devcade; Owner: devcade
+--
+
+ALTER TABLE ONLY public.blobs
+    ADD CONSTRAINT blobs_digest_key UNIQUE (digest);
+
+
 --
 -- Name: saves_user saves_user_pk; Type: CONSTRAINT; Schema: devcade; Owner: devcade
 --
@@ -144,6 +243,27 @@ ALTER TABLE ONLY public.tags
     ADD CONSTRAINT tags_pkey PRIMARY KEY (name);
 
 
+--
+-- Name: tags_name_lower; Type: INDEX; Schema: devcade; Owner: devcade
+--
+
+--
+-- Name: game_name_lower; Type: INDEX; Schema: devcade; Owner: devcade
+--
+
+CREATE INDEX game_name_lower ON public.game USING btree (lower((name)::text));
+
+
+--
+-- Name: game_ratings_device; Type: INDEX; Schema: devcade; Owner: devcade
+--
+
+CREATE UNIQUE INDEX game_ratings_device ON public.game_ratings USING btree (game_id, device_id);
+
+
+CREATE UNIQUE INDEX tags_name_lower ON public.tags USING btree (lower((name)::text));
+
+
 --
 -- Name: users user_pk; Type: CONSTRAINT; Schema: devcade; Owner: devcade
 --
@@ -184,6 +304,38 @@ ALTER TABLE ONLY public.game_tags
     ADD CONSTRAINT game_id FOREIGN KEY (game_id) REFERENCES public.game(id) ON UPDATE CASCADE ON DELETE CASCADE;
 
 
+--
+-- Name: game_versions game_versions_game_id_fk; Type: FK CONSTRAINT; Schema: devcade; Owner: devcade
+--
+
+ALTER TABLE ONLY public.game_versions
+    ADD CONSTRAINT game_versions_game_id_fk FOREIGN KEY (game_id) REFERENCES public.game(id) ON UPDATE CASCADE ON DELETE CASCADE;
+
+
+--
+-- Name: game_versions game_versions_key_fk; Type: FK CONSTRAINT; Schema: devcade; Owner: devcade
+--
+
+ALTER TABLE ONLY public.game_versions
+    ADD CONSTRAINT game_versions_key_fk FOREIGN KEY (key) REFERENCES public.blobs(key);
+
+
+--
+-- Name: game_builds game_builds_game_id_fk; Type: FK CONSTRAINT; Schema: devcade; Owner: devcade
+--
+
+ALTER TABLE ONLY public.game_builds
+    ADD CONSTRAINT game_builds_game_id_fk FOREIGN KEY (game_id) REFERENCES public.game(id) ON UPDATE CASCADE ON DELETE CASCADE;
+
+
+--
+-- Name: game_ratings game_ratings_game_id_fk; Type: FK CONSTRAINT; Schema: devcade; Owner: devcade
+--
+
+ALTER TABLE ONLY public.game_ratings
+    ADD CONSTRAINT game_ratings_game_id_fk FOREIGN KEY (game_id) REFERENCES public.game(id) ON UPDATE CASCADE ON DELETE CASCADE;
+
+
 --
 -- Name: saves_user saves_user_game_game_id_fk; Type: FK CONSTRAINT; Schema: devcade; Owner: devcade
 --
@@ -272,11 +424,11 @@ INSERT INTO game VALUES
 ('CCCCCCCC-CCCC-CCCC-CCCC-CCCCCCCCCCCC', 'ella', '2023-03-23', 'TestGameC', '0d641140903c21d47a007b0136e7c2a7295a254a', 'TestGameC Description'),
 ('DDDDDDDD-DDDD-DDDD-DDDD-DDDDDDDDDDDD', 'atom', '2023-03-23', 'TestGameD', '04d6c7defa5dd48067cb44a473ac8eeb17f529f5', 'TestGameD Description'),
 ('EEEEEEEE-EEEE-EEEE-EEEE-EEEEEEEEEEEE', 'joeneil', '2023-03-23', 'TestGameE', '5d4ac1284877c9262df5808b8ab0e922863f9464', 'TestGameE Description'),
--- ('FFFFFFFF-FFFF-FFFF-FFFF-FFFFFFFFFFFF', 'mtft', '2023-03-23', 'TestGameF', 'cb838a5177364dacaaeff3724d27202729ad4427', 'TestGameF Description'),
-('GGGGGGGG-GGGG-GGGG-GGGG-GGGGGGGGGGGG', 'skyz', '2023-03-23', 'TestGameG', '3bb390de22dbc674b993e33536bd53c6851a7290', 'TestGameG Description');
--- ('HHHHHHHH-HHHH-HHHH-HHHH-HHHHHHHHHHHH', 'skyz', '2023-03-23', 'TestGameH', '579e03f4fdad803a53602808ce2cfaead7c69344', 'TestGameH Description'),
--- ('IIIIIIII-IIII-IIII-IIII-IIIIIIIIIIII', 'skyz', '2023-03-23', 'TestGameI', '6f6e1f0733bc60463d32436d2c115382ec6a801f', 'TestGameI Description'),
--- ('JJJJJJJJ-JJJJ-JJJJ-JJJJ-JJJJJJJJJJJJ', 'skyz', '2023-03-23', 'TestGameJ', 'a5e8a81726700bc1b408cb60366f232ada0e726b', 'TestGameJ Description'),
+('FFFFFFFF-FFFF-FFFF-FFFF-FFFFFFFFFFFF', 'mtft', '2023-03-23', 'TestGameF', 'cb838a5177364dacaaeff3724d27202729ad4427', 'TestGameF Description'),
+('77777777-7777-7777-7777-777777777777', 'skyz', '2023-03-23', 'TestGameG', '3bb390de22dbc674b993e33536bd53c6851a7290', 'TestGameG Description'),
+('88888888-8888-8888-8888-888888888888', 'skyz', '2023-03-23', 'TestGameH', NULL, 'TestGameH Description'),
+('99999999-9999-9999-9999-999999999999', 'skyz', '2023-03-23', 'TestGameI', '6f6e1f0733bc60463d32436d2c115382ec6a801f', 'TestGameI Description'),
+('10101010-1010-1010-1010-101010101010', 'skyz', '2023-03-23', 'TestGameJ', 'a5e8a81726700bc1b408cb60366f232ada0e726b', 'TestGameJ Description');
 -- ('KKKKKKKK-KKKK-KKKK-KKKK-KKKKKKKKKKKK', 'skyz', '2023-03-23', 'TestGameK', '8b4290df8ecdd83dbd215fe745499c0f5e492e28', 'TestGameK Description'),
 -- ('LLLLLLLL-LLLL-LLLL-LLLL-LLLLLLLLLLLL', 'skyz', '2023-03-23', 'TestGameL', 'f942b92d813a16ab1ef322e8ad7b1a15d42390b5', 'TestGameL Description'),
 -- ('MMMMMMMM-MMMM-MMMM-MMMM-MMMMMMMMMMMM', 'skyz', '2023-03-23', 'TestGameM', '0d54118dcfd7105ac57008a835998f5a08488368', 'TestGameM Description'),
@@ -294,6 +446,9 @@ INSERT INTO game VALUES
 -- ('YYYYYYYY-YYYY-YYYY-YYYY-YYYYYYYYYYYY', 'skyz', '2023-03-23', 'TestGameY', 'e500d0c0816f7c0b2f12890a711d7378725ca9a0', 'TestGameY Description'),
 -- ('ZZZZZZZZ-ZZZZ-ZZZZ-ZZZZ-ZZZZZZZZZZZZ', 'skyz', '2023-03-23', 'TestGameZ', 'e4553dd5e307c7ce3aded8ca857c46e18a7935e9', 'TestGameZ Description');
 
+UPDATE game SET published = hash IS NOT NULL;
+
 INSERT INTO game_tags VALUES
 ('AAAAAAAA-AAAA-AAAA-AAAA-AAAAAAAAAAAA', 'TestTag1'),
-('CCCCCCCC-CCCC-CCCC-CCCC-CCCCCCCCCCCC', 'TestTag4');
+('CCCCCCCC-CCCC-CCCC-CCCC-CCCCCCCCCCCC', 'TestTag4'),
+('88888888-8888-8888-8888-888888888888', 'TestTag1');
@@ -0,0 +1,6 @@
+-- Track when a game was last modified so destructive operations can honor
+-- If-Match / If-Unmodified-Since preconditions
+ALTER TABLE public.game ADD COLUMN updated_at timestamp with time zone;
+UPDATE public.game SET updated_at = upload_date;
+ALTER TABLE public.game ALTER COLUMN updated_at SET DEFAULT now();
+ALTER TABLE public.game ALTER COLUMN updated_at SET NOT NULL;
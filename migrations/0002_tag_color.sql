@@ -0,0 +1,2 @@
+-- Display color for tags, as #rrggbb
+ALTER TABLE public.tags ADD COLUMN color character varying(7) DEFAULT '#b0197e' NOT NULL;
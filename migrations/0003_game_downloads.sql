@@ -0,0 +1,2 @@
+-- Count how many times each game's flatpak has been served
+ALTER TABLE public.game ADD COLUMN downloads bigint DEFAULT 0 NOT NULL;
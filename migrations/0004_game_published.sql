@@ -0,0 +1,3 @@
+-- Games stay drafts, hidden from the listing, until their flatpak is uploaded
+ALTER TABLE public.game ADD COLUMN published boolean DEFAULT false NOT NULL;
+UPDATE public.game SET published = hash IS NOT NULL;
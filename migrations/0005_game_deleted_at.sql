@@ -0,0 +1,2 @@
+-- Deleted games are hidden until restored or purged, leaving their files in S3
+ALTER TABLE public.game ADD COLUMN deleted_at timestamp with time zone;
@@ -0,0 +1,8 @@
+-- Every flatpak uploaded for a game, stored in S3 as {id}/versions/{version}.flatpak
+CREATE TABLE public.game_versions (
+    game_id character(36) NOT NULL REFERENCES public.game(id) ON UPDATE CASCADE ON DELETE CASCADE,
+    version integer NOT NULL,
+    hash character varying(255) NOT NULL,
+    uploaded_at timestamp with time zone DEFAULT now() NOT NULL,
+    PRIMARY KEY (game_id, version)
+);
@@ -0,0 +1,13 @@
+-- Flatpaks are stored once per distinct file and shared by every version that
+-- uploaded it. Versions stored before this keep their own key, with no digest.
+CREATE TABLE public.blobs (
+    key character varying(255) PRIMARY KEY,
+    digest character(64) UNIQUE
+);
+
+ALTER TABLE public.game_versions ADD COLUMN key character varying(255);
+UPDATE public.game_versions SET key = game_id || '/versions/' || version || '.flatpak';
+ALTER TABLE public.game_versions ALTER COLUMN key SET NOT NULL;
+INSERT INTO public.blobs (key) SELECT key FROM public.game_versions;
+ALTER TABLE public.game_versions
+    ADD CONSTRAINT game_versions_key_fk FOREIGN KEY (key) REFERENCES public.blobs(key);
@@ -0,0 +1,2 @@
+-- Room for longer descriptions, up to the 4096 characters the API accepts
+ALTER TABLE public.game ALTER COLUMN description TYPE character varying(4096);
@@ -0,0 +1,20 @@
+-- Tags differing only in case or surrounding whitespace are merged into the
+-- spelling used by the most games, then kept unique ignoring case
+CREATE TEMPORARY TABLE tag_merges AS
+SELECT name, first_value(name) OVER (
+    PARTITION BY lower(btrim(name))
+    ORDER BY (SELECT COUNT(*) FROM public.game_tags WHERE tag_name = tags.name) DESC, name
+) AS canonical
+FROM public.tags;
+
+INSERT INTO public.game_tags
+SELECT game_tags.game_id, tag_merges.canonical
+FROM public.game_tags JOIN tag_merges ON tag_merges.name = game_tags.tag_name
+WHERE tag_merges.name <> tag_merges.canonical
+ON CONFLICT DO NOTHING;
+DELETE FROM public.tags USING tag_merges
+WHERE tags.name = tag_merges.name AND tag_merges.name <> tag_merges.canonical;
+DROP TABLE tag_merges;
+
+UPDATE public.tags SET name = btrim(name) WHERE name <> btrim(name);
+CREATE UNIQUE INDEX tags_name_lower ON public.tags (lower(name));
@@ -0,0 +1,4 @@
+-- Flatpak sizes, for cabinets to plan storage before downloading. Rows from
+-- before this are filled in by POST /admin/games/backfill-sizes.
+ALTER TABLE public.game ADD COLUMN size_bytes bigint;
+ALTER TABLE public.blobs ADD COLUMN size_bytes bigint;
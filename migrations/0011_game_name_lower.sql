@@ -0,0 +1,4 @@
+-- Backs the case-insensitive check for new games reusing a live game's name.
+-- It isn't unique, since uploads can be forced through and older games
+-- already share names.
+CREATE INDEX game_name_lower ON public.game (lower(name));
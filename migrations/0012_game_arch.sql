@@ -0,0 +1,13 @@
+-- Flatpaks can be built for architectures besides x86_64. Each version records
+-- the one it was built for, and the flatpak served for each architecture other
+-- than x86_64 is recorded here. x86_64's stays on the game row, where clients
+-- from before other architectures were accepted look for it.
+ALTER TABLE public.game_versions ADD COLUMN arch character varying(32) DEFAULT 'x86_64' NOT NULL;
+
+CREATE TABLE public.game_builds (
+    game_id character(36) NOT NULL REFERENCES public.game(id) ON UPDATE CASCADE ON DELETE CASCADE,
+    arch character varying(32) NOT NULL,
+    hash character varying(255) NOT NULL,
+    size_bytes bigint,
+    PRIMARY KEY (game_id, arch)
+);
@@ -0,0 +1,3 @@
+-- Flatpaks can be built on branches besides master, e.g. stable or beta, and
+-- each version records the one it was
+ALTER TABLE public.game_versions ADD COLUMN branch character varying(255) DEFAULT 'master' NOT NULL;
@@ -0,0 +1,11 @@
+-- Players rate games from 1 to 5. A cabinet sends its device id with each
+-- rating so rating the same game again replaces its earlier score rather than
+-- counting twice; ratings without one always count.
+CREATE TABLE public.game_ratings (
+    game_id character(36) NOT NULL REFERENCES public.game(id) ON UPDATE CASCADE ON DELETE CASCADE,
+    device_id character varying(64),
+    score smallint NOT NULL CHECK (score BETWEEN 1 AND 5),
+    rated_at timestamp with time zone DEFAULT now() NOT NULL
+);
+
+CREATE UNIQUE INDEX game_ratings_device ON public.game_ratings (game_id, device_id);
@@ -0,0 +1,4 @@
+-- Cabinets cache flatpaks, so downloads undercount how often games are played.
+-- They report each session instead, counted separately.
+ALTER TABLE public.game ADD COLUMN play_count bigint DEFAULT 0 NOT NULL;
+ALTER TABLE public.game ADD COLUMN last_played_at timestamp with time zone;
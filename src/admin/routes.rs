@@ -0,0 +1,483 @@
+use crate::{
+    error::ApiError,
+    games::{
+        routes::{
+            asset_status, check_game_id, download_object, upload_banner_webp, upload_icon_thumbnail,
+        },
+        s3::retry_s3,
+    },
+    models::{AppState, Game},
+    security::RequireApiKey,
+};
+use actix_web::{
+    get, post,
+    web::{Data, Path, Query},
+    HttpResponse,
+};
+use aws_sdk_s3::{model::Object, Client};
+use chrono::Utc;
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::{query, query_as, query_scalar};
+use std::{
+    collections::{BTreeMap, HashSet},
+    error::Error,
+};
+use utoipa::{IntoParams, ToSchema};
+
+/// Where stored flatpak versions are kept, alongside the per-game prefixes
+const BLOBS_PREFIX: &str = "blobs";
+/// How old a prefix's newest object must be before it counts as orphaned, by
+/// default
+const DEFAULT_ORPHAN_MIN_AGE_SECONDS: i64 = 3600;
+/// How many games have their images reprocessed at once
+const CONCURRENT_REGENERATIONS: usize = 4;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GameAsset {
+    Flatpak,
+    Banner,
+    Icon,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct IncompleteGame {
+    #[schema(example = "9c6ff7d5-1ab4-4a24-99b8-b1bf29b5b6e5")]
+    pub id: String,
+    #[schema(example = "BrickBreaker")]
+    pub name: String,
+    #[schema(example = "ella")]
+    pub author: String,
+    pub missing: Vec<GameAsset>,
+}
+
+/// Every object key in the games bucket, following pagination
+pub async fn list_bucket_keys(
+    s3: &Client,
+    bucket: &str,
+) -> Result<HashSet<String>, Box<dyn Error>> {
+    Ok(list_bucket_objects(s3, bucket, None)
+        .await?
+        .into_iter()
+        .filter_map(|object| object.key)
+        .collect())
+}
+
+/// Every object in the games bucket whose key starts with `prefix`, if one
+/// is given, following pagination
+async fn list_bucket_objects(
+    s3: &Client,
+    bucket: &str,
+    prefix: Option<String>,
+) -> Result<Vec<Object>, Box<dyn Error>> {
+    let mut objects = vec![];
+    let mut continuation_token = None;
+    loop {
+        let list = || {
+            s3.list_objects_v2()
+                .bucket(bucket)
+                .set_prefix(prefix.clone())
+                .set_continuation_token(continuation_token.clone())
+                .send()
+        };
+        let page = retry_s3("list_objects_v2", list).await?;
+        objects.extend(page.contents.unwrap_or_default());
+        continuation_token = page.next_continuation_token;
+        if continuation_token.is_none() {
+            return Ok(objects);
+        }
+    }
+}
+
+/// Which of a game's assets are absent, going by its recorded hash and the
+/// objects present in the bucket
+pub fn missing_assets(game: &Game, keys: &HashSet<String>) -> Vec<GameAsset> {
+    let mut missing = vec![];
+    if game.hash.is_none() || !keys.contains(&format!("{}/{}.flatpak", game.id, game.id)) {
+        missing.push(GameAsset::Flatpak);
+    }
+    if !keys.contains(&format!("{}/banner", game.id)) {
+        missing.push(GameAsset::Banner);
+    }
+    if !keys.contains(&format!("{}/icon", game.id)) {
+        missing.push(GameAsset::Icon);
+    }
+    missing
+}
+
+#[utoipa::path(
+    context_path = "/admin",
+    responses(
+        (status = 200, description = "Games missing a flatpak, banner or icon, going by one listing of the bucket. Flatpaks that are present but don't match their hash are reported by /games/verify-integrity.", body = [IncompleteGame]),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    security(("api_key" = []))
+)]
+#[get("/games/incomplete", wrap = "RequireApiKey")]
+pub async fn get_incomplete_games(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(incomplete_games(&state).await?))
+}
+
+/// Every game missing a flatpak, banner or icon, with which ones it's missing
+pub async fn incomplete_games(state: &AppState) -> Result<Vec<IncompleteGame>, ApiError> {
+    let games =
+        query_as::<_, Game>("SELECT * FROM game WHERE deleted_at IS NULL ORDER BY upload_date, id")
+            .fetch_all(&state.db)
+            .await?;
+    let keys = list_bucket_keys(&state.s3, &state.games_bucket)
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(games
+        .into_iter()
+        .filter_map(|game| {
+            let missing = missing_assets(&game, &keys);
+            (!missing.is_empty()).then_some(IncompleteGame {
+                id: game.id,
+                name: game.name,
+                author: game.author,
+                missing,
+            })
+        })
+        .collect())
+}
+
+/// Assets computed from a game's uploaded originals rather than uploaded
+/// themselves
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DerivedAsset {
+    /// The game's cell in the icon sprite sheet
+    Sprite,
+    /// The shrunken icon shown in the menu grid
+    IconThumb,
+    /// The WebP copy of the banner, when banners are transcoded
+    BannerWebp,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RegeneratedAssets {
+    #[schema(example = "9c6ff7d5-1ab4-4a24-99b8-b1bf29b5b6e5")]
+    pub id: String,
+    /// Derived assets produced for the game; an asset is absent if its
+    /// original (e.g. the icon) is missing or unreadable
+    pub generated: Vec<DerivedAsset>,
+}
+
+/// Rebuilds a game's icon thumbnail and WebP banner from its stored icon and
+/// banner, with the current processing settings, returning which were stored
+async fn regenerate_images(state: &AppState, id: &str) -> Vec<DerivedAsset> {
+    let (s3, bucket) = (&state.s3, state.games_bucket.as_str());
+    let mut generated = vec![];
+    let thumbnail = async {
+        let icon = download_object(s3, bucket, &format!("{id}/icon")).await?;
+        upload_icon_thumbnail(icon.path(), s3, bucket, id).await
+    };
+    match thumbnail.await {
+        Ok(()) => generated.push(DerivedAsset::IconThumb),
+        Err(e) => log::warn!("Couldn't regenerate icon thumbnail for game {id}: {e}"),
+    }
+    let webp = async {
+        let banner = download_object(s3, bucket, &format!("{id}/banner")).await?;
+        upload_banner_webp(banner.path(), s3, bucket, id).await
+    };
+    match webp.await {
+        Ok(true) => generated.push(DerivedAsset::BannerWebp),
+        Ok(false) => {}
+        Err(e) => log::warn!("Couldn't regenerate WebP banner for game {id}: {e}"),
+    }
+    generated
+}
+
+/// Rebuilds every derived asset from the stored originals, reporting what
+/// was produced for each of `ids`
+async fn regenerate_assets(
+    state: &AppState,
+    ids: Vec<String>,
+) -> Result<Vec<RegeneratedAssets>, Box<dyn Error>> {
+    state.icon_sprite.invalidate();
+    let sprite = state
+        .icon_sprite
+        .get_or_build(&state.db, &state.s3, &state.games_bucket)
+        .await?;
+    Ok(stream::iter(ids)
+        .map(|id| {
+            let sprite = &sprite;
+            async move {
+                let mut generated = vec![];
+                if sprite.atlas.icons.contains_key(&id) {
+                    generated.push(DerivedAsset::Sprite);
+                }
+                generated.extend(regenerate_images(state, &id).await);
+                RegeneratedAssets { id, generated }
+            }
+        })
+        .buffered(CONCURRENT_REGENERATIONS)
+        .collect()
+        .await)
+}
+
+#[utoipa::path(
+    context_path = "/admin",
+    responses(
+        (status = 200, description = "Regenerated the game's sprite sheet cell, icon thumbnail and WebP banner from its stored icon and banner, with the current processing settings", body = RegeneratedAssets),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 404, description = "Missing game", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game")
+    ),
+    security(("api_key" = []))
+)]
+#[post("/games/{id}/regenerate-assets", wrap = "RequireApiKey")]
+pub async fn regenerate_game_assets(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
+    let (id,) = path.into_inner();
+    check_game_id(&id)?;
+    if !query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM game WHERE id = $1)")
+        .bind(&id)
+        .fetch_one(&state.db)
+        .await?
+    {
+        return Err(ApiError::game_not_found());
+    }
+    let mut regenerated = regenerate_assets(&state, vec![id]).await?;
+    Ok(HttpResponse::Ok().json(regenerated.remove(0)))
+}
+
+#[utoipa::path(
+    context_path = "/admin",
+    responses(
+        (status = 200, description = "Regenerated every game's sprite sheet cell, icon thumbnail and WebP banner, as for a single game", body = [RegeneratedAssets]),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    security(("api_key" = []))
+)]
+#[post("/games/regenerate-assets", wrap = "RequireApiKey")]
+pub async fn regenerate_all_assets(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let ids: Vec<String> = query_scalar("SELECT id FROM game WHERE deleted_at IS NULL ORDER BY id")
+        .fetch_all(&state.db)
+        .await?;
+    let regenerated = regenerate_assets(&state, ids).await?;
+    Ok(HttpResponse::Ok().json(regenerated))
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct SizeBackfill {
+    /// How many games had the size of their current flatpak recorded
+    pub games: usize,
+    /// How many stored flatpak versions had their size recorded
+    pub blobs: usize,
+    /// Keys of flatpaks that should be in the bucket but aren't, and so are
+    /// still without a size
+    pub missing: Vec<String>,
+}
+
+#[utoipa::path(
+    context_path = "/admin",
+    responses(
+        (status = 200, description = "Recorded the size of every flatpak uploaded before sizes were", body = SizeBackfill),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query, or S3 couldn't be reached", body = ErrorBody),
+    ),
+    security(("api_key" = []))
+)]
+#[post("/games/backfill-sizes", wrap = "RequireApiKey")]
+pub async fn backfill_sizes(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let mut backfill = SizeBackfill::default();
+    let games: Vec<String> = query_scalar(
+        "SELECT id FROM game WHERE hash IS NOT NULL AND size_bytes IS NULL ORDER BY id",
+    )
+    .fetch_all(&state.db)
+    .await?;
+    for id in games {
+        let key = format!("{}/{}.flatpak", id, id);
+        match asset_status(&state.s3, &state.games_bucket, key.clone())
+            .await?
+            .size
+        {
+            Some(size) => {
+                // An upload since the list was read recorded its own size
+                query("UPDATE game SET size_bytes = $1 WHERE id = $2 AND size_bytes IS NULL")
+                    .bind(size)
+                    .bind(&id)
+                    .execute(&state.db)
+                    .await?;
+                backfill.games += 1;
+            }
+            None => backfill.missing.push(key),
+        }
+    }
+    let blobs: Vec<String> =
+        query_scalar("SELECT key FROM blobs WHERE size_bytes IS NULL ORDER BY key")
+            .fetch_all(&state.db)
+            .await?;
+    for key in blobs {
+        match asset_status(&state.s3, &state.games_bucket, key.clone())
+            .await?
+            .size
+        {
+            Some(size) => {
+                query("UPDATE blobs SET size_bytes = $1 WHERE key = $2 AND size_bytes IS NULL")
+                    .bind(size)
+                    .bind(&key)
+                    .execute(&state.db)
+                    .await?;
+                backfill.blobs += 1;
+            }
+            None => backfill.missing.push(key),
+        }
+    }
+    Ok(HttpResponse::Ok().json(backfill))
+}
+
+/// Objects stored under a prefix that no game's id matches, or flatpak blobs
+/// no row records, left behind by failed uploads
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct OrphanedGame {
+    /// The prefix the objects are stored under, `blobs` for flatpak blobs
+    #[schema(example = "9c6ff7d5-1ab4-4a24-99b8-b1bf29b5b6e5")]
+    pub id: String,
+    #[schema(example = json!(["9c6ff7d5-1ab4-4a24-99b8-b1bf29b5b6e5/banner"]))]
+    pub keys: Vec<String>,
+    /// Total size of the objects in bytes
+    #[schema(example = 48213)]
+    pub size_bytes: i64,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct OrphanQuery {
+    /// Only count prefixes whose newest object is at least this many seconds
+    /// old, so the files of a game still being uploaded are left alone
+    /// (default 3600)
+    min_age: Option<i64>,
+    /// Only look at objects stored under this prefix
+    id: Option<String>,
+}
+
+/// Groups the bucket's objects by the game id they're stored under, keeping
+/// the prefixes that no game row (deleted or not) has, along with the blobs
+/// that have no row of their own
+async fn find_orphans(
+    state: &AppState,
+    params: &OrphanQuery,
+) -> Result<Vec<OrphanedGame>, ApiError> {
+    let prefix = params.id.as_ref().map(|id| format!("{id}/"));
+    let objects = list_bucket_objects(&state.s3, &state.games_bucket, prefix).await?;
+    // Read after listing, so a game saved in between has objects too new to
+    // count rather than being mistaken for an orphan
+    let ids: HashSet<String> = query_scalar("SELECT id FROM game")
+        .fetch_all(&state.db)
+        .await?
+        .into_iter()
+        .collect();
+    let blobs: HashSet<String> = query_scalar("SELECT key FROM blobs")
+        .fetch_all(&state.db)
+        .await?
+        .into_iter()
+        .collect();
+    let cutoff = Utc::now().timestamp() - params.min_age.unwrap_or(DEFAULT_ORPHAN_MIN_AGE_SECONDS);
+    let mut prefixes: BTreeMap<String, (OrphanedGame, i64)> = BTreeMap::new();
+    for object in objects {
+        let Some((id, key)) = object
+            .key()
+            .and_then(|key| Some((key.split_once('/')?.0, key)))
+        else {
+            continue;
+        };
+        let modified = object.last_modified().map_or(i64::MAX, |date| date.secs());
+        if id == BLOBS_PREFIX {
+            // Blobs are shared between games, so each is judged on its own
+            // age rather than by the newest of them
+            if blobs.contains(key) || modified > cutoff {
+                continue;
+            }
+        } else if ids.contains(id) {
+            continue;
+        }
+        let (orphan, newest) = prefixes.entry(id.to_string()).or_insert_with(|| {
+            (
+                OrphanedGame {
+                    id: id.to_string(),
+                    keys: vec![],
+                    size_bytes: 0,
+                },
+                i64::MIN,
+            )
+        });
+        orphan.keys.push(key.to_string());
+        orphan.size_bytes += object.size();
+        *newest = (*newest).max(modified);
+    }
+    Ok(prefixes
+        .into_values()
+        .filter(|(_, newest)| *newest <= cutoff)
+        .map(|(orphan, _)| orphan)
+        .collect())
+}
+
+#[utoipa::path(
+    context_path = "/admin",
+    responses(
+        (status = 200, description = "Objects in the bucket stored under an id no game has, and flatpak blobs no row records", body = [OrphanedGame]),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query, or S3 couldn't be reached", body = ErrorBody),
+    ),
+    params(OrphanQuery),
+    security(("api_key" = []))
+)]
+#[get("/games/orphans", wrap = "RequireApiKey")]
+pub async fn get_orphans(
+    state: Data<AppState>,
+    params: Query<OrphanQuery>,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(find_orphans(&state, &params).await?))
+}
+
+#[utoipa::path(
+    context_path = "/admin",
+    responses(
+        (status = 200, description = "Deleted the objects stored under an id no game has, and flatpak blobs no row records", body = [OrphanedGame]),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query, or S3 couldn't be reached", body = ErrorBody),
+    ),
+    params(OrphanQuery),
+    security(("api_key" = []))
+)]
+#[post("/games/orphans/purge", wrap = "RequireApiKey")]
+pub async fn purge_orphans(
+    state: Data<AppState>,
+    params: Query<OrphanQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let orphans = find_orphans(&state, &params).await?;
+    for key in orphans.iter().flat_map(|orphan| &orphan.keys) {
+        let delete = || {
+            state
+                .s3
+                .delete_object()
+                .bucket(&state.games_bucket)
+                .key(key)
+                .send()
+        };
+        retry_s3("delete_object", delete)
+            .await
+            .map_err(ApiError::internal)?;
+    }
+    if !orphans.is_empty() {
+        log::info!(
+            "Purged orphaned objects under {}",
+            orphans
+                .iter()
+                .map(|orphan| orphan.id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    Ok(HttpResponse::Ok().json(orphans))
+}
@@ -0,0 +1,185 @@
+use crate::admin::routes::{
+    DerivedAsset, GameAsset, IncompleteGame, OrphanedGame, RegeneratedAssets, SizeBackfill,
+};
+use crate::app::get_app_data;
+#[cfg(test)]
+use crate::tests::{get_test_server, TEST_GAME_A};
+use aws_sdk_s3::types::ByteStream;
+
+#[actix_web::test]
+async fn test_get_incomplete_games() {
+    let srv = get_test_server().await;
+    let req = srv
+        .get("/api/admin/games/incomplete")
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<IncompleteGame> = res.json().await.unwrap();
+    assert!(games.iter().all(|game| !game.missing.is_empty()));
+    // Test data is uploaded as zips rather than flatpaks
+    let game_a = games.iter().find(|game| game.id == TEST_GAME_A.id).unwrap();
+    assert!(game_a.missing.contains(&GameAsset::Flatpak));
+}
+
+#[actix_web::test]
+async fn test_get_missing_assets() {
+    let srv = get_test_server().await;
+    let req = srv
+        .get("/api/games/missing-assets")
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<IncompleteGame> = res.json().await.unwrap();
+    let game_a = games.iter().find(|game| game.id == TEST_GAME_A.id).unwrap();
+    assert_eq!(game_a.missing, vec![GameAsset::Flatpak]);
+
+    let res = srv.get("/api/games/missing-assets").send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[actix_web::test]
+async fn test_get_incomplete_games_unauthorized() {
+    let srv = get_test_server().await;
+    let res = srv.get("/api/admin/games/incomplete").send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[actix_web::test]
+async fn test_regenerate_game_assets() {
+    let srv = get_test_server().await;
+    let req = srv
+        .post(format!(
+            "/api/admin/games/{}/regenerate-assets",
+            TEST_GAME_A.id
+        ))
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    let regenerated: RegeneratedAssets = res.json().await.unwrap();
+    assert_eq!(regenerated.id, TEST_GAME_A.id);
+    // The WebP banner also depends on the build having a WebP encoder
+    assert!(regenerated
+        .generated
+        .starts_with(&[DerivedAsset::Sprite, DerivedAsset::IconThumb]));
+    // Test data is uploaded without thumbnails
+    let res = srv
+        .get(format!("/api/games/{}/icon/thumb", TEST_GAME_A.id))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+}
+
+#[actix_web::test]
+async fn test_regenerate_assets_missing_game() {
+    let srv = get_test_server().await;
+    let req = srv
+        .post("/api/admin/games/00000000-0000-0000-0000-000000000000/regenerate-assets")
+        .insert_header(("frontend_api_key", "TESTING"));
+    let res = req.send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn test_backfill_sizes() {
+    let srv = get_test_server().await;
+    let req = srv
+        .post("/api/admin/games/backfill-sizes")
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    let backfill: SizeBackfill = res.json().await.unwrap();
+    // Test data is uploaded as zips rather than flatpaks, so has no size
+    let flatpak = format!("{}/{}.flatpak", TEST_GAME_A.id, TEST_GAME_A.id);
+    assert!(backfill.missing.contains(&flatpak));
+}
+
+#[actix_web::test]
+async fn test_purge_orphans() {
+    let srv = get_test_server().await;
+    let state = get_app_data().await;
+    let id = uuid::Uuid::new_v4().to_string();
+    state
+        .s3
+        .put_object()
+        .bucket(&state.games_bucket)
+        .key(format!("{id}/banner"))
+        .body(ByteStream::from_static(b"left behind"))
+        .send()
+        .await
+        .unwrap();
+    let list = |min_age: i64| {
+        srv.get(format!(
+            "/api/admin/games/orphans?min_age={min_age}&id={id}"
+        ))
+        .insert_header(("frontend_api_key", "TESTING"))
+    };
+
+    // Too new to be anything but an upload in progress
+    let mut res = list(3600).send().await.unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.json::<Vec<OrphanedGame>>().await.unwrap(), vec![]);
+
+    let orphan = OrphanedGame {
+        id: id.clone(),
+        keys: vec![format!("{id}/banner")],
+        size_bytes: 11,
+    };
+    let mut res = list(0).send().await.unwrap();
+    assert_eq!(
+        res.json::<Vec<OrphanedGame>>().await.unwrap(),
+        vec![orphan.clone()]
+    );
+    let req = srv
+        .post(format!("/api/admin/games/orphans/purge?min_age=0&id={id}"))
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.json::<Vec<OrphanedGame>>().await.unwrap(), vec![orphan]);
+    let mut res = list(0).send().await.unwrap();
+    assert_eq!(res.json::<Vec<OrphanedGame>>().await.unwrap(), vec![]);
+}
+
+#[actix_web::test]
+async fn test_get_orphans_reports_unrecorded_blobs() {
+    let srv = get_test_server().await;
+    let state = get_app_data().await;
+    let key = format!("blobs/{}.flatpak", uuid::Uuid::new_v4().simple());
+    state
+        .s3
+        .put_object()
+        .bucket(&state.games_bucket)
+        .key(&key)
+        .body(ByteStream::from_static(b"never recorded"))
+        .send()
+        .await
+        .unwrap();
+    let req = srv
+        .get("/api/admin/games/orphans?min_age=0&id=blobs")
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    let orphans: Vec<OrphanedGame> = res.json().await.unwrap();
+    assert_eq!(orphans.len(), 1);
+    assert_eq!(orphans[0].id, "blobs");
+    assert!(orphans[0].keys.contains(&key));
+    let recorded: Vec<String> = sqlx::query_scalar("SELECT key FROM blobs")
+        .fetch_all(&state.db)
+        .await
+        .unwrap();
+    assert!(!orphans[0].keys.iter().any(|key| recorded.contains(key)));
+}
+
+#[actix_web::test]
+async fn test_get_orphans_skips_games() {
+    let srv = get_test_server().await;
+    let req = srv
+        .get(format!(
+            "/api/admin/games/orphans?min_age=0&id={}",
+            TEST_GAME_A.id
+        ))
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.json::<Vec<OrphanedGame>>().await.unwrap(), vec![]);
+}
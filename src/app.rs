@@ -1,50 +1,118 @@
 use crate::{
-    games::routes::{self as games, FileUploadDoc, GameData, GameUploadDoc},
-    models::{AppState, Game, GameWithTags, Tag, User, UserType},
-    tags::routes as tags,
+    admin::routes::{
+        self as admin, DerivedAsset, GameAsset, IncompleteGame, OrphanedGame, RegeneratedAssets,
+        SizeBackfill,
+    },
+    error::ErrorBody,
+    events::{EventProducer, GameEvent, GameEventKind},
+    features,
+    games::{
+        flatpak::{FlatpakSummary, FlatpakSummaryCache},
+        routes::{
+            self as games, AssetStatus, BatchDeleteResult, BatchDeleteStatus, DownloadUrl,
+            FileUploadDoc, FlatpakHash, GameData, GameExport, GamePatch, GameRating, GameStatus,
+            GameUploadDoc, ImportResult, IntegrityFailure, IntegrityProblem, IntegrityReport,
+            NewRating,
+        },
+        sprite::{IconAtlas, IconSpriteCache, SpriteRect},
+    },
+    metrics,
+    models::{
+        AppState, Game, GameSummary, GameVersion, GameWithTags, Tag, TagWithCount, User, UserType,
+    },
+    security::{RateLimit, RateLimiter, RequireDocsAuth},
+    tags::routes::{self as tags, BulkTagResult, TagStats},
     users::routes as users,
+    validation::{FieldError, ValidationErrors},
 };
 
 use actix_web::body::BoxBody;
+use actix_web::dev::Extensions;
+use actix_web::http::header;
+use actix_web::middleware::NormalizePath;
+use actix_web::rt::net::TcpStream;
 use actix_web::web::{self, scope, Data};
 use actix_web::HttpResponse;
 use aws_sdk_s3 as s3;
-use aws_sdk_s3::Endpoint;
+use aws_sdk_s3::{Endpoint, Region, RetryConfig};
 
 use sqlx::postgres::PgPoolOptions;
-use std::env;
+use std::{
+    any::Any,
+    env,
+    os::fd::{AsRawFd, BorrowedFd, RawFd},
+};
 use utoipa::{
     openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
     Modify, OpenApi,
 };
 use utoipa_swagger_ui::SwaggerUi;
 
+const DEFAULT_S3_ENDPOINT: &str = "https://s3.csh.rit.edu";
+const DEFAULT_S3_REGION: &str = "us-east-1";
+
 #[derive(OpenApi)]
 #[openapi(
     paths(
         games::get_all_games,
+        games::search_games,
+        games::get_popular_games,
+        games::get_recently_played_games,
+        games::get_game_events,
+        games::export_games,
+        games::import_games,
         games::get_game,
+        games::get_similar_games,
         games::edit_game,
+        games::patch_game,
         games::delete_game,
+        games::restore_game,
+        games::batch_delete_games,
         games::add_game,
+        games::publish_game,
         games::get_binary,
+        games::get_download_url,
+        games::get_binary_hash,
+        games::get_game_status,
+        games::get_flatpak_metadata,
+        games::get_flatpak_summary,
+        games::verify_integrity,
+        games::get_missing_assets,
         games::update_binary,
+        games::get_game_versions,
+        games::rollback_game,
+        games::rate_game,
+        games::get_game_rating,
+        games::record_play,
         games::get_banner,
         games::update_banner,
+        games::get_icon_sprite,
+        games::get_icon_atlas,
         games::get_icon,
+        games::get_icon_thumb,
         games::update_icon,
         tags::get_all_tags,
+        tags::get_tag_stats,
         tags::get_tag,
         tags::edit_tag,
         tags::delete_tag,
         tags::add_tag,
+        tags::bulk_upsert_tags,
         tags::get_tag_games,
+        users::get_all_authors,
         users::get_user,
+        users::get_user_games,
         users::add_user,
         users::edit_user,
+        admin::get_incomplete_games,
+        admin::regenerate_game_assets,
+        admin::regenerate_all_assets,
+        admin::backfill_sizes,
+        admin::get_orphans,
+        admin::purge_orphans,
     ),
     components(
-        schemas(GameData, Game, GameUploadDoc, FileUploadDoc, GameWithTags, Tag, User, UserType)
+        schemas(AssetStatus, GameEvent, GameEventKind, GameData, GamePatch, Game, GameExport, GameVersion, GameRating, NewRating, ImportResult, BatchDeleteResult, BatchDeleteStatus, DownloadUrl, FlatpakHash, FlatpakSummary, GameStatus, IntegrityFailure, IntegrityProblem, IntegrityReport, GameUploadDoc, FileUploadDoc, GameWithTags, GameSummary, Tag, TagWithCount, User, UserType, ErrorBody, FieldError, ValidationErrors, GameAsset, IncompleteGame, DerivedAsset, RegeneratedAssets, SizeBackfill, OrphanedGame, BulkTagResult, TagStats, IconAtlas, SpriteRect)
     ),
     tags(
         (name = "DevcadeAPI", description = "")
@@ -65,63 +133,172 @@ impl Modify for SecurityAddon {
     }
 }
 
+/// Paths are normalized by trimming trailing slashes (and merging repeated
+/// ones) before routing, so `/api/games` and `/api/games/` resolve to the same
+/// route. Routes must therefore be registered without a trailing slash.
+pub fn normalize_path() -> NormalizePath {
+    NormalizePath::trim()
+}
+
+/// The socket a request arrived on, recorded by [`record_connection`], so a
+/// response can hang up on a client however far its body has got
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionSocket(RawFd);
+
+impl ConnectionSocket {
+    /// A handle of the response's own on the connection, which stays usable
+    /// after the handler returns. Only call it while handling the request.
+    pub fn try_clone(&self) -> std::io::Result<std::net::TcpStream> {
+        // Safe because the connection, and so its socket, outlives the
+        // requests handled on it
+        let socket = unsafe { BorrowedFd::borrow_raw(self.0) };
+        Ok(socket.try_clone_to_owned()?.into())
+    }
+}
+
+/// Makes each connection's socket available to its requests as
+/// [`ConnectionSocket`], for `HttpServer::on_connect`
+pub fn record_connection(connection: &dyn Any, data: &mut Extensions) {
+    if let Some(socket) = connection.downcast_ref::<TcpStream>() {
+        data.insert(ConnectionSocket(socket.as_raw_fd()));
+    }
+}
+
 pub fn configure_app(cfg: &mut web::ServiceConfig) {
     let openapi = ApiDoc::openapi();
+    cfg.route("/metrics", web::get().to(metrics::metrics));
     cfg.service(
         scope("/api")
+            .wrap(RateLimit)
+            .wrap(metrics::RecordMetrics)
             .service(
                 scope("/games")
+                    .app_data(games::multipart_config())
+                    .app_data(games::temp_file_config())
                     .service(games::get_all_games)
+                    .service(games::search_games)
+                    .service(games::get_popular_games)
+                    .service(games::get_recently_played_games)
+                    .configure(features::configure_feature("events", |cfg| {
+                        cfg.service(games::get_game_events);
+                    }))
+                    .service(games::get_missing_assets)
+                    .service(games::export_games)
+                    .service(games::import_games)
                     .service(games::get_game)
+                    .service(games::get_similar_games)
                     .service(games::edit_game)
+                    .service(games::patch_game)
                     .service(games::delete_game)
+                    .service(games::restore_game)
+                    .service(games::batch_delete_games)
                     .service(games::add_game)
+                    .service(games::publish_game)
                     .service(games::get_binary)
+                    .service(games::get_download_url)
+                    .service(games::get_binary_hash)
+                    .service(games::get_game_status)
+                    .service(games::get_flatpak_metadata)
+                    .service(games::get_flatpak_summary)
+                    .service(games::verify_integrity)
                     .service(games::update_binary)
+                    .service(games::get_game_versions)
+                    .service(games::rollback_game)
+                    .service(games::rate_game)
+                    .service(games::get_game_rating)
+                    .service(games::record_play)
                     .service(games::get_banner)
                     .service(games::update_banner)
+                    .service(games::get_icon_sprite)
+                    .service(games::get_icon_atlas)
                     .service(games::get_icon)
+                    .service(games::get_icon_thumb)
                     .service(games::update_icon),
             )
             .service(
                 scope("/tags")
                     .service(tags::get_all_tags)
+                    .service(tags::get_tag_stats)
                     .service(tags::get_tag)
                     .service(tags::edit_tag)
                     .service(tags::delete_tag)
                     .service(tags::add_tag)
+                    .service(tags::bulk_upsert_tags)
                     .service(tags::get_tag_games),
             )
             .service(
                 scope("/users")
+                    .service(users::get_all_authors)
                     .service(users::get_user)
+                    .service(users::get_user_games)
                     .service(users::add_user)
                     .service(users::edit_user),
             )
-            .route("/openapi.json", web::get().to(open_api_spec))
-            .service(SwaggerUi::new("/docs/{_:.*}").url("/api/openapi.json", openapi)),
+            .service(
+                scope("/admin")
+                    .service(admin::get_incomplete_games)
+                    .service(admin::regenerate_all_assets)
+                    .service(admin::backfill_sizes)
+                    .service(admin::get_orphans)
+                    .service(admin::purge_orphans)
+                    .service(admin::regenerate_game_assets),
+            )
+            .service(
+                web::resource("/openapi.json")
+                    .wrap(RequireDocsAuth)
+                    .route(web::get().to(open_api_spec)),
+            )
+            .service(
+                scope("/docs")
+                    .wrap(RequireDocsAuth)
+                    .route("", web::get().to(docs_redirect))
+                    .service(SwaggerUi::new("/{_:.*}").url("/api/openapi.json", openapi)),
+            ),
     );
 }
 
+/// Trimming the trailing slash off `/api/docs/` would break the relative asset
+/// links in the Swagger UI page, so send clients to the index explicitly
+pub async fn docs_redirect() -> HttpResponse {
+    HttpResponse::Found()
+        .insert_header((header::LOCATION, "/api/docs/index.html"))
+        .finish()
+}
+
 pub async fn open_api_spec() -> HttpResponse<BoxBody> {
     let openapi = ApiDoc::openapi();
     HttpResponse::Ok().json(openapi)
 }
 
+/// Where game files are stored, configured so the same build can talk to CSH's
+/// S3, MinIO or AWS:
+///
+/// - `S3_ENDPOINT`: base URL of the S3 API, default `https://s3.csh.rit.edu`
+/// - `S3_REGION`: region requests are signed for, default `AWS_REGION` if set,
+///   otherwise `us-east-1`
+/// - `S3_FORCE_PATH_STYLE`: address buckets as `{endpoint}/{bucket}/{key}`
+///   rather than `{bucket}.{endpoint}/{key}`, default `true`. This SDK only
+///   speaks path style, so startup rejects `false` rather than ignoring it.
+///
+/// The client doesn't retry failed requests itself, since
+/// [`retry_s3`](crate::games::s3::retry_s3) does.
+fn s3_config(shared_config: &aws_config::SdkConfig) -> s3::Config {
+    let endpoint = env::var("S3_ENDPOINT").unwrap_or(DEFAULT_S3_ENDPOINT.to_string());
+    let region = env::var("S3_REGION")
+        .ok()
+        .map(Region::new)
+        .or_else(|| shared_config.region().cloned())
+        .unwrap_or(Region::from_static(DEFAULT_S3_REGION));
+    s3::config::Builder::from(shared_config)
+        .endpoint_resolver(Endpoint::immutable(endpoint.parse().unwrap()))
+        .region(region)
+        .retry_config(RetryConfig::disabled())
+        .build()
+}
+
 pub async fn get_app_data() -> Data<AppState> {
     let shared_config = aws_config::load_from_env().await;
-
-    // Create an S3 config from the shared config and override the endpoint resolver.
-    let s3_config = s3::config::Builder::from(&shared_config)
-        .endpoint_resolver(Endpoint::immutable(
-            env::var("S3_ENDPOINT")
-                .unwrap_or("https://s3.csh.rit.edu".to_string())
-                .parse()
-                .unwrap(),
-        ))
-        //.endpoint_resolver(s3::Endpoint::immutable("https://s3.csh.rit.edu".parse().unwrap()))
-        .build();
-    let s3_conn = s3::Client::from_conf(s3_config);
+    let s3_conn = s3::Client::from_conf(s3_config(&shared_config));
 
     let pool = PgPoolOptions::new()
         .connect(&env::var("SQL_URI").unwrap())
@@ -130,5 +307,10 @@ pub async fn get_app_data() -> Data<AppState> {
     Data::new(AppState {
         db: pool,
         s3: s3_conn.clone(),
+        games_bucket: env::var("S3_GAMES_BUCKET").unwrap(),
+        events: EventProducer::from_env().await,
+        icon_sprite: IconSpriteCache::default(),
+        flatpak_summaries: FlatpakSummaryCache::default(),
+        rate_limiter: RateLimiter::from_env(),
     })
 }
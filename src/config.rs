@@ -0,0 +1,142 @@
+use crate::security::parse_proxies;
+use std::{env, fmt::Display, str::FromStr};
+
+/// Environment variables the API can't serve requests without
+pub const REQUIRED_ENV_VARS: &[&str] = &[
+    "SQL_URI",
+    "S3_GAMES_BUCKET",
+    "AWS_ACCESS_KEY_ID",
+    "AWS_SECRET_ACCESS_KEY",
+    "FRONTEND_API_KEY",
+];
+
+/// Whether a setting's value parses as the type it's read as, and is one the
+/// API supports
+type Parses = fn(&str) -> bool;
+
+/// Optional settings that must parse when they're set, with what they must be
+const TYPED_ENV_VARS: &[(&str, &str, Parses)] = &[
+    ("MAX_IMAGE_PIXELS", "a whole number", parses::<u64>),
+    ("MAX_GAME_BYTES", "a whole number", parses::<usize>),
+    ("MAX_IMAGE_BYTES", "a whole number", parses::<usize>),
+    ("TRANSCODE_BANNERS", "true or false", parses::<bool>),
+    (
+        "DOWNLOAD_URL_EXPIRY_SECONDS",
+        "a whole number",
+        parses::<u64>,
+    ),
+    ("EXPORT_TIMEOUT_SECONDS", "a whole number", parses::<u64>),
+    ("SHUTDOWN_TIMEOUT_SECONDS", "a whole number", parses::<u64>),
+    (
+        "RATE_LIMIT_READS_PER_MINUTE",
+        "a whole number",
+        parses::<u32>,
+    ),
+    (
+        "RATE_LIMIT_WRITES_PER_MINUTE",
+        "a whole number",
+        parses::<u32>,
+    ),
+    ("CORS_PERMISSIVE", "true or false", parses::<bool>),
+    (
+        "TRUSTED_PROXIES",
+        "a comma separated list of IP addresses",
+        |value| parse_proxies(value).is_some(),
+    ),
+    // The S3 SDK only addresses buckets by path
+    (
+        "S3_FORCE_PATH_STYLE",
+        "true, as virtual-hosted bucket addressing isn't supported",
+        |value| value == "true",
+    ),
+];
+
+fn parses<T: FromStr>(value: &str) -> bool {
+    value.parse::<T>().is_ok()
+}
+
+/// Long enough for the largest flatpak to finish uploading over a slow link
+const DEFAULT_SHUTDOWN_TIMEOUT_SECONDS: u64 = 120;
+
+/// How long the server waits, after SIGTERM or SIGINT, for requests in flight
+/// to finish before dropping them, from `SHUTDOWN_TIMEOUT_SECONDS`. No new
+/// connections are accepted meanwhile.
+pub fn shutdown_timeout() -> u64 {
+    env_or("SHUTDOWN_TIMEOUT_SECONDS", DEFAULT_SHUTDOWN_TIMEOUT_SECONDS)
+}
+
+/// Reads one of the optional settings, or `default` when it's unset. Startup
+/// checks them all with [`invalid_env_vars`] first, so this can't fail once
+/// the server is running.
+pub fn env_or<T: FromStr>(var: &str, default: T) -> T
+where
+    T::Err: Display,
+{
+    match env::var(var) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|e| panic!("{var} is invalid: {e}")),
+        Err(_) => default,
+    }
+}
+
+/// Required variables that are unset or empty, so startup can report all of
+/// them at once instead of panicking on the first one a request touches
+pub fn missing_env_vars() -> Vec<&'static str> {
+    missing_vars(|var| env::var(var).ok())
+}
+
+fn missing_vars(lookup: impl Fn(&str) -> Option<String>) -> Vec<&'static str> {
+    REQUIRED_ENV_VARS
+        .iter()
+        .copied()
+        .filter(|var| lookup(var).is_none_or(|value| value.trim().is_empty()))
+        .collect()
+}
+
+/// Optional settings that are set to something they can't be parsed as,
+/// each described for the startup error
+pub fn invalid_env_vars() -> Vec<String> {
+    invalid_vars(|var| env::var(var).ok())
+}
+
+fn invalid_vars(lookup: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    TYPED_ENV_VARS
+        .iter()
+        .filter_map(|(var, expected, parses)| {
+            let value = lookup(var)?;
+            (!parses(&value)).then(|| format!("{var} must be {expected}, not {value:?}"))
+        })
+        .collect()
+}
+
+#[test]
+fn missing_vars_reports_unset_and_empty_values() {
+    let missing = missing_vars(|var| match var {
+        "SQL_URI" => Some("postgres://localhost".to_string()),
+        "FRONTEND_API_KEY" => Some(" ".to_string()),
+        _ => None,
+    });
+    assert!(!missing.contains(&"SQL_URI"));
+    assert!(missing.contains(&"FRONTEND_API_KEY"));
+    assert!(missing.contains(&"S3_GAMES_BUCKET"));
+}
+
+#[test]
+fn invalid_vars_reports_unparseable_settings() {
+    let invalid = invalid_vars(|var| match var {
+        "MAX_GAME_BYTES" => Some("1GiB".to_string()),
+        "TRANSCODE_BANNERS" => Some("yes".to_string()),
+        "EXPORT_TIMEOUT_SECONDS" => Some("600".to_string()),
+        "S3_FORCE_PATH_STYLE" => Some("false".to_string()),
+        _ => None,
+    });
+    assert_eq!(
+        invalid,
+        vec![
+            "MAX_GAME_BYTES must be a whole number, not \"1GiB\"",
+            "TRANSCODE_BANNERS must be true or false, not \"yes\"",
+            "S3_FORCE_PATH_STYLE must be true, as virtual-hosted bucket addressing isn't supported, not \"false\"",
+        ]
+    );
+}
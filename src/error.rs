@@ -0,0 +1,294 @@
+use actix_web::{
+    http::{header, StatusCode},
+    HttpResponse, ResponseError,
+};
+use glib::variant::FromVariant;
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt, time::Duration};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    games::{
+        flatpak::{FlatpakDecodingError, FlatpakMetadataError},
+        routes::GameError,
+    },
+    validation::ValidationErrors,
+};
+
+/// Seconds clients are asked to wait before retrying when the API is overloaded
+const RETRY_AFTER_SECONDS: u32 = 5;
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Every way a request can fail, with the status code each maps to. Handlers
+/// return this (usually via `?`) rather than building error responses by hand.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request itself was malformed or failed validation (400)
+    BadRequest(String),
+    /// Field-level validation failures, reported together as JSON (400)
+    Validation(ValidationErrors),
+    /// Missing or incorrect credentials (401)
+    Unauthorized(String),
+    /// The caller isn't allowed to do this (403)
+    Forbidden(String),
+    /// The requested resource doesn't exist (404)
+    NotFound(String),
+    /// The request clashes with another resource (409)
+    Conflict(String),
+    /// An upload is larger than we accept (413)
+    PayloadTooLarge(String),
+    /// An upload isn't of a type we accept (415)
+    UnsupportedMediaType(String),
+    /// A requested byte range lies outside the file (416)
+    RangeNotSatisfiable(String),
+    /// The client is over its rate limit, and may retry after this long (429)
+    TooManyRequests(Duration),
+    Database(sqlx::Error),
+    /// Any other unexpected failure (S3, filesystem, ...)
+    Internal(Box<dyn Error>),
+}
+
+/// The JSON body of every error response other than field validation
+/// failures, which are reported as [`ValidationErrors`]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct ErrorBody {
+    /// A stable code for the kind of error, for clients to match on
+    #[schema(example = "not_found")]
+    pub error: String,
+    /// A human readable explanation
+    #[schema(example = "Game ID Does Not Exist")]
+    pub detail: String,
+}
+
+impl ErrorBody {
+    pub fn new(error: &str, detail: impl Into<String>) -> Self {
+        ErrorBody {
+            error: error.to_string(),
+            detail: detail.into(),
+        }
+    }
+}
+
+impl ApiError {
+    pub fn internal(error: impl Into<Box<dyn Error>>) -> Self {
+        Self::Internal(error.into())
+    }
+
+    pub fn game_not_found() -> Self {
+        Self::NotFound("Game ID Does Not Exist".to_string())
+    }
+
+    pub fn flatpak_not_found() -> Self {
+        Self::NotFound("Game has no flatpak uploaded".to_string())
+    }
+
+    /// The `error` code reported in [`ErrorBody`]
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::BadRequest(_) => "bad_request",
+            Self::Validation(_) => "validation",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::Forbidden(_) => "forbidden",
+            Self::NotFound(_) => "not_found",
+            Self::Conflict(_) => "conflict",
+            Self::PayloadTooLarge(_) => "payload_too_large",
+            Self::UnsupportedMediaType(_) => "unsupported_media_type",
+            Self::RangeNotSatisfiable(_) => "range_not_satisfiable",
+            Self::TooManyRequests(_) => "too_many_requests",
+            Self::Database(sqlx::Error::PoolTimedOut) => "unavailable",
+            Self::Database(_) | Self::Internal(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadRequest(message)
+            | Self::Unauthorized(message)
+            | Self::Forbidden(message)
+            | Self::NotFound(message)
+            | Self::Conflict(message)
+            | Self::PayloadTooLarge(message)
+            | Self::UnsupportedMediaType(message)
+            | Self::RangeNotSatisfiable(message) => write!(f, "{message}"),
+            Self::Validation(errors) => write!(
+                f,
+                "Invalid fields: {}",
+                errors
+                    .errors
+                    .iter()
+                    .map(|error| error.field.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::TooManyRequests(retry_after) => write!(
+                f,
+                "Too many requests, try again in {} seconds",
+                retry_seconds(retry_after)
+            ),
+            Self::Database(sqlx::Error::PoolTimedOut) => {
+                write!(f, "Database is busy, try again shortly")
+            }
+            Self::Database(e) => write!(f, "{e}"),
+            Self::Internal(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for ApiError {}
+
+/// `Retry-After` only takes whole seconds, and rounding down would have
+/// clients retry too early
+fn retry_seconds(retry_after: &Duration) -> u64 {
+    retry_after.as_secs_f64().ceil().max(1.0) as u64
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(error: sqlx::Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+impl From<Box<dyn Error>> for ApiError {
+    fn from(error: Box<dyn Error>) -> Self {
+        Self::Internal(error)
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        Self::Validation(errors)
+    }
+}
+
+impl From<GameError> for ApiError {
+    fn from(error: GameError) -> Self {
+        Self::BadRequest(error.to_string())
+    }
+}
+
+/// A flatpak that can't be decoded was uploaded broken, unless we couldn't
+/// read our own copy of it
+impl From<FlatpakDecodingError> for ApiError {
+    fn from(error: FlatpakDecodingError) -> Self {
+        match error {
+            FlatpakDecodingError::ReadFailed(_) => Self::internal(error),
+            error => Self::BadRequest(error.to_string()),
+        }
+    }
+}
+
+impl<T: FromVariant + fmt::Debug + 'static> From<FlatpakMetadataError<T>> for ApiError {
+    fn from(error: FlatpakMetadataError<T>) -> Self {
+        Self::BadRequest(error.to_string())
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::BadRequest(_) | Self::Validation(_) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::RangeNotSatisfiable(_) => StatusCode::RANGE_NOT_SATISFIABLE,
+            Self::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::Database(sqlx::Error::PoolTimedOut) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Database(_) | Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Internal errors can mention tables, buckets or connection strings, so
+    /// clients only get a request id to quote while the detail is logged
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        match self {
+            Self::Validation(errors) => return response.json(errors),
+            Self::TooManyRequests(retry_after) => {
+                return response
+                    .insert_header((header::RETRY_AFTER, retry_seconds(retry_after)))
+                    .json(ErrorBody::new(self.code(), self.to_string()));
+            }
+            Self::Database(sqlx::Error::PoolTimedOut) => {
+                log::warn!("Timed out acquiring a database connection");
+                return response
+                    .insert_header((header::RETRY_AFTER, RETRY_AFTER_SECONDS))
+                    .json(ErrorBody::new(self.code(), self.to_string()));
+            }
+            Self::Database(_) | Self::Internal(_) => {}
+            _ => return response.json(ErrorBody::new(self.code(), self.to_string())),
+        }
+        let request_id = Uuid::new_v4();
+        log::error!("Internal error (request id {request_id}): {self}");
+        response
+            .insert_header((REQUEST_ID_HEADER, request_id.to_string()))
+            .json(ErrorBody::new(
+                self.code(),
+                format!("Internal error (request id {request_id})"),
+            ))
+    }
+}
+
+#[test]
+fn pool_timeout_is_service_unavailable() {
+    let response = ApiError::from(sqlx::Error::PoolTimedOut).error_response();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "5");
+    let response = ApiError::from(sqlx::Error::RowNotFound).error_response();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[test]
+fn internal_errors_hide_their_detail() {
+    let response =
+        ApiError::internal("connection to postgres://devcade:secret@db failed").error_response();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let request_id = response
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = actix_web::body::to_bytes(response.into_body());
+    let body = futures::executor::block_on(body).unwrap();
+    assert_eq!(
+        serde_json::from_slice::<ErrorBody>(&body).unwrap(),
+        ErrorBody::new(
+            "internal",
+            format!("Internal error (request id {request_id})")
+        )
+    );
+}
+
+#[test]
+fn client_errors_keep_their_message() {
+    let response =
+        ApiError::UnsupportedMediaType("Game provided is not a Flatpak!".into()).error_response();
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert!(response.headers().get(REQUEST_ID_HEADER).is_none());
+    let body = actix_web::body::to_bytes(response.into_body());
+    let body = futures::executor::block_on(body).unwrap();
+    assert_eq!(
+        serde_json::from_slice::<ErrorBody>(&body).unwrap(),
+        ErrorBody::new("unsupported_media_type", "Game provided is not a Flatpak!")
+    );
+    let response = ApiError::from(FlatpakDecodingError::BadChecksumLength {
+        expected: 32,
+        actual: 0,
+    })
+    .error_response();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn too_many_requests_says_when_to_retry() {
+    let response = ApiError::TooManyRequests(Duration::from_millis(2500)).error_response();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "3");
+}
@@ -0,0 +1,338 @@
+use crate::models::Game;
+use actix_web::rt::time::{sleep, timeout};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use hyper::{client::HttpConnector, Body, Client, Request};
+use hyper_rustls::HttpsConnector;
+use serde::Serialize;
+use sha2::Sha256;
+use std::{env, sync::Arc, time::Duration};
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+const DEFAULT_NATS_SUBJECT: &str = "devcade.games";
+/// How many times a webhook is tried before its event is dropped
+const WEBHOOK_ATTEMPTS: u32 = 3;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Doubled after every failed attempt
+const WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(1);
+const WEBHOOK_SIGNATURE_HEADER: &str = "x-devcade-signature";
+/// How many events a live update subscriber can fall behind by before it
+/// starts missing them
+const LIVE_EVENT_CAPACITY: usize = 64;
+
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GameEventKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl GameEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Updated => "updated",
+            Self::Deleted => "deleted",
+        }
+    }
+}
+
+/// Payload published whenever a game is created, updated or deleted
+#[derive(Serialize, ToSchema, Clone)]
+pub struct GameEvent {
+    pub event: GameEventKind,
+    pub game_id: String,
+    /// The game as it is after the change, absent for deletions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game: Option<Game>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl GameEvent {
+    pub fn created(game: &Game) -> Self {
+        Self::new(GameEventKind::Created, &game.id, Some(game.clone()))
+    }
+    pub fn updated(game: &Game) -> Self {
+        Self::new(GameEventKind::Updated, &game.id, Some(game.clone()))
+    }
+    pub fn deleted(game_id: &str) -> Self {
+        Self::new(GameEventKind::Deleted, game_id, None)
+    }
+    fn new(event: GameEventKind, game_id: &str, game: Option<Game>) -> Self {
+        GameEvent {
+            event,
+            game_id: game_id.to_string(),
+            game,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// The smaller payload POSTed to webhooks, which only need to know which game
+/// changed and not everything about it
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookPayload {
+    pub event: GameEventKind,
+    pub game_id: String,
+    /// Absent for deletions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl From<&GameEvent> for WebhookPayload {
+    fn from(event: &GameEvent) -> Self {
+        WebhookPayload {
+            event: event.event,
+            game_id: event.game_id.clone(),
+            name: event.game.as_ref().map(|game| game.name.clone()),
+            hash: event.game.as_ref().and_then(|game| game.hash.clone()),
+            timestamp: event.timestamp,
+        }
+    }
+}
+
+/// POSTs every event to each of `WEBHOOK_URLS` (comma separated). With
+/// `WEBHOOK_SECRET` set, the body's hex HMAC-SHA256 under it is sent in the
+/// `x-devcade-signature` header as `sha256=<hmac>`, for receivers to check.
+pub struct Webhooks {
+    urls: Vec<String>,
+    secret: Option<String>,
+    client: Client<HttpsConnector<HttpConnector>>,
+}
+
+impl Webhooks {
+    pub fn new(urls: Vec<String>, secret: Option<String>) -> Self {
+        Webhooks {
+            urls,
+            secret,
+            client: Client::builder().build(HttpsConnector::with_native_roots()),
+        }
+    }
+
+    fn from_env() -> Option<Self> {
+        let urls: Vec<String> = env::var("WEBHOOK_URLS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(str::to_string)
+            .collect();
+        let secret = env::var("WEBHOOK_SECRET")
+            .ok()
+            .filter(|secret| !secret.is_empty());
+        (!urls.is_empty()).then(|| Self::new(urls, secret))
+    }
+
+    fn sign(&self, body: &[u8]) -> Option<String> {
+        let secret = self.secret.as_ref()?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        Some(format!(
+            "sha256={}",
+            hex::encode(mac.finalize().into_bytes())
+        ))
+    }
+
+    /// Delivers to every webhook at once, retrying each on its own
+    pub async fn deliver(&self, payload: &WebhookPayload) {
+        let body = match serde_json::to_vec(payload) {
+            Ok(body) => body,
+            Err(e) => {
+                log::error!("Couldn't serialize webhook payload: {e}");
+                return;
+            }
+        };
+        let signature = self.sign(&body);
+        futures::future::join_all(
+            self.urls
+                .iter()
+                .map(|url| self.deliver_to(url, &body, signature.as_deref())),
+        )
+        .await;
+    }
+
+    async fn deliver_to(&self, url: &str, body: &[u8], signature: Option<&str>) {
+        let mut delay = WEBHOOK_RETRY_DELAY;
+        for attempt in 1..=WEBHOOK_ATTEMPTS {
+            let mut request = Request::post(url).header("content-type", "application/json");
+            if let Some(signature) = signature {
+                request = request.header(WEBHOOK_SIGNATURE_HEADER, signature);
+            }
+            let request = match request.body(Body::from(body.to_vec())) {
+                Ok(request) => request,
+                Err(e) => {
+                    log::error!("Webhook URL {url} is invalid: {e}");
+                    return;
+                }
+            };
+            let error = match timeout(WEBHOOK_TIMEOUT, self.client.request(request)).await {
+                Ok(Ok(response)) if response.status().is_success() => return,
+                Ok(Ok(response)) => format!("responded {}", response.status()),
+                Ok(Err(e)) => e.to_string(),
+                Err(_) => "timed out".to_string(),
+            };
+            if attempt == WEBHOOK_ATTEMPTS {
+                log::error!("Giving up on webhook {url} after {attempt} attempts: {error}");
+            } else {
+                log::warn!("Webhook {url} failed ({error}), retrying in {delay:?}");
+                sleep(delay).await;
+                delay *= 2;
+            }
+        }
+    }
+}
+
+/// Publishes [`GameEvent`]s to NATS when `NATS_URL` is set (on the subject in
+/// `NATS_SUBJECT`), to any [`Webhooks`], and to live update subscribers
+#[derive(Clone)]
+pub struct EventProducer {
+    nats: Option<(async_nats::Client, String)>,
+    webhooks: Option<Arc<Webhooks>>,
+    live: broadcast::Sender<GameEvent>,
+}
+
+impl Default for EventProducer {
+    fn default() -> Self {
+        EventProducer {
+            nats: None,
+            webhooks: None,
+            live: broadcast::channel(LIVE_EVENT_CAPACITY).0,
+        }
+    }
+}
+
+impl EventProducer {
+    pub async fn from_env() -> Self {
+        let producer = EventProducer {
+            webhooks: Webhooks::from_env().map(Arc::new),
+            ..Self::default()
+        };
+        let Ok(url) = env::var("NATS_URL") else {
+            return producer;
+        };
+        let subject = env::var("NATS_SUBJECT").unwrap_or(DEFAULT_NATS_SUBJECT.to_string());
+        match async_nats::connect(&url).await {
+            Ok(client) => EventProducer {
+                nats: Some((client, subject)),
+                ..producer
+            },
+            Err(e) => {
+                log::error!("Couldn't connect to NATS at {url}: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    /// Every event published from now on, until the receiver is dropped
+    pub fn subscribe(&self) -> broadcast::Receiver<GameEvent> {
+        self.live.subscribe()
+    }
+
+    /// Failures are logged rather than returned, since the change the event
+    /// describes has already been made. Webhooks are delivered in the
+    /// background, so retrying a slow receiver doesn't hold up the response.
+    pub async fn publish(&self, event: GameEvent) {
+        // Only fails when nobody is subscribed
+        let _ = self.live.send(event.clone());
+        if let Some(webhooks) = &self.webhooks {
+            let webhooks = webhooks.clone();
+            let payload = WebhookPayload::from(&event);
+            actix_web::rt::spawn(async move { webhooks.deliver(&payload).await });
+        }
+        let Some((client, subject)) = &self.nats else {
+            return;
+        };
+        let payload = match serde_json::to_vec(&event) {
+            Ok(payload) => payload,
+            Err(e) => {
+                log::error!("Couldn't serialize game event: {e}");
+                return;
+            }
+        };
+        if let Err(e) = client.publish(subject.clone(), payload.into()).await {
+            log::error!("Couldn't publish game event to {subject}: {e}");
+        }
+    }
+}
+
+#[test]
+fn deleted_event_omits_game() {
+    let event = serde_json::to_value(GameEvent::deleted("A")).unwrap();
+    assert_eq!(event["event"], "deleted");
+    assert_eq!(event["game_id"], "A");
+    assert!(event.get("game").is_none());
+}
+
+#[actix_web::test]
+async fn webhooks_are_signed_and_retried() {
+    use actix_web::{web, App, HttpRequest, HttpResponse};
+    use std::sync::Mutex;
+
+    /// The signature and body of every delivery
+    type Received = web::Data<Mutex<Vec<(Option<String>, web::Bytes)>>>;
+
+    // Fails the first delivery, to check it's retried
+    let received = Received::default();
+    let receiver = received.clone();
+    let srv = actix_test::start(move || {
+        App::new().app_data(receiver.clone()).route(
+            "/hook",
+            web::post().to(
+                |req: HttpRequest, body: web::Bytes, received: Received| async move {
+                    let mut received = received.lock().unwrap();
+                    let signature = req
+                        .headers()
+                        .get(WEBHOOK_SIGNATURE_HEADER)
+                        .map(|value| value.to_str().unwrap().to_string());
+                    received.push((signature, body));
+                    if received.len() == 1 {
+                        HttpResponse::ServiceUnavailable().finish()
+                    } else {
+                        HttpResponse::Ok().finish()
+                    }
+                },
+            ),
+        )
+    });
+    let webhooks = Webhooks::new(vec![srv.url("/hook")], Some("secret".to_string()));
+    let payload = WebhookPayload::from(&GameEvent::deleted("A"));
+    webhooks.deliver(&payload).await;
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    let (signature, body) = &received[1];
+    assert_eq!(signature.as_deref(), webhooks.sign(body).as_deref());
+    let delivered: serde_json::Value = serde_json::from_slice(body).unwrap();
+    assert_eq!(delivered["event"], "deleted");
+    assert_eq!(delivered["game_id"], "A");
+    assert!(delivered.get("name").is_none());
+}
+
+#[actix_web::test]
+async fn subscribers_receive_published_events() {
+    let producer = EventProducer::default();
+    // Publishing with nobody listening is fine
+    producer.publish(GameEvent::deleted("A")).await;
+    let mut events = producer.subscribe();
+    producer.publish(GameEvent::deleted("B")).await;
+    let event = events.recv().await.unwrap();
+    assert_eq!(event.event, GameEventKind::Deleted);
+    assert_eq!(event.game_id, "B");
+}
+
+#[test]
+fn webhook_signature_is_an_hmac_of_the_body() {
+    let webhooks = Webhooks::new(vec![], Some("key".to_string()));
+    assert_eq!(
+        webhooks
+            .sign(b"The quick brown fox jumps over the lazy dog")
+            .unwrap(),
+        "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+    );
+    assert_eq!(Webhooks::new(vec![], None).sign(b"body"), None);
+}
@@ -0,0 +1,66 @@
+use actix_web::web::ServiceConfig;
+use lazy_static::lazy_static;
+use std::{collections::HashSet, env};
+
+lazy_static! {
+    /// Features enabled for this deployment, from a comma separated `FEATURES`
+    /// env var (e.g. `FEATURES=events,webp`)
+    static ref ENABLED_FEATURES: HashSet<String> =
+        parse_features(&env::var("FEATURES").unwrap_or_default());
+}
+
+fn parse_features(features: &str) -> HashSet<String> {
+    features
+        .split(',')
+        .map(|feature| feature.trim().to_lowercase())
+        .filter(|feature| !feature.is_empty())
+        .collect()
+}
+
+pub fn is_enabled(feature: &str) -> bool {
+    ENABLED_FEATURES.contains(&feature.to_lowercase())
+}
+
+/// Every enabled feature, sorted, for logging at startup
+pub fn enabled_features() -> Vec<&'static str> {
+    let mut features: Vec<&str> = ENABLED_FEATURES.iter().map(String::as_str).collect();
+    features.sort_unstable();
+    features
+}
+
+/// Registers `routes` only if `feature` is enabled, so the routes of a
+/// disabled feature 404 like any other unknown path. Intended for use with
+/// `Scope::configure`; middleware can be gated the same way with
+/// `middleware::Condition::new(is_enabled(feature), ..)`
+pub fn configure_feature<F>(feature: &'static str, routes: F) -> impl FnOnce(&mut ServiceConfig)
+where
+    F: FnOnce(&mut ServiceConfig),
+{
+    move |cfg| {
+        if is_enabled(feature) {
+            routes(cfg);
+        }
+    }
+}
+
+#[test]
+fn parse_features_ignores_whitespace_case_and_empty_entries() {
+    let features = parse_features(" Events, video,,");
+    assert_eq!(features.len(), 2);
+    assert!(features.contains("events"));
+    assert!(features.contains("video"));
+    assert!(parse_features("").is_empty());
+}
+
+#[actix_web::test]
+async fn configure_feature_leaves_out_disabled_routes() {
+    use actix_web::{test, web, App, HttpResponse};
+    let app = test::init_service(
+        App::new().configure(configure_feature("never-enabled", |cfg| {
+            cfg.route("/hidden", web::get().to(HttpResponse::Ok));
+        })),
+    )
+    .await;
+    let req = test::TestRequest::get().uri("/hidden").to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 404);
+}
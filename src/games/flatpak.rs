@@ -1,16 +1,25 @@
+use chrono::{DateTime, TimeZone, Utc};
 use glib::variant::{FromVariant, Variant};
 use glib::VariantTy;
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
 use std::any::TypeId;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fmt::{self, Debug, Display};
+use std::io::{self, Read, Seek, SeekFrom};
 use std::marker::PhantomData;
+use std::sync::Mutex;
+use utoipa::ToSchema;
 
 pub struct FlatpakFile(Variant);
 
 lazy_static! {
     static ref FLATPAK_FILE_VARIANT: &'static VariantTy =
         VariantTy::new("(a{sv}tayay(a{sv}aya(say)sstayay)aya(uayttay)a(yaytt))").unwrap();
+    /// The OSTree commit embedded in a bundle
+    static ref COMMIT_VARIANT: &'static VariantTy =
+        VariantTy::new("(a{sv}aya(say)sstayay)").unwrap();
 }
 
 #[test]
@@ -41,59 +50,354 @@ impl<T: FromVariant + Debug + 'static> Display for FlatpakMetadataError<T> {
     }
 }
 
+/// Unboxes a metadata value, since `a{sv}` values are wrapped in a variant
+fn unbox(value: Variant) -> Variant {
+    if value.type_() != VariantTy::VARIANT {
+        return value;
+    }
+    match value.as_variant() {
+        Some(value) => value,
+        None => value,
+    }
+}
+
+fn find_metadata_value(dict_array: &Variant, key: &str) -> Option<Variant> {
+    (0..dict_array.n_children()).find_map(|index| {
+        let dict_entry = dict_array.child_value(index);
+        let candidate_key = String::from_variant(&dict_entry.child_value(0))?;
+        (candidate_key == key).then(|| unbox(dict_entry.child_value(1)))
+    })
+}
+
+fn lookup_metadata_key<T: FromVariant + Debug>(
+    dict_array: &Variant,
+    key: &str,
+) -> Result<T, FlatpakMetadataError<T>> {
+    let value = find_metadata_value(dict_array, key)
+        .ok_or_else(|| FlatpakMetadataError::MissingKey(key.to_string()))?;
+    T::from_variant(&value)
+        .ok_or_else(|| FlatpakMetadataError::IncorrectFormat(key.to_string(), PhantomData {}))
+}
+
+/// Like [`lookup_metadata_key`] for array values, whose elements may each be
+/// boxed in a variant too (`av` rather than `as`)
+fn lookup_metadata_list<T: FromVariant + Debug>(
+    dict_array: &Variant,
+    key: &str,
+) -> Result<Vec<T>, FlatpakMetadataError<T>> {
+    let incorrect_format =
+        || FlatpakMetadataError::IncorrectFormat(key.to_string(), PhantomData {});
+    let value = find_metadata_value(dict_array, key)
+        .ok_or_else(|| FlatpakMetadataError::MissingKey(key.to_string()))?;
+    if !value.type_().is_array() {
+        return Err(incorrect_format());
+    }
+    (0..value.n_children())
+        .map(|index| T::from_variant(&unbox(value.child_value(index))).ok_or_else(incorrect_format))
+        .collect()
+}
+
+fn metadata_entries(dict_array: &Variant) -> BTreeMap<String, String> {
+    (0..dict_array.n_children())
+        .filter_map(|index| {
+            let dict_entry = dict_array.child_value(index);
+            let key = String::from_variant(&dict_entry.child_value(0))?;
+            let value = unbox(dict_entry.child_value(1));
+            let value = match value.str() {
+                Some(value) => value.to_string(),
+                None => value.print(false).to_string(),
+            };
+            Some((key, value))
+        })
+        .collect()
+}
+
+fn validate_metadata(metadata: &Variant) -> Result<(), FlatpakDecodingError> {
+    if !metadata.is_container() {
+        return Err(FlatpakDecodingError::MetadataNotContainer);
+    }
+    for index in 0..metadata.n_children() {
+        let child = metadata.child_value(index);
+        if !child.is_container() || child.n_children() != 2 {
+            return Err(FlatpakDecodingError::MetadataChildNotContainer);
+        }
+    }
+    Ok(())
+}
+
+/// Bundles are checksummed with SHA-256
+const CHECKSUM_LENGTH: usize = 32;
+
+fn validate_checksum(checksum: &Variant) -> Result<(), FlatpakDecodingError> {
+    let actual = if checksum.is_container() {
+        checksum.n_children()
+    } else {
+        0
+    };
+    if actual != CHECKSUM_LENGTH {
+        return Err(FlatpakDecodingError::BadChecksumLength {
+            expected: CHECKSUM_LENGTH,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+fn encode_checksum(checksum: &Variant) -> String {
+    hex::encode(<Vec<u8> as FromVariant>::from_variant(checksum).unwrap())
+}
+
 impl FlatpakFile {
     pub fn get_metadata_key<T: FromVariant + Debug>(
         &self,
         key: &str,
     ) -> Result<T, FlatpakMetadataError<T>> {
-        let dict_array = self.0.child_value(0);
-        for index in 0..dict_array.n_children() {
-            let dict_entry = dict_array.child_value(index);
-            if let Some(candidate_key) = String::from_variant(&dict_entry.child_value(0)) {
-                if candidate_key == key {
-                    let value = dict_entry.child_value(1);
-                    let value = match value.as_variant() {
-                        Some(value) => value,
-                        None => value,
-                    };
-                    return T::from_variant(&value).ok_or_else(|| {
-                        FlatpakMetadataError::IncorrectFormat(key.to_string(), PhantomData {})
-                    });
-                };
-            }
-        }
-        Err(FlatpakMetadataError::MissingKey(key.to_string()))
+        lookup_metadata_key(&self.0.child_value(0), key)
     }
+    /// Reads an array-valued metadata key, such as a list of permissions
+    pub fn get_metadata_key_list<T: FromVariant + Debug>(
+        &self,
+        key: &str,
+    ) -> Result<Vec<T>, FlatpakMetadataError<T>> {
+        lookup_metadata_list(&self.0.child_value(0), key)
+    }
+    /// Every metadata entry, with non-string values rendered in GVariant text format
+    pub fn metadata(&self) -> BTreeMap<String, String> {
+        metadata_entries(&self.0.child_value(0))
+    }
+    /// GLib reads members of malformed data as empty or zeroed rather than
+    /// failing, so the framing is checked up front the same way
+    /// [`Self::load_header_only`] does to reject truncated or foreign files.
     pub fn load<T: AsRef<[u8]>>(bytes: T) -> Result<Self, FlatpakDecodingError> {
+        Self::load_header_only(io::Cursor::new(bytes.as_ref()))?;
         let variant = Variant::from_data_with_type(bytes, &FLATPAK_FILE_VARIANT);
-        let metadata = variant.child_value(0);
-        if !metadata.is_container() {
-            return Err(FlatpakDecodingError::MetadataNotContainer);
+        validate_metadata(&variant.child_value(0))?;
+        validate_checksum(&variant.child_value(3))?;
+        Ok(FlatpakFile(variant))
+    }
+
+    /// Reads only the metadata, checksum and commit of a bundle, without
+    /// loading the (potentially huge) delta parts that follow them.
+    ///
+    /// The bundle is a GVariant tuple, whose framing offsets are stored at the
+    /// very end of the data, so this reads those first and then seeks back to
+    /// read the leading members.
+    pub fn load_header_only<R: Read + Seek>(
+        mut reader: R,
+    ) -> Result<FlatpakHeader, FlatpakDecodingError> {
+        // The metadata, from-checksum, to-checksum and commit members are each
+        // framed by an offset; the timestamp between them is fixed size.
+        const FRAMED_MEMBERS: u64 = 4;
+        let size = reader.seek(SeekFrom::End(0))?;
+        let offset_size = framing_offset_size(size);
+        if size < FRAMED_MEMBERS * offset_size {
+            return Err(FlatpakDecodingError::IncorrectFormat);
         }
-        for index in 0..metadata.n_children() {
-            let child = metadata.child_value(index);
-            if !child.is_container() || child.n_children() != 2 {
-                return Err(FlatpakDecodingError::MetadataChildNotContainer);
-            }
+        let mut offsets = vec![0u8; (FRAMED_MEMBERS * offset_size) as usize];
+        reader.seek(SeekFrom::End(-(offsets.len() as i64)))?;
+        reader.read_exact(&mut offsets)?;
+        // Offsets are stored in reverse, so the last one frames the first member
+        let read_offset = |index: usize| {
+            let end = offsets.len() - index * offset_size as usize;
+            offsets[end - offset_size as usize..end]
+                .iter()
+                .rev()
+                .fold(0u64, |offset, byte| (offset << 8) | *byte as u64)
+        };
+        let metadata_end = read_offset(0);
+        let checksum_start = read_offset(1);
+        let checksum_end = read_offset(2);
+        let commit_end = read_offset(3);
+        // The aligned 8-byte timestamp (and the from-checksum) come between
+        // the metadata and the checksum
+        let timestamp_end = metadata_end.next_multiple_of(8) + 8;
+        // The commit holds a timestamp too, so it's aligned the same way
+        let commit_start = checksum_end.next_multiple_of(8);
+        if timestamp_end > checksum_start
+            || checksum_start > checksum_end
+            || commit_start > commit_end
+            || commit_end > size - offsets.len() as u64
+        {
+            return Err(FlatpakDecodingError::IncorrectFormat);
         }
-        let checksum = variant.child_value(3);
-        if !checksum.is_container() || checksum.n_children() != 32 {
-            return Err(FlatpakDecodingError::BadChecksumLength);
+
+        let mut header = vec![0u8; commit_end as usize];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut header)?;
+        let commit = header.split_off(commit_start as usize);
+        header.truncate(checksum_end as usize);
+        let checksum = header.split_off(checksum_start as usize);
+        header.truncate(metadata_end as usize);
+        let metadata = Variant::from_data_with_type(header, VariantTy::VARDICT);
+        let checksum = Variant::from_data_with_type(checksum, VariantTy::BYTE_STRING);
+        let commit = Variant::from_data_with_type(commit, &COMMIT_VARIANT);
+        validate_metadata(&metadata)?;
+        validate_checksum(&checksum)?;
+        Ok(FlatpakHeader {
+            metadata,
+            checksum,
+            commit,
+        })
+    }
+
+    pub fn get_hash(&self) -> String {
+        encode_checksum(&self.0.child_value(3))
+    }
+
+    /// The serialized commit and the GPG signatures made over it, which the
+    /// bundle carries in the commit's detached metadata
+    pub fn commit_signatures(&self) -> (Vec<u8>, Vec<Vec<u8>>) {
+        let commit = self.0.child_value(4).data_as_bytes().to_vec();
+        let signatures = find_metadata_value(&self.0.child_value(0), "ostree.commitmeta")
+            .and_then(|detached| find_metadata_value(&detached, "ostree.gpgsigs"))
+            .and_then(|signatures| Vec::<Vec<u8>>::from_variant(&signatures))
+            .unwrap_or_default();
+        (commit, signatures)
+    }
+
+    pub fn summary(&self) -> Result<FlatpakSummary, FlatpakMetadataError<String>> {
+        summarize(
+            &self.0.child_value(0),
+            &self.0.child_value(3),
+            &self.0.child_value(4),
+        )
+    }
+}
+
+fn summarize(
+    metadata: &Variant,
+    checksum: &Variant,
+    commit: &Variant,
+) -> Result<FlatpakSummary, FlatpakMetadataError<String>> {
+    // The app's own metadata file is embedded as a key file string
+    let key_file: Option<String> = lookup_metadata_key(metadata, "metadata").ok();
+    let application_key = |name| {
+        key_file
+            .as_deref()
+            .and_then(|key_file| key_file_value(key_file, "Application", name))
+    };
+    // OSTree stores the commit timestamp big-endian, and 0 when unset
+    let timestamp = commit
+        .child_value(5)
+        .get::<u64>()
+        .map(u64::from_be)
+        .unwrap_or(0);
+    Ok(FlatpakSummary {
+        hash: encode_checksum(checksum),
+        flatpak_ref: lookup_metadata_key(metadata, "ref")?,
+        runtime: application_key("runtime"),
+        required_flatpak: application_key("required-flatpak"),
+        built_at: i64::try_from(timestamp)
+            .ok()
+            .filter(|timestamp| *timestamp != 0)
+            .and_then(|timestamp| Utc.timestamp_opt(timestamp, 0).single()),
+    })
+}
+
+/// Looks up `key` in `[group]` of a GKeyFile-format string
+fn key_file_value(key_file: &str, group: &str, key: &str) -> Option<String> {
+    let mut in_group = false;
+    for line in key_file.lines().map(str::trim) {
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            in_group = name == group;
+        } else if in_group {
+            if let Some((candidate, value)) = line.split_once('=') {
+                if candidate.trim() == key {
+                    return Some(value.trim().to_string());
+                }
+            }
         }
-        Ok(FlatpakFile(variant))
     }
+    None
+}
+
+/// The parts of a bundle's metadata its author is likely to want to check
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct FlatpakSummary {
+    #[schema(example = "5ec8f244899431af8effad9e7ec9b2543226c78f")]
+    pub hash: String,
+    #[serde(rename = "ref")]
+    #[schema(
+        example = "app/edu.rit.csh.devcade.game.id-9b4e2ff4-a6d6-4fbe-8d5c-0d3e9a0d1c1e/x86_64/master"
+    )]
+    pub flatpak_ref: String,
+    /// The runtime the app was built against
+    #[schema(example = "org.freedesktop.Platform/x86_64/22.08")]
+    pub runtime: Option<String>,
+    /// The oldest flatpak version able to run the app, if it declares one
+    #[schema(example = "1.12.0")]
+    pub required_flatpak: Option<String>,
+    /// When the bundled commit was made, if it records a time
+    pub built_at: Option<DateTime<Utc>>,
+}
+
+/// Summaries of stored flatpaks by hash. Uploading a new flatpak changes the
+/// game's hash, so an entry can never go stale.
+#[derive(Default)]
+pub struct FlatpakSummaryCache(Mutex<HashMap<String, FlatpakSummary>>);
 
+impl FlatpakSummaryCache {
+    pub fn get(&self, hash: &str) -> Option<FlatpakSummary> {
+        self.0.lock().unwrap().get(hash).cloned()
+    }
+
+    pub fn insert(&self, hash: String, summary: FlatpakSummary) {
+        self.0.lock().unwrap().insert(hash, summary);
+    }
+}
+
+/// Size in bytes of each framing offset in a serialized GVariant container
+fn framing_offset_size(container_size: u64) -> u64 {
+    match container_size {
+        0..=0xff => 1,
+        0x100..=0xffff => 2,
+        0x1_0000..=0xffff_ffff => 4,
+        _ => 8,
+    }
+}
+
+/// The metadata, checksum and commit of a bundle, as read by
+/// [`FlatpakFile::load_header_only`]
+pub struct FlatpakHeader {
+    metadata: Variant,
+    checksum: Variant,
+    commit: Variant,
+}
+
+impl FlatpakHeader {
+    pub fn get_metadata_key<T: FromVariant + Debug>(
+        &self,
+        key: &str,
+    ) -> Result<T, FlatpakMetadataError<T>> {
+        lookup_metadata_key(&self.metadata, key)
+    }
+    pub fn get_metadata_key_list<T: FromVariant + Debug>(
+        &self,
+        key: &str,
+    ) -> Result<Vec<T>, FlatpakMetadataError<T>> {
+        lookup_metadata_list(&self.metadata, key)
+    }
+    pub fn metadata(&self) -> BTreeMap<String, String> {
+        metadata_entries(&self.metadata)
+    }
     pub fn get_hash(&self) -> String {
-        hex::encode(<Vec<u8> as FromVariant>::from_variant(&self.0.child_value(3)).unwrap())
+        encode_checksum(&self.checksum)
+    }
+    pub fn summary(&self) -> Result<FlatpakSummary, FlatpakMetadataError<String>> {
+        summarize(&self.metadata, &self.checksum, &self.commit)
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum FlatpakDecodingError {
     IncorrectFormat,
+    ReadFailed(io::ErrorKind),
     MetadataNotContainer,
     MetadataChildNotContainer,
-    BadChecksumLength,
+    BadChecksumLength { expected: usize, actual: usize },
 }
 
 impl Error for FlatpakDecodingError {}
@@ -101,7 +405,11 @@ impl Display for FlatpakDecodingError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::IncorrectFormat => write!(f, "Incorrect flatpak bundle format"),
-            Self::BadChecksumLength => write!(f, "Flatpak bundle checksum is the incorrect length"),
+            Self::ReadFailed(kind) => write!(f, "Couldn't read flatpak bundle: {kind}"),
+            Self::BadChecksumLength { expected, actual } => write!(
+                f,
+                "Flatpak bundle checksum is {actual} bytes long, expected {expected}"
+            ),
             Self::MetadataNotContainer => {
                 write!(f, "Flatpak bundle metadata field isn't a container")
             }
@@ -111,3 +419,193 @@ impl Display for FlatpakDecodingError {
         }
     }
 }
+
+impl From<io::Error> for FlatpakDecodingError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::UnexpectedEof => Self::IncorrectFormat,
+            kind => Self::ReadFailed(kind),
+        }
+    }
+}
+
+/// Builds a minimal bundle with the given `ref`, optionally padded so the
+/// container needs wider framing offsets
+#[cfg(test)]
+pub(crate) fn sample_bundle(flatpak_ref: &str, padding: usize) -> Vec<u8> {
+    use glib::ToVariant;
+    sample_bundle_with_metadata(vec![("ref", flatpak_ref.to_variant())], padding)
+}
+
+#[cfg(test)]
+pub(crate) fn sample_bundle_with_metadata(
+    entries: Vec<(&str, Variant)>,
+    padding: usize,
+) -> Vec<u8> {
+    use glib::ToVariant;
+    let empty = |type_string: &str| {
+        Variant::array_from_iter_with_type(
+            VariantTy::new(type_string).unwrap(),
+            Vec::<Variant>::new(),
+        )
+    };
+    let metadata = Variant::array_from_iter_with_type(
+        VariantTy::new("{sv}").unwrap(),
+        entries.into_iter().map(|(key, value)| {
+            Variant::from_dict_entry(&key.to_variant(), &Variant::from_variant(&value))
+        }),
+    );
+    let commit = Variant::tuple_from_iter([
+        empty("{sv}"),
+        empty("y"),
+        empty("(say)"),
+        "".to_variant(),
+        "".to_variant(),
+        0u64.to_variant(),
+        empty("y"),
+        empty("y"),
+    ]);
+    Variant::tuple_from_iter([
+        metadata,
+        0u64.to_variant(),
+        empty("y"),
+        Variant::array_from_fixed_array(&[0xab_u8; 32]),
+        commit,
+        Variant::array_from_fixed_array(&vec![0u8; padding]),
+        empty("(uayttay)"),
+        empty("(yaytt)"),
+    ])
+    .data_as_bytes()
+    .to_vec()
+}
+
+#[test]
+fn load_header_only_matches_full_load() {
+    let flatpak_ref = "app/edu.rit.csh.devcade.game.id-test/x86_64/master";
+    for padding in [0, 0x1_0000] {
+        let bundle = sample_bundle(flatpak_ref, padding);
+        let full = FlatpakFile::load(bundle.clone()).unwrap();
+        let header = FlatpakFile::load_header_only(std::io::Cursor::new(bundle)).unwrap();
+        assert_eq!(header.get_hash(), full.get_hash());
+        assert_eq!(header.get_hash(), "ab".repeat(32));
+        assert_eq!(
+            header.get_metadata_key::<String>("ref").unwrap(),
+            flatpak_ref
+        );
+        assert_eq!(header.metadata(), full.metadata());
+        assert_eq!(header.summary().unwrap(), full.summary().unwrap());
+    }
+}
+
+#[test]
+fn load_header_only_rejects_truncated_bundle() {
+    let mut bundle = sample_bundle("app/test/x86_64/master", 0x1_0000);
+    bundle.truncate(bundle.len() / 2);
+    assert!(FlatpakFile::load_header_only(std::io::Cursor::new(bundle)).is_err());
+    assert!(FlatpakFile::load_header_only(std::io::Cursor::new(Vec::new())).is_err());
+}
+
+#[test]
+fn get_metadata_key_list_reads_arrays() {
+    use glib::ToVariant;
+    let permissions = vec!["network".to_string(), "x11".to_string()];
+    let boxed: Vec<Variant> = permissions
+        .iter()
+        .map(|permission| Variant::from_variant(&permission.to_variant()))
+        .collect();
+    let flatpak_ref = "app/edu.rit.csh.devcade.game.id-test/x86_64/master";
+    let bundle = sample_bundle_with_metadata(
+        vec![
+            ("ref", flatpak_ref.to_variant()),
+            ("xa.permissions", permissions.to_variant()),
+            (
+                "xa.boxed-permissions",
+                Variant::array_from_iter_with_type(VariantTy::VARIANT, boxed),
+            ),
+        ],
+        0,
+    );
+    let flatpak = FlatpakFile::load(bundle).unwrap();
+    assert_eq!(
+        flatpak.get_metadata_key::<String>("ref").unwrap(),
+        flatpak_ref
+    );
+    for key in ["xa.permissions", "xa.boxed-permissions"] {
+        assert_eq!(
+            flatpak.get_metadata_key_list::<String>(key).unwrap(),
+            permissions
+        );
+    }
+    assert!(matches!(
+        flatpak.get_metadata_key_list::<String>("ref"),
+        Err(FlatpakMetadataError::IncorrectFormat(..))
+    ));
+    assert!(matches!(
+        flatpak.get_metadata_key_list::<u64>("xa.permissions"),
+        Err(FlatpakMetadataError::IncorrectFormat(..))
+    ));
+    assert!(matches!(
+        flatpak.get_metadata_key_list::<String>("xa.missing"),
+        Err(FlatpakMetadataError::MissingKey(_))
+    ));
+}
+
+#[test]
+fn summary_reads_the_embedded_metadata_file() {
+    use glib::ToVariant;
+    let flatpak_ref = "app/edu.rit.csh.devcade.game.id-test/x86_64/master";
+    let metadata = "[Application]\nname=edu.rit.csh.devcade.game.id-test\n\
+        runtime=org.freedesktop.Platform/x86_64/22.08\nrequired-flatpak=1.12.0\n\n\
+        [Context]\nruntime=not-this-one\n";
+    let bundle = sample_bundle_with_metadata(
+        vec![
+            ("ref", flatpak_ref.to_variant()),
+            ("metadata", metadata.to_variant()),
+        ],
+        0,
+    );
+    let summary = FlatpakFile::load(bundle).unwrap().summary().unwrap();
+    assert_eq!(
+        summary,
+        FlatpakSummary {
+            hash: "ab".repeat(32),
+            flatpak_ref: flatpak_ref.to_string(),
+            runtime: Some("org.freedesktop.Platform/x86_64/22.08".to_string()),
+            required_flatpak: Some("1.12.0".to_string()),
+            built_at: None,
+        }
+    );
+}
+
+#[test]
+fn load_rejects_truncated_and_foreign_bytes() {
+    let bundle = sample_bundle("app/test/x86_64/master", 0x1_0000);
+    let mut truncated = bundle.clone();
+    truncated.truncate(bundle.len() / 2);
+    // Deterministic noise, standing in for an upload that isn't a flatpak
+    let noise: Vec<u8> = (0u32..4096)
+        .map(|index| (index.wrapping_mul(2_654_435_761) >> 13) as u8)
+        .collect();
+    for bytes in [truncated, noise, bundle[..3].to_vec(), Vec::new()] {
+        assert!(matches!(
+            FlatpakFile::load(bytes),
+            Err(FlatpakDecodingError::IncorrectFormat)
+        ));
+    }
+}
+
+#[test]
+fn short_checksum_reports_its_length() {
+    let error = validate_checksum(&Variant::array_from_fixed_array(&[0xab_u8; 20])).unwrap_err();
+    assert!(matches!(
+        error,
+        FlatpakDecodingError::BadChecksumLength {
+            expected: 32,
+            actual: 20
+        }
+    ));
+    assert_eq!(
+        error.to_string(),
+        "Flatpak bundle checksum is 20 bytes long, expected 32"
+    );
+}
@@ -1,4 +1,7 @@
 pub mod flatpak;
 pub mod routes;
+pub mod s3;
+pub mod signature;
+pub mod sprite;
 #[cfg(test)]
 pub mod tests;
@@ -1,30 +1,211 @@
 use crate::{
-    games::flatpak::FlatpakFile,
-    models::{AppState, Game, GameWithTags},
-    security::RequireApiKey,
+    admin::routes::{incomplete_games, list_bucket_keys},
+    app::ConnectionSocket,
+    config::env_or,
+    error::{ApiError, ErrorBody},
+    events::GameEvent,
+    features::is_enabled,
+    games::{
+        flatpak::{FlatpakDecodingError, FlatpakFile, FlatpakHeader, FlatpakSummary},
+        s3::{retry_s3, S3RangeReader},
+        signature::check_signature,
+    },
+    metrics::{observe_upload, record_flatpak_rejection},
+    models::{AppState, Game, GameSummary, GameVersion, GameWithTags, Tag},
+    security::{has_api_key, RequireApiKey},
+    validation::ValidationErrors,
 };
-use actix_multipart::form::{tempfile::TempFile, text::Text, MultipartForm};
+use actix_multipart::{
+    form::{
+        tempfile::{TempFile, TempFileConfig},
+        text::Text,
+        MultipartForm, MultipartFormConfig,
+    },
+    MultipartError,
+};
+use actix_web::rt::time::timeout;
 use actix_web::{
-    delete, get, post, put,
-    web::{Data, Json, Path},
-    HttpResponse, Responder,
+    delete,
+    error::{InternalError, PayloadError},
+    get,
+    http::{
+        header::{
+            self, Accept, CacheControl, CacheDirective, ContentDisposition, DispositionParam,
+            DispositionType, ETag, EntityTag, HeaderValue, IfMatch, IfNoneMatch, IfUnmodifiedSince,
+            Quality,
+        },
+        StatusCode,
+    },
+    patch, post, put,
+    web::{self, Data, Json, Path, Payload, Query},
+    HttpMessage, HttpRequest, HttpResponse, ResponseError,
 };
-use aws_sdk_s3::{types::ByteStream, Client};
+use aws_sdk_s3::{
+    error::{DeleteObjectError, GetObjectError, HeadObjectError},
+    output::GetObjectOutput,
+    presigning::config::PresigningConfig,
+    types::{ByteStream, SdkError},
+    Client,
+};
+use bytes::Bytes;
 use chrono::prelude::*;
+use futures::{stream, StreamExt};
+use image::{DynamicImage, ImageError, ImageFormat, ImageOutputFormat};
 use lazy_static::lazy_static;
 use memmap::Mmap;
 use serde::{Deserialize, Serialize};
-use sqlx::{query, query_as};
-use std::{env, error::Error, fmt};
-use utoipa::ToSchema;
+use sha2::{Digest, Sha256};
+use sqlx::{
+    postgres::PgRow, query, query_as, query_scalar, FromRow, PgPool, Postgres, Transaction,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::Infallible,
+    env,
+    error::Error,
+    fmt,
+    io::{Cursor, Write},
+    ops::RangeInclusive,
+    path::{Path as FsPath, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+use tempfile::NamedTempFile;
+use tokio::sync::broadcast::error::RecvError;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
 lazy_static! {
-    static ref GAMES_BUCKET: String = env::var("S3_GAMES_BUCKET").unwrap();
+    static ref AUTHOR_ALLOWLIST: HashSet<String> = author_list("AUTHOR_ALLOWLIST");
+    static ref AUTHOR_DENYLIST: HashSet<String> = author_list("AUTHOR_DENYLIST");
+    static ref MAX_IMAGE_PIXELS: u64 = env_or("MAX_IMAGE_PIXELS", DEFAULT_MAX_IMAGE_PIXELS);
+    static ref MAX_GAME_BYTES: usize = env_or("MAX_GAME_BYTES", DEFAULT_MAX_GAME_BYTES);
+    static ref MAX_IMAGE_BYTES: usize = env_or("MAX_IMAGE_BYTES", DEFAULT_MAX_IMAGE_BYTES);
+    /// Whether banners get a WebP copy on upload. Defaults to on whenever the
+    /// `webp` feature is enabled and the `image` crate was built with its
+    /// `webp-encoder` feature.
+    static ref TRANSCODE_BANNERS: bool = env_or(
+        "TRANSCODE_BANNERS",
+        is_enabled("webp") && webp_encoder_available()
+    );
+    /// How long a presigned flatpak download URL stays valid for
+    static ref DOWNLOAD_URL_EXPIRY: Duration = Duration::from_secs(env_or(
+        "DOWNLOAD_URL_EXPIRY_SECONDS",
+        DEFAULT_DOWNLOAD_URL_EXPIRY_SECONDS
+    ));
+    /// How long an export may take before its client is disconnected
+    static ref EXPORT_TIMEOUT: Duration = Duration::from_secs(env_or(
+        "EXPORT_TIMEOUT_SECONDS",
+        DEFAULT_EXPORT_TIMEOUT_SECONDS
+    ));
+    /// Where uploads, and flatpaks fetched back from S3, are spooled to disk
+    static ref UPLOAD_TMP_DIR: PathBuf = env::var("UPLOAD_TMP_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| env::temp_dir());
+    /// Architectures flatpaks may be built for, comma separated
+    static ref FLATPAK_ARCHES: Vec<String> = ref_list("FLATPAK_ARCHES", DEFAULT_ARCH);
+    /// Branches flatpaks may be built on, comma separated
+    static ref FLATPAK_BRANCHES: Vec<String> = ref_list("FLATPAK_BRANCHES", DEFAULT_BRANCH);
+}
+
+/// 8192x8192, far beyond any banner or icon we'd display
+const DEFAULT_MAX_IMAGE_PIXELS: u64 = 8192 * 8192;
+const DEFAULT_MAX_GAME_BYTES: usize = 1024 * 1024 * 1024;
+const DEFAULT_MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+const DEFAULT_DOWNLOAD_URL_EXPIRY_SECONDS: u64 = 5 * 60;
+const DEFAULT_EXPORT_TIMEOUT_SECONDS: u64 = 10 * 60;
+/// The architecture every cabinet ran before others were accepted. Its
+/// flatpak is the one recorded on the game row, and served when a client
+/// doesn't ask for another.
+const DEFAULT_ARCH: &str = "x86_64";
+const DEFAULT_BRANCH: &str = "master";
+/// Room for the text fields and multipart framing around the files
+const FORM_OVERHEAD_BYTES: usize = 1024 * 1024;
+
+/// Caps a whole multipart form at the size of the largest upload we accept,
+/// so an oversized body is cut off while streaming instead of filling the
+/// tempdir. Each file is then checked against its own limit.
+pub fn multipart_config() -> MultipartFormConfig {
+    MultipartFormConfig::default()
+        .total_limit(MAX_GAME_BYTES.max(2 * *MAX_IMAGE_BYTES) + FORM_OVERHEAD_BYTES)
+        .error_handler(|e, _| match e {
+            MultipartError::Payload(PayloadError::Overflow) => InternalError::from_response(
+                e,
+                ApiError::PayloadTooLarge("Upload exceeds the maximum size".to_string())
+                    .error_response(),
+            )
+            .into(),
+            e => e.into(),
+        })
+}
+
+/// The directory uploads are spooled to, `UPLOAD_TMP_DIR` or the system temp
+/// dir. It needs room for the largest flatpak we accept, which the small tmpfs
+/// containers tend to have as `/tmp` doesn't.
+pub fn upload_tmp_dir() -> &'static FsPath {
+    &UPLOAD_TMP_DIR
+}
+
+pub fn temp_file_config() -> TempFileConfig {
+    TempFileConfig::default().directory(upload_tmp_dir())
+}
+
+fn check_upload_size(file: &TempFile, max_bytes: usize, what: &str) -> Result<(), ApiError> {
+    if file.size > max_bytes {
+        return Err(ApiError::PayloadTooLarge(format!(
+            "{what} exceeds the maximum size of {max_bytes} bytes"
+        )));
+    }
+    Ok(())
+}
+
+/// Reads a comma-separated list of flatpak arches or branches, falling back
+/// to `default` alone when unset or empty
+fn ref_list(var: &str, default: &str) -> Vec<String> {
+    let list: Vec<String> = env::var(var)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|component| !component.is_empty())
+        .map(str::to_string)
+        .collect();
+    if list.is_empty() {
+        vec![default.to_string()]
+    } else {
+        list
+    }
+}
+
+/// Reads a comma-separated list of author ids, treating unset as empty
+fn author_list(var: &str) -> HashSet<String> {
+    env::var(var)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|author| !author.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Moderation check for publishing: denied authors are always blocked, and a
+/// non-empty allowlist restricts publishing to the authors on it
+fn check_author_permitted(author: &str) -> Result<(), ApiError> {
+    if AUTHOR_DENYLIST.contains(author) {
+        return Err(ApiError::Forbidden(format!(
+            "Author {author} is not permitted to publish games"
+        )));
+    }
+    if !AUTHOR_ALLOWLIST.is_empty() && !AUTHOR_ALLOWLIST.contains(author) {
+        return Err(ApiError::Forbidden(format!(
+            "Publishing is currently restricted and author {author} is not on the allowlist"
+        )));
+    }
+    Ok(())
 }
 
+/// An uploaded game that doesn't meet our requirements for a flatpak
 #[derive(Debug, Clone)]
-struct GameError {
+pub struct GameError {
     reason: String,
 }
 
@@ -44,15 +225,53 @@ impl fmt::Display for GameError {
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct GameData {
-    #[schema(example = "BrickBreaker")]
+    #[schema(example = "BrickBreaker", min_length = 1, max_length = 128)]
     name: String,
-    #[schema(example = "Break bricks, get points")]
+    #[schema(example = "Break bricks, get points", max_length = 4096)]
     description: String,
+    /// Hands the game to another author, who must already be a user. Left
+    /// unchanged when omitted.
     #[schema(example = "ella")]
-    author: String,
+    author: Option<String>,
+    /// Replaces the game's tags, at most 20. Every tag must already exist,
+    /// otherwise the edit is rejected, and repeated names are only applied once.
+    #[schema(max_items = 20)]
     tags: Vec<String>,
 }
 
+/// The fields of a game to change with `PATCH`. Absent fields and fields sent
+/// as `null` are both left unchanged, whereas `PUT` replaces every field.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct GamePatch {
+    #[schema(example = "BrickBreaker", min_length = 1, max_length = 128)]
+    #[serde(default)]
+    name: Option<String>,
+    #[schema(example = "Break bricks, get points", max_length = 4096)]
+    #[serde(default)]
+    description: Option<String>,
+    /// Hands the game to another author, who must already be a user
+    #[schema(example = "ella")]
+    #[serde(default)]
+    author: Option<String>,
+    /// Replaces the game's tags, at most 20, which must all already exist.
+    /// Absent or `null` keeps the current tags, and `[]` removes them all.
+    #[schema(max_items = 20)]
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+}
+
+/// A new game along with its flatpak, published in one go
+#[derive(Debug, MultipartForm)]
+pub struct PublishedGameUpload {
+    pub game: TempFile,
+    pub banner: TempFile,
+    pub icon: TempFile,
+    pub title: Text<String>,
+    pub description: Text<String>,
+    pub author: Text<String>,
+    pub tags: Text<String>,
+}
+
 #[derive(Debug, MultipartForm)]
 pub struct GameUpload {
     pub banner: TempFile,
@@ -75,7 +294,10 @@ pub struct GameUploadDoc {
     title: String,
     description: String,
     author: String,
-    tags: Vec<String>,
+    /// Comma separated tag names. Every tag must already exist, otherwise the
+    /// upload is rejected, and repeated names are only applied once.
+    #[schema(example = "Action,Multiplayer")]
+    tags: String,
 }
 
 #[derive(Debug, MultipartForm)]
@@ -96,46 +318,496 @@ enum ImageComponent {
     Icon,
 }
 
+/// Widths, as a multiple of the height, that the arcade UI can draw without
+/// visibly stretching the art
+const BANNER_ASPECT_RATIOS: RangeInclusive<f64> = 1.5..=7.0;
+const ICON_ASPECT_RATIOS: RangeInclusive<f64> = 0.9..=1.1;
+
 impl ImageComponent {
     pub fn filename(&self) -> String {
         format!("{:?}", self).to_lowercase()
     }
+
+    fn aspect_ratios(&self) -> RangeInclusive<f64> {
+        match self {
+            Self::Banner => BANNER_ASPECT_RATIOS,
+            Self::Icon => ICON_ASPECT_RATIOS,
+        }
+    }
+
+    fn other(&self) -> Self {
+        match self {
+            Self::Banner => Self::Icon,
+            Self::Icon => Self::Banner,
+        }
+    }
+
+    fn fits(&self, width: u32, height: u32) -> bool {
+        height != 0
+            && self
+                .aspect_ratios()
+                .contains(&(width as f64 / height as f64))
+    }
+}
+
+fn game_etag(updated_at: &DateTime<Utc>) -> EntityTag {
+    EntityTag::new_strong(updated_at.timestamp_micros().to_string())
+}
+
+/// Evaluates `If-Match` (or, when absent, `If-Unmodified-Since`) against the
+/// game's last modification time, producing a 412 if the client's copy is stale
+fn check_preconditions(req: &HttpRequest, updated_at: &DateTime<Utc>) -> Result<(), HttpResponse> {
+    let etag = game_etag(updated_at);
+    let current = match req.get_header::<IfMatch>() {
+        Some(IfMatch::Any) => true,
+        Some(IfMatch::Items(tags)) => tags.iter().any(|tag| tag.strong_eq(&etag)),
+        None => match req.get_header::<IfUnmodifiedSince>() {
+            // HTTP dates only have second precision
+            Some(IfUnmodifiedSince(since)) => {
+                updated_at.timestamp() <= DateTime::<Utc>::from(SystemTime::from(since)).timestamp()
+            }
+            None => true,
+        },
+    };
+    if current {
+        Ok(())
+    } else {
+        Err(HttpResponse::PreconditionFailed()
+            .insert_header(ETag(etag))
+            .json(ErrorBody::new(
+                "precondition_failed",
+                "Game has been modified since the provided version",
+            )))
+    }
+}
+
+/// Marks a game as changed, returning the row to announce
+async fn touch_game(db: &PgPool, id: &str) -> Result<Game, sqlx::Error> {
+    query_as("UPDATE game SET updated_at = now() WHERE id = $1 RETURNING *")
+        .bind(id)
+        .fetch_one(db)
+        .await
+}
+
+/// Matches the length of `users.id`
+const MAX_AUTHOR_LENGTH: usize = 32;
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+const TOTAL_COUNT_HEADER: &str = "x-total-count";
+const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    /// Games with every requested tag
+    All,
+    /// Games with at least one requested tag
+    #[default]
+    Any,
+}
+
+/// Conditions shared by the listing and its total count, binding the author
+/// as $1, the tags as $2 and whether every tag must match as $3
+const GAME_FILTERS: &str = "
+    ($1::varchar IS NULL OR game.author = $1)
+    AND ($2::varchar[] IS NULL OR game.id IN (
+        SELECT game_id FROM game_tags
+        WHERE lower(tag_name) = ANY($2)
+        GROUP BY game_id
+        HAVING NOT $3 OR COUNT(DISTINCT tag_name) = cardinality($2)
+    ))
+    AND ($4 OR game.published)
+    AND game.deleted_at IS NULL";
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct GamesQuery {
+    /// Only list games by this author (a user id, e.g. `ella`)
+    author: Option<String>,
+    /// Comma separated tags to filter by
+    tags: Option<String>,
+    /// Whether games need `all` of the tags or `any` of them (default `any`)
+    #[serde(rename = "match")]
+    #[param(inline)]
+    tag_match: Option<TagMatch>,
+    /// Also list unpublished drafts (requires the API key)
+    include_drafts: Option<bool>,
+    /// Order to list games in: `name`, `date`, `author`, `downloads` or
+    /// `updated`, prefixed with `-` to reverse (default `name`)
+    sort: Option<String>,
+    /// Maximum number of games to return (default 50, max 200)
+    limit: Option<i64>,
+    /// Number of games to skip (default 0)
+    offset: Option<i64>,
+    /// Lists games by id from just after this one, so games added or removed
+    /// between pages can't make iteration skip or repeat any. Start with an
+    /// empty cursor, then pass each page's X-Next-Cursor until it's absent.
+    /// Can't be combined with `sort` or `offset`.
+    cursor: Option<String>,
+    /// `summary` lists only what the menu shows for each game, leaving out
+    /// descriptions and the author's full user (default `full`)
+    #[param(inline)]
+    fields: Option<GameFields>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GameFields {
+    /// Every field of each game, with its tags and author
+    #[default]
+    Full,
+    /// Each game's id, name, icon URL, author name and tag names
+    Summary,
+}
+
+/// Maps a `sort` parameter onto a fixed `ORDER BY` clause, so user input
+/// never reaches the query itself
+fn sort_order(sort: &str) -> Option<&'static str> {
+    Some(match sort {
+        "name" => "name ASC",
+        "-name" => "name DESC",
+        "date" => "upload_date ASC",
+        "-date" => "upload_date DESC",
+        "author" => "author ASC",
+        "-author" => "author DESC",
+        "downloads" => "downloads ASC",
+        "-downloads" => "downloads DESC",
+        "updated" => "updated_at ASC",
+        "-updated" => "updated_at DESC",
+        _ => return None,
+    })
 }
 
 #[utoipa::path(
     context_path = "/games",
+    params(GamesQuery),
     responses(
-        (status = 200, description = "List all games", body = [GameWithTags]),
-        (status = 500, description = "Error Created by Query"),
+        (status = 200, description = "List a page of games, as GameSummary with fields=summary, with the number of matching games in X-Total-Count and, when iterating by cursor, the cursor of the next page in X-Next-Cursor", body = [GameWithTags]),
+        (status = 400, description = "Invalid filters", body = ValidationErrors),
+        (status = 401, description = "Drafts were requested without the API key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     )
 )]
-#[get("/")]
-pub async fn get_all_games(state: Data<AppState>) -> impl Responder {
-    match query_as::<_, GameWithTags>(
-        "
-        SELECT game.*,
-            ROW(users.*)::users AS \"user\",
-            array_remove(ARRAY_AGG(tags.*), NULL) AS \"tags\"
-        FROM game
-        LEFT JOIN game_tags ON game_tags.game_id = game.id
-        LEFT JOIN tags ON tags.name = game_tags.tag_name
-        LEFT JOIN users ON game.author = users.id
-        GROUP BY game.id, users.id ORDER BY name ASC
-        ",
-    )
-    .fetch_all(&state.db)
-    .await
+#[get("")]
+pub async fn get_all_games(
+    req: HttpRequest,
+    state: Data<AppState>,
+    filters: Query<GamesQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let include_drafts = filters.include_drafts.unwrap_or(false);
+    if include_drafts && !has_api_key(&req) {
+        return Err(ApiError::Unauthorized(
+            "Listing drafts requires the api key".to_string(),
+        ));
+    }
+    let mut errors = ValidationErrors::default();
+    if let Some(author) = &filters.author {
+        if author.is_empty() || author.chars().count() > MAX_AUTHOR_LENGTH {
+            errors.add(
+                "author",
+                format!("Author must be between 1 and {MAX_AUTHOR_LENGTH} characters"),
+            );
+        }
+    }
+    if filters.limit.map(|limit| limit < 0).unwrap_or(false) {
+        errors.add("limit", "Limit must not be negative");
+    }
+    if filters.offset.map(|offset| offset < 0).unwrap_or(false) {
+        errors.add("offset", "Offset must not be negative");
+    }
+    let order = sort_order(filters.sort.as_deref().unwrap_or("name")).unwrap_or_else(|| {
+        errors.add(
+            "sort",
+            "Sort must be one of name, date, author, downloads or updated, optionally prefixed with -",
+        );
+        ""
+    });
+    if filters.cursor.is_some() {
+        if filters.sort.is_some() {
+            errors.add("sort", "Sort can't be combined with a cursor");
+        }
+        if filters.offset.is_some() {
+            errors.add("offset", "Offset can't be combined with a cursor");
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+    let order = match filters.cursor {
+        Some(_) => "game.id ASC".to_string(),
+        None => format!("{order}, name ASC, game.id ASC"),
+    };
+    let limit = filters
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .min(MAX_PAGE_SIZE);
+    let offset = filters.offset.unwrap_or(0);
+    let tags: Option<Vec<String>> = filters
+        .tags
+        .as_ref()
+        .map(|tags| {
+            unique_tags(tags.split(',').map(str::to_string))
+                .iter()
+                .map(|tag| Tag::normalize_name(tag))
+                .collect::<Vec<_>>()
+        })
+        .filter(|tags| !tags.is_empty());
+    let match_all = filters.tag_match.unwrap_or_default() == TagMatch::All;
+    let total: i64 = query_scalar(&format!("SELECT COUNT(*) FROM game WHERE {GAME_FILTERS}"))
+        .bind(&filters.author)
+        .bind(&tags)
+        .bind(match_all)
+        .bind(include_drafts)
+        .fetch_one(&state.db)
+        .await?;
+    let page = GamesPage {
+        filters: &filters,
+        tags: &tags,
+        match_all,
+        include_drafts,
+        order: &order,
+        limit,
+        offset,
+        total,
+    };
+    let response = match filters.fields.unwrap_or_default() {
+        GameFields::Full => {
+            page.respond::<GameWithTags>(&state.db, FULL_GAME_COLUMNS)
+                .await
+        }
+        GameFields::Summary => {
+            page.respond::<GameSummary>(&state.db, GAME_SUMMARY_COLUMNS)
+                .await
+        }
+    };
+    Ok(response?)
+}
+
+const FULL_GAME_COLUMNS: &str = "
+    game.*,
+    ROW(users.*)::users AS \"user\",
+    array_remove(ARRAY_AGG(tags.*), NULL) AS \"tags\"";
+/// Leaves out the description and user row, which the menu doesn't need to
+/// draw its list
+const GAME_SUMMARY_COLUMNS: &str = "
+    game.id, game.name, game.size_bytes,
+    '/api/games/' || game.id || '/icon' AS icon_url,
+    COALESCE(users.first_name || ' ' || users.last_name, game.author) AS author_name,
+    array_remove(ARRAY_AGG(tags.name ORDER BY tags.name), NULL) AS tags";
+
+/// One page of the games listing, fetched as whichever shape was asked for
+struct GamesPage<'a> {
+    filters: &'a GamesQuery,
+    tags: &'a Option<Vec<String>>,
+    match_all: bool,
+    include_drafts: bool,
+    order: &'a str,
+    limit: i64,
+    offset: i64,
+    total: i64,
+}
+
+trait HasId {
+    fn id(&self) -> &str;
+}
+
+impl HasId for GameWithTags {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl HasId for GameSummary {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl GamesPage<'_> {
+    async fn respond<T>(&self, db: &PgPool, columns: &str) -> Result<HttpResponse, sqlx::Error>
+    where
+        T: for<'r> FromRow<'r, PgRow> + HasId + Serialize + Send + Unpin,
     {
-        Ok(games) => HttpResponse::Ok().json(games),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        let games = query_as::<_, T>(&format!(
+            "
+            SELECT {columns}
+            FROM game
+            LEFT JOIN game_tags ON game_tags.game_id = game.id
+            LEFT JOIN tags ON tags.name = game_tags.tag_name
+            LEFT JOIN users ON game.author = users.id
+            WHERE {GAME_FILTERS} AND ($7::varchar IS NULL OR game.id > $7)
+            GROUP BY game.id, users.id ORDER BY {}
+            LIMIT $5 OFFSET $6
+            ",
+            self.order
+        ))
+        .bind(&self.filters.author)
+        .bind(self.tags)
+        .bind(self.match_all)
+        .bind(self.include_drafts)
+        .bind(self.limit)
+        .bind(self.offset)
+        .bind(&self.filters.cursor)
+        .fetch_all(db)
+        .await?;
+        let mut response = HttpResponse::Ok();
+        response.insert_header((TOTAL_COUNT_HEADER, self.total.to_string()));
+        // A short page is the last one
+        if self.filters.cursor.is_some() && self.limit > 0 && games.len() as i64 == self.limit {
+            if let Some(last) = games.last() {
+                response.insert_header((NEXT_CURSOR_HEADER, last.id().to_string()));
+            }
+        }
+        Ok(response.json(games))
     }
 }
 
-async fn verify_and_upload_game(
-    game: TempFile,
-    s3: &Client,
+/// Carries a game's recorded flatpak hash on downloads, so the cabinet can
+/// check what it received without asking separately
+const FLATPAK_HASH_HEADER: &str = "x-flatpak-sha256";
+const APP_ID_PREFIX: &str = "edu.rit.csh.devcade.game.id-";
+
+/// The id of the game a flatpak's ref names, if its app id is one of ours
+fn flatpak_ref_game(flatpak_ref: &str) -> Option<&str> {
+    flatpak_ref.split('/').nth(1)?.strip_prefix(APP_ID_PREFIX)
+}
+
+/// The architectures and branches a flatpak's ref may name
+struct AcceptedRefs<'a> {
+    arches: &'a [String],
+    branches: &'a [String],
+}
+
+/// A flatpak's ref (`app/<app id>/<arch>/<branch>`) must name the game it's
+/// uploaded to, so one game's bundle can't overwrite another's, and an
+/// architecture and branch we accept, which are returned
+fn check_flatpak_ref<'a>(
+    flatpak_ref: &'a str,
     uuid: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
+    accepted: &AcceptedRefs,
+) -> Result<(&'a str, &'a str), GameError> {
+    let components: Vec<&str> = flatpak_ref.split('/').collect();
+    let [kind, app_id, arch, branch] = components[..] else {
+        return Err(GameError::new(&format!(
+            "Flatpak ref {flatpak_ref} must be of the form app/<app id>/<arch>/<branch>"
+        )));
+    };
+    if kind != "app" {
+        return Err(GameError::new("Flatpak must be of type app"));
+    }
+    let expected = format!("{APP_ID_PREFIX}{uuid}");
+    if app_id != expected {
+        return Err(GameError::new(&match app_id.strip_prefix(APP_ID_PREFIX) {
+            Some(other) => format!(
+                "Flatpak was built for game {other}, not {uuid} (app id must be {expected})"
+            ),
+            None => format!("Flatpak app id {app_id} must be {expected}"),
+        }));
+    }
+    if !accepted.arches.iter().any(|allowed| allowed == arch) {
+        return Err(GameError::new(&format!(
+            "Flatpak architecture {arch} must be one of {}",
+            accepted.arches.join(", ")
+        )));
+    }
+    if !accepted.branches.iter().any(|allowed| allowed == branch) {
+        return Err(GameError::new(&format!(
+            "Flatpak branch {branch} must be one of {}",
+            accepted.branches.join(", ")
+        )));
+    }
+    Ok((arch, branch))
+}
+
+#[test]
+fn check_flatpak_ref_requires_this_games_app_id() {
+    let uuid = "9b4e2ff4-a6d6-4fbe-8d5c-0d3e9a0d1c1e";
+    let defaults = ([DEFAULT_ARCH.to_string()], [DEFAULT_BRANCH.to_string()]);
+    let accepted = AcceptedRefs {
+        arches: &defaults.0,
+        branches: &defaults.1,
+    };
+    assert!(check_flatpak_ref(
+        &format!("app/{APP_ID_PREFIX}{uuid}/x86_64/master"),
+        uuid,
+        &accepted
+    )
+    .is_ok());
+    let error = check_flatpak_ref(
+        &format!("app/{APP_ID_PREFIX}00000000-0000-0000-0000-000000000000/x86_64/master"),
+        uuid,
+        &accepted,
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("built for game 00000000-0000-0000-0000-000000000000, not"));
+    assert!(check_flatpak_ref("app/org.example.Game/x86_64/master", uuid, &accepted).is_err());
+    assert!(check_flatpak_ref(
+        &format!("runtime/{APP_ID_PREFIX}{uuid}/x86_64/master"),
+        uuid,
+        &accepted
+    )
+    .is_err());
+    assert!(check_flatpak_ref("app", uuid, &accepted).is_err());
+}
+
+#[test]
+fn check_flatpak_ref_accepts_only_configured_arches_and_branches() {
+    let uuid = "9b4e2ff4-a6d6-4fbe-8d5c-0d3e9a0d1c1e";
+    let flatpak_ref = format!("app/{APP_ID_PREFIX}{uuid}/aarch64/beta");
+    let defaults = ([DEFAULT_ARCH.to_string()], [DEFAULT_BRANCH.to_string()]);
+    let error = check_flatpak_ref(
+        &flatpak_ref,
+        uuid,
+        &AcceptedRefs {
+            arches: &defaults.0,
+            branches: &defaults.1,
+        },
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .ends_with("Flatpak architecture aarch64 must be one of x86_64"));
+    let arches = [DEFAULT_ARCH.to_string(), "aarch64".to_string()];
+    let error = check_flatpak_ref(
+        &flatpak_ref,
+        uuid,
+        &AcceptedRefs {
+            arches: &arches,
+            branches: &defaults.1,
+        },
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .ends_with("Flatpak branch beta must be one of master"));
+    let branches = ["stable".to_string(), "beta".to_string()];
+    assert_eq!(
+        check_flatpak_ref(
+            &flatpak_ref,
+            uuid,
+            &AcceptedRefs {
+                arches: &arches,
+                branches: &branches,
+            },
+        )
+        .unwrap(),
+        ("aarch64", "beta")
+    );
+}
+
+/// An uploaded flatpak, read once for everything later checked about it
+struct LoadedFlatpak {
+    file: TempFile,
+    digest: String,
+    flatpak: FlatpakFile,
+    flatpak_ref: String,
+    /// When the upload started being processed, for the upload metrics
+    started: Instant,
+}
+
+async fn load_flatpak(game: TempFile) -> Result<LoadedFlatpak, ApiError> {
+    let started = Instant::now();
     let game_content_type = game
         .content_type
         .as_ref()
@@ -144,454 +816,3427 @@ async fn verify_and_upload_game(
     if game_content_type != "application/vnd.flatpak"
         && game_content_type != "application/octet-stream"
     {
-        return Err(Box::new(GameError::new("Game provided is not a Flatpak!")));
+        return Err(ApiError::UnsupportedMediaType(
+            "Game provided is not a Flatpak!".to_string(),
+        ));
+    }
+    // An empty file can't be memory-mapped, and isn't a flatpak either way
+    if game.size == 0 {
+        return Err(FlatpakDecodingError::IncorrectFormat.into());
     }
     // Afaik, this is only unsafe because outside processes (read: the OS) could
     // write to our file unsynchronized
-    let file_memory_map = unsafe { Mmap::map(game.file.as_file()) }?;
-    let flatpak = FlatpakFile::load(file_memory_map)?;
+    let file_memory_map = unsafe { Mmap::map(game.file.as_file()) }.map_err(ApiError::internal)?;
+    // Hashing and checking a bundle of up to a gigabyte is left to the
+    // blocking pool
+    let (digest, flatpak) = web::block(move || {
+        let digest = hex::encode(Sha256::digest(&file_memory_map));
+        FlatpakFile::load(file_memory_map).map(|flatpak| (digest, flatpak))
+    })
+    .await
+    .map_err(ApiError::internal)??;
+    let flatpak_ref = flatpak.get_metadata_key("ref")?;
+    Ok(LoadedFlatpak {
+        file: game,
+        digest,
+        flatpak,
+        flatpak_ref,
+        started,
+    })
+}
+
+async fn verify_and_upload_game(
+    game: LoadedFlatpak,
+    db: &PgPool,
+    s3: &Client,
+    bucket: &str,
+    uuid: &str,
+) -> Result<UploadedFlatpak, ApiError> {
+    let LoadedFlatpak {
+        file: game,
+        digest,
+        flatpak,
+        flatpak_ref,
+        started,
+    } = game;
     let hash = flatpak.get_hash();
-    let flatpak_ref: String = flatpak.get_metadata_key("ref")?;
-    let components: Vec<&str> = flatpak_ref.split('/').collect();
-    if components[0] != "app" {
-        return Err(Box::new(GameError::new("Flatpak must be of type app")));
-    }
-    let app_id = format!("edu.rit.csh.devcade.game.id-{uuid}");
-    if components[1] != app_id {
-        return Err(Box::new(GameError::new(&format!(
-            "Flatpak app id {} must be {app_id}",
-            components[1]
-        ))));
-    }
-    if components[2] != "x86_64" {
-        return Err(Box::new(GameError::new(
-            "Flatpak architecture must be x86_64",
-        )));
+    if let Some(other) = flatpak_ref_game(&flatpak_ref).filter(|other| *other != uuid) {
+        if game_exists(db, other).await? {
+            return Err(ApiError::Conflict(format!(
+                "Flatpak app id already belongs to game {other}"
+            )));
+        }
     }
-    if components[3] != "master" {
-        return Err(Box::new(GameError::new("Flatpak branch must be master")));
+    let accepted = AcceptedRefs {
+        arches: &FLATPAK_ARCHES,
+        branches: &FLATPAK_BRANCHES,
+    };
+    let (arch, branch) = check_flatpak_ref(&flatpak_ref, uuid, &accepted)?;
+    let (arch, branch) = (arch.to_string(), branch.to_string());
+    check_signature(&flatpak).await?;
+
+    let known: bool = query_scalar("SELECT EXISTS (SELECT 1 FROM blobs WHERE digest = $1)")
+        .bind(&digest)
+        .fetch_one(db)
+        .await?;
+    let uploaded = if known {
+        None
+    } else {
+        Some(upload_blob(s3, bucket, &game, &digest).await?)
+    };
+    observe_upload("game", game.size, started.elapsed());
+    Ok(UploadedFlatpak {
+        size: game.size as i64,
+        file: game,
+        digest,
+        hash,
+        arch,
+        branch,
+        uploaded,
+    })
+}
+
+/// A verified flatpak whose bytes are stored, unless identical ones already
+/// were, but not yet recorded
+struct UploadedFlatpak {
+    file: TempFile,
+    digest: String,
+    hash: String,
+    size: i64,
+    arch: String,
+    branch: String,
+    /// The blob this upload stored, which is its to delete if it fails
+    uploaded: Option<String>,
+}
+
+/// Stores a flatpak under a key of its own, rather than one named by its
+/// digest alone, so an upload that fails can delete what it stored without
+/// taking an identical upload's bytes with it
+async fn upload_blob(
+    s3: &Client,
+    bucket: &str,
+    game: &TempFile,
+    digest: &str,
+) -> Result<String, ApiError> {
+    let key = format!("blobs/{digest}-{}.flatpak", Uuid::new_v4().simple());
+    let upload = || async {
+        let body = ByteStream::from_path(game.file.path())
+            .await
+            .map_err(|e| SdkError::ConstructionFailure(e.into()))?;
+        s3.put_object()
+            .key(&key)
+            .body(body)
+            .bucket(bucket)
+            .send()
+            .await
+    };
+    retry_s3("put_object", upload)
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(key)
+}
+
+/// Records an uploaded flatpak's blob, or shares the one already recorded for
+/// its digest, deleting this upload's copy
+async fn record_flatpak(
+    transaction: &mut Transaction<'_, Postgres>,
+    s3: &Client,
+    bucket: &str,
+    uuid: &str,
+    flatpak: UploadedFlatpak,
+) -> Result<StoredFlatpak, ApiError> {
+    // Uploads of the same bytes take turns recording them, so only one adds
+    // the digest's row
+    query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind(&flatpak.digest)
+        .execute(&mut *transaction)
+        .await?;
+    // Sharing the blob keeps it from being collected before this upload's
+    // version is recorded
+    let existing: Option<String> =
+        query_scalar("SELECT key FROM blobs WHERE digest = $1 FOR SHARE")
+            .bind(&flatpak.digest)
+            .fetch_optional(&mut *transaction)
+            .await?;
+    let deduplicated = existing.is_some();
+    let key = match existing {
+        Some(key) => {
+            if let Some(uploaded) = &flatpak.uploaded {
+                discard_blob(s3, bucket, uploaded).await;
+            }
+            // Blobs stored before sizes were recorded learn theirs
+            query("UPDATE blobs SET size_bytes = $1 WHERE key = $2 AND size_bytes IS NULL")
+                .bind(flatpak.size)
+                .bind(&key)
+                .execute(&mut *transaction)
+                .await?;
+            key
+        }
+        None => {
+            // The blob found before uploading may have been released since
+            let key = match &flatpak.uploaded {
+                Some(key) => key.clone(),
+                None => upload_blob(s3, bucket, &flatpak.file, &flatpak.digest).await?,
+            };
+            let recorded = query("INSERT INTO blobs (key, digest, size_bytes) VALUES ($1, $2, $3)")
+                .bind(&key)
+                .bind(&flatpak.digest)
+                .bind(flatpak.size)
+                .execute(&mut *transaction)
+                .await;
+            if let Err(e) = recorded {
+                discard_blob(s3, bucket, &key).await;
+                return Err(e.into());
+            }
+            key
+        }
+    };
+    tracing::info!(
+        game_id = uuid,
+        hash = %flatpak.hash,
+        arch = %flatpak.arch,
+        branch = %flatpak.branch,
+        bytes = flatpak.size,
+        deduplicated,
+        "Stored flatpak"
+    );
+    Ok(StoredFlatpak {
+        hash: flatpak.hash,
+        key,
+        size: flatpak.size,
+        arch: flatpak.arch,
+        branch: flatpak.branch,
+        created: !deduplicated,
+    })
+}
+
+/// A recorded flatpak and the object holding its bytes
+struct StoredFlatpak {
+    hash: String,
+    key: String,
+    size: i64,
+    arch: String,
+    branch: String,
+    /// Whether this upload stored the blob, rather than finding it already
+    /// stored, so it's this upload's to delete if it fails
+    created: bool,
+}
+
+/// Deletes the blob an upload stored once the upload has failed. Its key is
+/// the upload's own and its row was never committed, so nothing else can
+/// refer to it.
+async fn discard_blob(s3: &Client, bucket: &str, key: &str) {
+    let delete = || s3.delete_object().bucket(bucket).key(key).send();
+    match retry_s3("delete_object", delete).await {
+        Err(e) if !is_missing_key(&e) => log::warn!("Failed to delete unused flatpak {key}: {e}"),
+        _ => {}
+    }
+}
+
+/// Where the flatpak a game serves for `arch` is kept. The default
+/// architecture's keeps the key it had before others were accepted.
+fn flatpak_key(id: &str, arch: &str) -> String {
+    if arch == DEFAULT_ARCH {
+        format!("{id}/{id}.flatpak")
+    } else {
+        format!("{id}/{id}.{arch}.flatpak")
     }
+}
+
+/// Replaces the flatpak a game serves for `arch` with a copy of a stored one
+async fn make_flatpak_current(
+    s3: &Client,
+    bucket: &str,
+    id: &str,
+    arch: &str,
+    key: &str,
+) -> Result<(), ApiError> {
+    let copy = || {
+        s3.copy_object()
+            .bucket(bucket)
+            .copy_source(format!("{bucket}/{key}"))
+            .key(flatpak_key(id, arch))
+            .send()
+    };
+    retry_s3("copy_object", copy)
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(())
+}
 
-    let _ = s3
-        .put_object()
-        .key(format!("{}/{}.flatpak", uuid, uuid))
-        .body(ByteStream::from_path(game.file.path()).await?)
-        .bucket(&GAMES_BUCKET.to_string())
-        .send()
+/// Records `hash` as the flatpak a game serves for `arch`, returning the
+/// game's new `updated_at`, and the hash and size on its row. Only the default
+/// architecture's flatpak is kept on the row, for the clients that don't know
+/// about any other.
+async fn set_current_build(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: &str,
+    arch: &str,
+    hash: &str,
+    size: Option<i64>,
+) -> Result<(DateTime<Utc>, Option<String>, Option<i64>), sqlx::Error> {
+    let default_arch = arch == DEFAULT_ARCH;
+    if !default_arch {
+        query(
+            "INSERT INTO game_builds (game_id, arch, hash, size_bytes) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (game_id, arch) DO UPDATE
+            SET hash = EXCLUDED.hash, size_bytes = EXCLUDED.size_bytes",
+        )
+        .bind(id)
+        .bind(arch)
+        .bind(hash)
+        .bind(size)
+        .execute(&mut *transaction)
         .await?;
-    Ok(hash)
+    }
+    query_as(
+        "UPDATE game SET updated_at = now(),
+            hash = CASE WHEN $2 THEN $3 ELSE hash END,
+            size_bytes = CASE WHEN $2 THEN $4 ELSE size_bytes END
+        WHERE id = $1 RETURNING updated_at, hash, size_bytes",
+    )
+    .bind(id)
+    .bind(default_arch)
+    .bind(hash)
+    .bind(size)
+    .fetch_one(&mut *transaction)
+    .await
+}
+
+/// The hash of the flatpak a game serves for `arch`, if it has one
+async fn current_hash(db: &PgPool, game: &Game, arch: &str) -> Result<Option<String>, sqlx::Error> {
+    Ok(current_build(db, game, arch).await?.map(|(hash, _)| hash))
+}
+
+/// The hash and recorded size of the flatpak a game serves for `arch`, if it
+/// has one
+async fn current_build(
+    db: &PgPool,
+    game: &Game,
+    arch: &str,
+) -> Result<Option<(String, Option<i64>)>, sqlx::Error> {
+    if arch == DEFAULT_ARCH {
+        return Ok(game.hash.clone().map(|hash| (hash, game.size_bytes)));
+    }
+    query_as("SELECT hash, size_bytes FROM game_builds WHERE game_id = $1 AND arch = $2")
+        .bind(&game.id)
+        .bind(arch)
+        .fetch_optional(db)
+        .await
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ArchQuery {
+    /// Architecture of the flatpak wanted (default x86_64)
+    arch: Option<String>,
+}
+
+impl ArchQuery {
+    fn arch(&self) -> &str {
+        self.arch.as_deref().unwrap_or(DEFAULT_ARCH)
+    }
+}
+
+/// Every stored flatpak the given games' versions point at
+async fn version_keys(db: &PgPool, ids: &[String]) -> Result<Vec<String>, sqlx::Error> {
+    query_scalar("SELECT DISTINCT key FROM game_versions WHERE game_id = ANY($1)")
+        .bind(ids)
+        .fetch_all(db)
+        .await
+}
+
+/// Removes the stored flatpaks that no version points at anymore. A blob's
+/// references are the versions naming its key, so a version being recorded
+/// at the same time fails the delete on its foreign key and keeps the blob.
+/// The row stays locked until the object is gone, so an upload of the same
+/// file waits and then stores it afresh.
+async fn release_blobs(db: &PgPool, s3: &Client, bucket: &str, keys: Vec<String>) {
+    for key in keys {
+        let released = async {
+            let mut transaction = db.begin().await?;
+            let deleted = query(
+                "DELETE FROM blobs WHERE key = $1
+                AND NOT EXISTS (SELECT 1 FROM game_versions WHERE key = $1)",
+            )
+            .bind(&key)
+            .execute(&mut transaction)
+            .await?
+            .rows_affected();
+            if deleted == 1 {
+                let delete = || s3.delete_object().bucket(bucket).key(&key).send();
+                match retry_s3("delete_object", delete).await {
+                    Err(e) if !is_missing_key(&e) => return Err(e.into()),
+                    _ => {}
+                }
+            }
+            transaction.commit().await?;
+            Ok::<_, Box<dyn Error>>(())
+        };
+        if let Err(e) = released.await {
+            log::warn!("Failed to release flatpak {key}: {e}");
+        }
+    }
+}
+
+/// Guards against decompression bombs by checking the dimensions in the image
+/// header, which the `image` crate reads without decoding any pixel data.
+/// Images whose header can't be read are left to the content type check.
+fn check_image_pixels(image: &TempFile) -> Result<(), ApiError> {
+    if exceeds_pixel_limit(image.file.path(), *MAX_IMAGE_PIXELS) {
+        return Err(ApiError::BadRequest(
+            "Image too large to process".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects banners and icons whose shape is outside the range the arcade UI
+/// renders. As with the pixel limit, unreadable headers are left to the
+/// content type check.
+fn check_image_shape(image: &TempFile, image_type: &ImageComponent) -> Result<(), ApiError> {
+    match image_dimensions(image.file.path()) {
+        Some((width, height)) => match shape_problem(image_type, width, height) {
+            Some(problem) => Err(ApiError::BadRequest(problem)),
+            None => Ok(()),
+        },
+        None => Ok(()),
+    }
+}
+
+fn shape_problem(image_type: &ImageComponent, width: u32, height: u32) -> Option<String> {
+    if image_type.fits(width, height) {
+        return None;
+    }
+    let allowed = image_type.aspect_ratios();
+    let mut problem = format!(
+        "{:?} is {}x{}, but its width must be between {} and {} times its height",
+        image_type,
+        width,
+        height,
+        allowed.start(),
+        allowed.end()
+    );
+    // Banners and icons are easy to mix up, as both are just images
+    let other = image_type.other();
+    if other.fits(width, height) {
+        problem.push_str(&format!(
+            ". It's shaped like {}, so check the banner and icon weren't swapped",
+            match other {
+                ImageComponent::Banner => "a banner",
+                ImageComponent::Icon => "an icon",
+            }
+        ));
+    }
+    Some(problem)
+}
+
+fn image_dimensions(path: &std::path::Path) -> Option<(u32, u32)> {
+    image::io::Reader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok())
+}
+
+fn exceeds_pixel_limit(path: &std::path::Path, max_pixels: u64) -> bool {
+    image_dimensions(path)
+        .map(|(width, height)| width as u64 * height as u64 > max_pixels)
+        .unwrap_or(false)
+}
+
+/// A PNG claiming the given size, but without any actual pixel data
+#[cfg(test)]
+fn png_without_pixels(width: u32, height: u32) -> Vec<u8> {
+    let crc32 = |bytes: &[u8]| {
+        !bytes.iter().fold(!0u32, |crc, byte| {
+            (0..8).fold(crc ^ *byte as u32, |crc, _| {
+                (crc >> 1) ^ (0xedb8_8320 & (!(crc & 1)).wrapping_add(1))
+            })
+        })
+    };
+    let mut header = b"IHDR".to_vec();
+    header.extend(width.to_be_bytes());
+    header.extend(height.to_be_bytes());
+    header.extend([8, 6, 0, 0, 0]);
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    // The decoder reads chunks up to the start of the image data
+    for chunk in [header, b"IDAT".to_vec()] {
+        png.extend((chunk.len() as u32 - 4).to_be_bytes());
+        png.extend(&chunk);
+        png.extend(crc32(&chunk).to_be_bytes());
+    }
+    png
+}
+
+#[test]
+fn unique_tags_drops_blank_and_repeated_names() {
+    let tags = unique_tags(
+        ["Action", " Puzzle", "", "action", "PUZZLE "]
+            .into_iter()
+            .map(str::to_string),
+    );
+    assert_eq!(tags, vec!["Action", "Puzzle"]);
+}
+
+#[test]
+fn exceeds_pixel_limit_reads_only_the_header() {
+    let mut bomb = NamedTempFile::new().unwrap();
+    bomb.write_all(&png_without_pixels(100_000, 100_000))
+        .unwrap();
+    assert!(exceeds_pixel_limit(bomb.path(), DEFAULT_MAX_IMAGE_PIXELS));
+    let mut icon = NamedTempFile::new().unwrap();
+    icon.write_all(&png_without_pixels(256, 256)).unwrap();
+    assert!(!exceeds_pixel_limit(icon.path(), DEFAULT_MAX_IMAGE_PIXELS));
 }
 
 async fn verify_and_upload_image(
     image: TempFile,
     s3: &Client,
+    bucket: &str,
     image_type: ImageComponent,
     uuid: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), ApiError> {
+    let started = Instant::now();
     let image_content_type = image
         .content_type
         .as_ref()
-        .ok_or("Could not determine file type")?
+        .ok_or_else(|| ApiError::UnsupportedMediaType("Could not determine file type".to_string()))?
         .clone();
     if image_content_type.type_() != "image" {
-        return Err(Box::new(GameError::new(&format!(
+        return Err(ApiError::UnsupportedMediaType(format!(
             "{:?} provided is not an image",
             image_type
-        ))));
+        )));
+    }
+    let upload = || async {
+        let body = ByteStream::from_path(image.file.path())
+            .await
+            .map_err(|e| SdkError::ConstructionFailure(e.into()))?;
+        s3.put_object()
+            .key(format!("{}/{}", uuid, image_type.filename()))
+            .body(body)
+            // Keys have no extension, so the stored content type is the only
+            // record of the format when the image is served back
+            .content_type(image_content_type.essence_str())
+            .bucket(bucket)
+            .send()
+            .await
+    };
+    retry_s3("put_object", upload)
+        .await
+        .map_err(ApiError::internal)?;
+    observe_upload(&image_type.filename(), image.size, started.elapsed());
+    tracing::info!(
+        game_id = uuid,
+        asset = image_type.filename(),
+        bytes = image.size,
+        "Stored image"
+    );
+    if let ImageComponent::Icon = image_type {
+        // The full icon is already stored, so a missing thumbnail is not
+        // worth failing the upload over
+        if let Err(e) = upload_icon_thumbnail(image.file.path(), s3, bucket, uuid).await {
+            log::warn!("Failed to generate icon thumbnail for game {uuid}: {e}");
+        }
+    }
+    if let ImageComponent::Banner = image_type {
+        // Clients that can't get the WebP copy are served the original
+        if let Err(e) = upload_banner_webp(image.file.path(), s3, bucket, uuid).await {
+            log::warn!("Failed to store WebP banner for game {uuid}: {e}");
+        }
     }
-    let _ = s3
-        .put_object()
-        .key(format!(
-            "{}/{}",
-            uuid,
-            image_type.filename(),
-            //image_content_type.subtype()
-        ))
-        .body(ByteStream::from_path(image.file.path()).await?)
-        .bucket(&GAMES_BUCKET.to_string())
-        .send()
-        .await?;
     Ok(())
 }
 
-async fn verify_and_upload(
-    banner: TempFile,
-    icon: TempFile,
-    s3: &Client,
-    uuid: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    verify_and_upload_image(banner, s3, ImageComponent::Banner, uuid).await?;
-    verify_and_upload_image(icon, s3, ImageComponent::Icon, uuid).await?;
-    Ok(())
+fn webp_encoder_available() -> bool {
+    !matches!(
+        ImageOutputFormat::from(ImageFormat::WebP),
+        ImageOutputFormat::Unsupported(_)
+    )
+}
+
+fn encode_webp(path: &std::path::Path) -> Result<Vec<u8>, ImageError> {
+    let banner = image::io::Reader::open(path)?
+        .with_guessed_format()?
+        .decode()?;
+    let mut webp = Cursor::new(Vec::new());
+    DynamicImage::ImageRgba8(banner.to_rgba8())
+        .write_to(&mut webp, ImageOutputFormat::from(ImageFormat::WebP))?;
+    Ok(webp.into_inner())
+}
+
+/// Stores a WebP copy of a newly uploaded banner, returning whether one was
+/// stored. When there's no copy to store, the previous banner's is removed so
+/// it can't be served instead. Decoding and encoding run on the blocking
+/// pool, since a large banner takes long enough to stall other requests.
+pub async fn upload_banner_webp(
+    path: &std::path::Path,
+    s3: &Client,
+    bucket: &str,
+    uuid: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let key = format!("{}/banner.webp", uuid);
+    let webp = if *TRANSCODE_BANNERS {
+        let path = path.to_path_buf();
+        Some(web::block(move || encode_webp(&path)).await?)
+    } else {
+        None
+    };
+    let error: Option<Box<dyn std::error::Error>> = match webp {
+        Some(Ok(webp)) => {
+            let webp = Bytes::from(webp);
+            let upload = || {
+                s3.put_object()
+                    .key(&key)
+                    .body(ByteStream::from(webp.clone()))
+                    .content_type("image/webp")
+                    .bucket(bucket)
+                    .send()
+            };
+            retry_s3("put_object", upload).await?;
+            return Ok(true);
+        }
+        Some(Err(e)) => Some(e.into()),
+        None => None,
+    };
+    let delete = || s3.delete_object().bucket(bucket).key(&key).send();
+    match retry_s3("delete_object", delete).await {
+        Err(e) if !is_missing_key(&e) => return Err(e.into()),
+        _ => {}
+    }
+    error.map_or(Ok(false), Err)
+}
+
+/// Whether the client listed WebP among the image formats it accepts.
+/// Wildcards don't count, since browsers send `*/*` regardless.
+fn accepts_webp(req: &HttpRequest) -> bool {
+    req.get_header::<Accept>().is_some_and(|accept| {
+        accept
+            .iter()
+            .any(|item| item.item.essence_str() == "image/webp" && item.quality > Quality::ZERO)
+    })
+}
+
+/// Side length, in pixels, the icon is shrunk to for the menu grid
+const ICON_THUMB_SIZE: u32 = 128;
+
+fn encode_icon_thumbnail(path: &std::path::Path) -> Result<Vec<u8>, ImageError> {
+    let icon = image::io::Reader::open(path)?
+        .with_guessed_format()?
+        .decode()?;
+    let mut png = Cursor::new(Vec::new());
+    icon.thumbnail(ICON_THUMB_SIZE, ICON_THUMB_SIZE)
+        .write_to(&mut png, ImageOutputFormat::Png)?;
+    Ok(png.into_inner())
+}
+
+/// Stores the menu grid's thumbnail of a newly uploaded icon, shrinking it on
+/// the blocking pool like the banner's WebP copy
+pub async fn upload_icon_thumbnail(
+    path: &std::path::Path,
+    s3: &Client,
+    bucket: &str,
+    uuid: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path.to_path_buf();
+    let png = Bytes::from(web::block(move || encode_icon_thumbnail(&path)).await??);
+    let upload = || {
+        s3.put_object()
+            .key(format!("{}/icon_thumb", uuid))
+            .body(ByteStream::from(png.clone()))
+            .content_type("image/png")
+            .bucket(bucket)
+            .send()
+    };
+    retry_s3("put_object", upload).await?;
+    Ok(())
+}
+
+/// How long clients may use a cached image before revalidating it. Kept short
+/// since a re-uploaded image keeps its URL, and revalidating is cheap.
+const IMAGE_MAX_AGE_SECONDS: u32 = 600;
+
+/// Passes an image from S3 straight through to the client instead of holding
+/// it in memory, with the content type it was uploaded as. The object's ETag
+/// lets clients revalidate their copy, getting a 304 if it's still current.
+fn stream_image(req: &HttpRequest, objout: GetObjectOutput) -> HttpResponse {
+    let etag = objout
+        .e_tag()
+        .and_then(|etag| etag.parse::<EntityTag>().ok());
+    let mut response = HttpResponse::Ok();
+    response.insert_header(CacheControl(vec![
+        CacheDirective::Public,
+        CacheDirective::MaxAge(IMAGE_MAX_AGE_SECONDS),
+    ]));
+    if let Some(etag) = etag {
+        let cached = match req.get_header::<IfNoneMatch>() {
+            Some(IfNoneMatch::Any) => true,
+            Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+            None => false,
+        };
+        response.insert_header(ETag(etag));
+        if cached {
+            return response.status(StatusCode::NOT_MODIFIED).finish();
+        }
+    }
+    let content_type = objout
+        .content_type()
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    response.content_type(content_type).streaming(objout.body)
+}
+
+const MAX_NAME_LENGTH: usize = 128;
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+const MAX_TAGS: usize = 20;
+
+fn validate_text_fields(
+    name_field: &str,
+    name: &str,
+    description: &str,
+    errors: &mut ValidationErrors,
+) {
+    validate_name(name_field, name, errors);
+    validate_description(description, errors);
+}
+
+fn validate_name(name_field: &str, name: &str, errors: &mut ValidationErrors) {
+    if name.trim().is_empty() {
+        errors.add(name_field, "Name must not be empty");
+    } else if name.chars().count() > MAX_NAME_LENGTH {
+        errors.add(
+            name_field,
+            format!("Name must be at most {MAX_NAME_LENGTH} characters"),
+        );
+    }
+}
+
+fn validate_description(description: &str, errors: &mut ValidationErrors) {
+    if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+        errors.add(
+            "description",
+            format!("Description must be at most {MAX_DESCRIPTION_LENGTH} characters"),
+        );
+    }
+}
+
+fn validate_tag_count(count: usize, errors: &mut ValidationErrors) {
+    if count > MAX_TAGS {
+        errors.add("tags", format!("A game can have at most {MAX_TAGS} tags"));
+    }
+}
+
+fn validate_image(image: &TempFile, image_type: &ImageComponent, errors: &mut ValidationErrors) {
+    let is_image = image
+        .content_type
+        .as_ref()
+        .map(|mime| mime.type_() == "image")
+        .unwrap_or(false);
+    if !is_image {
+        errors.add(
+            &image_type.filename(),
+            format!("{:?} provided is not an image", image_type),
+        );
+    }
+}
+
+async fn validate_author(
+    db: &PgPool,
+    author: &str,
+    errors: &mut ValidationErrors,
+) -> Result<(), sqlx::Error> {
+    if author.trim().is_empty() {
+        errors.add("author", "Author must not be empty");
+        return Ok(());
+    }
+    let exists = query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM users WHERE id = $1)")
+        .bind(author)
+        .fetch_one(db)
+        .await?;
+    if !exists {
+        errors.add("author", format!("Author {author} does not exist"));
+    }
+    Ok(())
+}
+
+/// Trims tag names and drops blank or repeated ones, ignoring case, keeping
+/// the first occurrence of each so game_tags never gets the same pairing twice
+fn unique_tags(tags: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    tags.into_iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty() && seen.insert(Tag::normalize_name(tag)))
+        .collect()
+}
+
+/// Looks up the stored spelling of each tag, since "action" should land on an
+/// existing "Action" tag. Tags are never created implicitly by an upload or
+/// edit, so a typo is reported as a 400 instead of leaving a dangling
+/// reference in game_tags.
+async fn resolve_tags(
+    db: &PgPool,
+    tags: &[String],
+    errors: &mut ValidationErrors,
+) -> Result<Vec<String>, sqlx::Error> {
+    // Too many tags has already been reported, so don't look them all up
+    if tags.is_empty() || tags.len() > MAX_TAGS {
+        return Ok(vec![]);
+    }
+    let normalized: Vec<String> = tags.iter().map(|tag| Tag::normalize_name(tag)).collect();
+    let known: BTreeMap<String, String> =
+        query_scalar::<_, String>("SELECT name FROM tags WHERE lower(name) = ANY($1)")
+            .bind(&normalized)
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|name| (Tag::normalize_name(&name), name))
+            .collect();
+    let unknown: Vec<&str> = tags
+        .iter()
+        .zip(&normalized)
+        .filter(|(_, normalized)| !known.contains_key(*normalized))
+        .map(|(tag, _)| tag.as_str())
+        .collect();
+    if !unknown.is_empty() {
+        errors.add("tags", format!("Unknown tags: {}", unknown.join(", ")));
+    }
+    Ok(normalized
+        .iter()
+        .filter_map(|normalized| known.get(normalized).cloned())
+        .collect())
+}
+
+async fn verify_and_upload(
+    banner: TempFile,
+    icon: TempFile,
+    s3: &Client,
+    bucket: &str,
+    uuid: &str,
+) -> Result<(), ApiError> {
+    // Either failing fails the whole upload, and the caller discards
+    // whichever image did land
+    futures::try_join!(
+        verify_and_upload_image(banner, s3, bucket, ImageComponent::Banner, uuid),
+        verify_and_upload_image(icon, s3, bucket, ImageComponent::Icon, uuid),
+    )?;
+    Ok(())
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    request_body(content=GameUploadDoc, content_type="multipart/form-data", description="Multipart Form. Contains flatpak file of game, banner, icon, name, description, and author"),
+    responses(
+        (status = 201, description = "Created new game"),
+        (status = 400, description = "Invalid fields in upload, including any tags that don't exist", body = ValidationErrors),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 403, description = "Author is not permitted to publish games", body = ErrorBody),
+        (status = 409, description = "Another game already has this name, ignoring case, and the upload wasn't forced", body = ErrorBody),
+        (status = 413, description = "Upload exceeds the maximum size", body = ErrorBody),
+        (status = 415, description = "Upload is the wrong kind of file, going by its content type", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(NewGameQuery),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("", wrap = "RequireApiKey")]
+pub async fn add_game(
+    state: Data<AppState>,
+    params: Query<NewGameQuery>,
+    MultipartForm(form): MultipartForm<GameUpload>,
+) -> Result<HttpResponse, ApiError> {
+    let tags = validate_new_game(
+        &state.db,
+        NewGame {
+            title: &form.title,
+            description: &form.description,
+            author: &form.author,
+            tags: &form.tags,
+            banner: &form.banner,
+            icon: &form.icon,
+        },
+    )
+    .await?;
+    let uuid = Uuid::new_v4().to_string();
+    // Whatever made it into the bucket before a failure would otherwise be
+    // orphaned, since no game row points at it
+    if let Err(e) = verify_and_upload(
+        form.banner,
+        form.icon,
+        &state.s3,
+        &state.games_bucket,
+        &uuid,
+    )
+    .await
+    {
+        discard_game_files(&state.s3, &state.games_bucket, &uuid).await;
+        return Err(e);
+    }
+    let game = Game {
+        id: uuid,
+        author: form.author.clone(),
+        upload_date: Local::now().date_naive(),
+        name: form.title.clone(),
+        hash: None,
+        description: form.description.clone(),
+        downloads: 0,
+        play_count: 0,
+        last_played_at: None,
+        updated_at: Utc::now(),
+        published: false,
+        size_bytes: None,
+    };
+    let inserted = async {
+        let mut transaction = state.db.begin().await?;
+        claim_name(&mut transaction, &game.name, params.force.unwrap_or(false)).await?;
+        insert_game(&mut transaction, &game, &tags).await?;
+        transaction.commit().await?;
+        Ok::<_, ApiError>(())
+    };
+    if let Err(e) = inserted.await {
+        discard_game_files(&state.s3, &state.games_bucket, &game.id).await;
+        return Err(e);
+    }
+    state.icon_sprite.invalidate();
+    state.events.publish(GameEvent::created(&game)).await;
+    Ok(HttpResponse::Created().json(game))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct NewGameQuery {
+    /// Accept the game even if another game already has its name, ignoring
+    /// case (default false)
+    force: Option<bool>,
+}
+
+/// The parts of a new game checked before anything is stored
+struct NewGame<'a> {
+    title: &'a str,
+    description: &'a str,
+    author: &'a str,
+    /// Comma separated
+    tags: &'a str,
+    banner: &'a TempFile,
+    icon: &'a TempFile,
+}
+
+/// Checks everything about a new game but its flatpak, returning its tags as
+/// they're stored
+async fn validate_new_game(db: &PgPool, game: NewGame<'_>) -> Result<Vec<String>, ApiError> {
+    check_author_permitted(game.author)?;
+    let tags = unique_tags(game.tags.split(',').map(str::to_string));
+    let mut errors = ValidationErrors::default();
+    validate_text_fields("title", game.title, game.description, &mut errors);
+    validate_tag_count(tags.len(), &mut errors);
+    validate_image(game.banner, &ImageComponent::Banner, &mut errors);
+    validate_image(game.icon, &ImageComponent::Icon, &mut errors);
+    validate_author(db, game.author, &mut errors).await?;
+    let tags = resolve_tags(db, &tags, &mut errors).await?;
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+    for (image, image_type) in [
+        (game.banner, ImageComponent::Banner),
+        (game.icon, ImageComponent::Icon),
+    ] {
+        check_upload_size(image, *MAX_IMAGE_BYTES, &format!("{:?}", image_type))?;
+        check_image_pixels(image)?;
+        check_image_shape(image, &image_type)?;
+    }
+    Ok(tags)
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    request_body(content=GameUploadDoc, content_type="multipart/form-data", description="Multipart Form. Contains flatpak file of game, banner, icon, name, description, author and tags. The game's id is the one the flatpak's app id names, edu.rit.csh.devcade.game.id-<uuid>."),
+    responses(
+        (status = 201, description = "Created and published the game, with its flatpak as version 1", body = Game),
+        (status = 400, description = "Invalid fields in upload, including any tags that don't exist, or a flatpak whose app id doesn't name a game id", body = ValidationErrors),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 403, description = "Author is not permitted to publish games", body = ErrorBody),
+        (status = 409, description = "A game with the flatpak's id already exists, or another game already has this name, ignoring case, and the upload wasn't forced", body = ErrorBody),
+        (status = 413, description = "Upload exceeds the maximum size", body = ErrorBody),
+        (status = 415, description = "Upload is the wrong kind of file, going by its content type", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(NewGameQuery),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/publish", wrap = "RequireApiKey")]
+pub async fn publish_game(
+    state: Data<AppState>,
+    params: Query<NewGameQuery>,
+    MultipartForm(form): MultipartForm<PublishedGameUpload>,
+) -> Result<HttpResponse, ApiError> {
+    let tags = validate_new_game(
+        &state.db,
+        NewGame {
+            title: &form.title,
+            description: &form.description,
+            author: &form.author,
+            tags: &form.tags,
+            banner: &form.banner,
+            icon: &form.icon,
+        },
+    )
+    .await?;
+    check_upload_size(&form.game, *MAX_GAME_BYTES, "Game")?;
+    let flatpak = load_flatpak(form.game)
+        .await
+        .and_then(|flatpak| Ok((flatpak_game_id(&flatpak.flatpak_ref)?, flatpak)))
+        .inspect_err(|e| {
+            if e.status_code().is_client_error() {
+                record_flatpak_rejection();
+            }
+        });
+    let (id, flatpak) = flatpak?;
+    let mut game = Game {
+        id,
+        author: form.author.clone(),
+        upload_date: Local::now().date_naive(),
+        name: form.title.clone(),
+        hash: None,
+        description: form.description.clone(),
+        downloads: 0,
+        play_count: 0,
+        last_played_at: None,
+        updated_at: Utc::now(),
+        published: false,
+        size_bytes: None,
+    };
+    // The game is saved as a draft before anything is uploaded, so the name
+    // and id are claimed without holding a transaction open across S3
+    let mut transaction = state.db.begin().await?;
+    claim_name(&mut transaction, &game.name, params.force.unwrap_or(false)).await?;
+    match insert_game(&mut transaction, &game, &tags).await {
+        Ok(()) => {}
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("23505") => {
+            return Err(ApiError::Conflict(format!(
+                "Game {} already exists",
+                game.id
+            )));
+        }
+        Err(e) => return Err(e.into()),
+    }
+    transaction.commit().await?;
+
+    let mut stored_blob = None;
+    let published = async {
+        let uploaded =
+            verify_and_upload_game(flatpak, &state.db, &state.s3, &state.games_bucket, &game.id)
+                .await
+                .inspect_err(|e| {
+                    if e.status_code().is_client_error() {
+                        record_flatpak_rejection();
+                    }
+                })?;
+        stored_blob = uploaded.uploaded.clone();
+        verify_and_upload(
+            form.banner,
+            form.icon,
+            &state.s3,
+            &state.games_bucket,
+            &game.id,
+        )
+        .await?;
+        let mut transaction = state.db.begin().await?;
+        let stored = record_flatpak(
+            &mut transaction,
+            &state.s3,
+            &state.games_bucket,
+            &game.id,
+            uploaded,
+        )
+        .await?;
+        stored_blob = stored.created.then(|| stored.key.clone());
+        make_flatpak_current(
+            &state.s3,
+            &state.games_bucket,
+            &game.id,
+            &stored.arch,
+            &stored.key,
+        )
+        .await?;
+        query(
+            "INSERT INTO game_versions (game_id, version, hash, key, arch, branch)
+            VALUES ($1, 1, $2, $3, $4, $5)",
+        )
+        .bind(&game.id)
+        .bind(&stored.hash)
+        .bind(&stored.key)
+        .bind(&stored.arch)
+        .bind(&stored.branch)
+        .execute(&mut transaction)
+        .await?;
+        let current = set_current_build(
+            &mut transaction,
+            &game.id,
+            &stored.arch,
+            &stored.hash,
+            Some(stored.size),
+        )
+        .await?;
+        query("UPDATE game SET published = true WHERE id = $1")
+            .bind(&game.id)
+            .execute(&mut transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok::<_, ApiError>(current)
+    }
+    .await;
+    match published {
+        Ok((updated_at, hash, size_bytes)) => {
+            game.updated_at = updated_at;
+            game.hash = hash;
+            game.size_bytes = size_bytes;
+            game.published = true;
+        }
+        Err(e) => {
+            // The draft claimed the id, so the game's files are this upload's
+            discard_game_files(&state.s3, &state.games_bucket, &game.id).await;
+            if let Some(key) = stored_blob {
+                discard_blob(&state.s3, &state.games_bucket, &key).await;
+            }
+            if let Err(e) = delete_game_rows(&state.db, &[game.id.clone()]).await {
+                log::warn!(
+                    "Failed to delete draft of unpublished game {}: {e}",
+                    game.id
+                );
+            }
+            return Err(e);
+        }
+    }
+    state.icon_sprite.invalidate();
+    state.events.publish(GameEvent::created(&game)).await;
+    Ok(HttpResponse::Created().json(game))
+}
+
+/// The id of the game a flatpak is built for, named by its app id
+fn flatpak_game_id(flatpak_ref: &str) -> Result<String, ApiError> {
+    flatpak_ref_game(flatpak_ref)
+        .filter(|id| Uuid::parse_str(id).is_ok())
+        .map(str::to_string)
+        .ok_or_else(|| {
+            ApiError::BadRequest(format!(
+                "Flatpak app id must be {APP_ID_PREFIX}<game uuid>, not {flatpak_ref}"
+            ))
+        })
+}
+
+/// Removes the files uploaded for a game that was never saved
+async fn discard_game_files(s3: &Client, bucket: &str, uuid: &str) {
+    if let Err(e) = delete_recursively(s3, bucket, uuid).await {
+        log::warn!("Failed to delete files of unsaved game {uuid}: {e}");
+    }
+}
+
+/// Rejects a name another game (that isn't deleted) already has, ignoring
+/// case, unless forced. The lock makes a concurrent upload of the same name
+/// wait for this transaction to end, so both can't pass the check.
+async fn claim_name(
+    transaction: &mut Transaction<'_, Postgres>,
+    name: &str,
+    force: bool,
+) -> Result<(), ApiError> {
+    query("SELECT pg_advisory_xact_lock(hashtext(lower($1)))")
+        .bind(name)
+        .execute(&mut *transaction)
+        .await?;
+    if force {
+        return Ok(());
+    }
+    let existing: Option<String> = query_scalar(
+        "SELECT id FROM game WHERE lower(name) = lower($1) AND deleted_at IS NULL LIMIT 1",
+    )
+    .bind(name)
+    .fetch_optional(&mut *transaction)
+    .await?;
+    match existing {
+        Some(id) => Err(ApiError::Conflict(format!(
+            "Game {id} is already named {name}"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Inserts a new game and its tags
+async fn insert_game(
+    transaction: &mut Transaction<'_, Postgres>,
+    game: &Game,
+    tags: &[String],
+) -> Result<(), sqlx::Error> {
+    query(
+        "INSERT INTO game (id, author, upload_date, name, hash, description, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(&game.id)
+    .bind(&game.author)
+    .bind(game.upload_date)
+    .bind(&game.name)
+    .bind(&game.hash)
+    .bind(&game.description)
+    .bind(game.updated_at)
+    .execute(&mut *transaction)
+    .await?;
+    for tag_name in tags {
+        query("INSERT INTO game_tags VALUES ($1, $2)")
+            .bind(&game.id)
+            .bind(tag_name)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SearchQuery {
+    /// Words to look for in game names, descriptions and author names
+    q: String,
+    /// Maximum number of games to return (default 50, max 200)
+    limit: Option<i64>,
+}
+
+/// Escapes `%`, `_` and `\` so user input matches literally in `ILIKE`
+fn escape_like(pattern: &str) -> String {
+    pattern
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Games matching the search, most relevant first", body = [GameWithTags]),
+        (status = 400, description = "Empty search", body = ValidationErrors),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    )
+)]
+#[get("/search")]
+pub async fn search_games(
+    state: Data<AppState>,
+    search: Query<SearchQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let terms = search.q.trim();
+    if terms.is_empty() {
+        let mut errors = ValidationErrors::default();
+        errors.add("q", "Search must not be empty");
+        return Err(errors.into());
+    }
+    let limit = search
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(0, MAX_PAGE_SIZE);
+    // Full-text matches are ranked by relevance; the substring match catches
+    // partial words (e.g. "brick" in "BrickBreaker") that stemming won't
+    let games: Vec<GameWithTags> = query_as(
+        "
+        SELECT game.*,
+            ROW(users.*)::users AS \"user\",
+            array_remove(ARRAY_AGG(tags.*), NULL) AS \"tags\"
+        FROM game
+        LEFT JOIN game_tags ON game_tags.game_id = game.id
+        LEFT JOIN tags ON tags.name = game_tags.tag_name
+        LEFT JOIN users ON game.author = users.id,
+        to_tsvector('english', concat_ws(' ', game.name, game.description,
+            users.first_name, users.last_name)) AS document,
+        plainto_tsquery('english', $1) AS search
+        WHERE game.published AND game.deleted_at IS NULL AND (document @@ search
+            OR game.name ILIKE '%' || $2 || '%'
+            OR game.description ILIKE '%' || $2 || '%')
+        GROUP BY game.id, users.id, document, search
+        ORDER BY ts_rank(document, search) DESC, game.name ASC
+        LIMIT $3
+        ",
+    )
+    .bind(terms)
+    .bind(escape_like(terms))
+    .bind(limit)
+    .fetch_all(&state.db)
+    .await?;
+    Ok(HttpResponse::Ok().json(games))
+}
+
+const GAME_WITH_TAGS_BY_ID: &str = "
+    SELECT game.*,
+        ROW(users.*)::users AS \"user\",
+        array_remove(ARRAY_AGG(tags.*), NULL) AS \"tags\"
+    FROM game
+    LEFT JOIN game_tags ON game_tags.game_id = game.id
+    LEFT JOIN tags ON tags.name = game_tags.tag_name
+    LEFT JOIN users ON users.id = game.author
+    WHERE game.id = $1 AND game.deleted_at IS NULL
+    GROUP BY game.id, users.id
+    ";
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Get specified game, with an ETag to send back in If-Match when editing", body = GameWithTags),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    )
+)]
+#[get("/{id}")]
+pub async fn get_game(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    let game: GameWithTags = query_as(GAME_WITH_TAGS_BY_ID)
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(ApiError::game_not_found)?;
+    Ok(HttpResponse::Ok()
+        .insert_header(ETag(game_etag(&game.updated_at)))
+        .json(game))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PopularQuery {
+    /// Maximum number of games to return (default 10, max 50)
+    limit: Option<i64>,
+    /// `rating` ranks by average rating instead, with unrated games last
+    /// (default `downloads`)
+    #[param(inline)]
+    sort: Option<PopularSort>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PopularSort {
+    /// Most downloaded games first
+    #[default]
+    Downloads,
+    /// Highest average rating first, more ratings breaking ties
+    Rating,
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Most downloaded or, with sort=rating, highest rated games first", body = [GameWithTags]),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(PopularQuery)
+)]
+#[get("/popular")]
+pub async fn get_popular_games(
+    state: Data<AppState>,
+    params: Query<PopularQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let limit = params.limit.unwrap_or(10).clamp(0, 50);
+    let order = match params.sort.unwrap_or_default() {
+        PopularSort::Downloads => "game.downloads DESC",
+        PopularSort::Rating => {
+            "ratings.average DESC NULLS LAST, ratings.count DESC NULLS LAST, game.downloads DESC"
+        }
+    };
+    // Ratings are totalled before joining the tags, which would otherwise
+    // count each rating once per tag
+    let games: Vec<GameWithTags> = query_as(&format!(
+        "
+        SELECT game.*,
+            ROW(users.*)::users AS \"user\",
+            array_remove(ARRAY_AGG(tags.*), NULL) AS \"tags\"
+        FROM game
+        LEFT JOIN (
+            SELECT game_id, AVG(score) AS average, COUNT(*) AS count
+            FROM game_ratings
+            GROUP BY game_id
+        ) ratings ON ratings.game_id = game.id
+        LEFT JOIN game_tags ON game_tags.game_id = game.id
+        LEFT JOIN tags ON tags.name = game_tags.tag_name
+        LEFT JOIN users ON users.id = game.author
+        WHERE game.published AND game.deleted_at IS NULL
+        GROUP BY game.id, users.id, ratings.average, ratings.count
+        ORDER BY {order}, game.name ASC
+        LIMIT $1
+        "
+    ))
+    .bind(limit)
+    .fetch_all(&state.db)
+    .await?;
+    Ok(HttpResponse::Ok().json(games))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct RecentlyPlayedQuery {
+    /// Maximum number of games to return (default 10, max 50)
+    limit: Option<i64>,
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Games most recently played on a cabinet first, leaving out any never played", body = [GameWithTags]),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(RecentlyPlayedQuery)
+)]
+#[get("/recently-played")]
+pub async fn get_recently_played_games(
+    state: Data<AppState>,
+    params: Query<RecentlyPlayedQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let limit = params.limit.unwrap_or(10).clamp(0, 50);
+    let games: Vec<GameWithTags> = query_as(
+        "
+        SELECT game.*,
+            ROW(users.*)::users AS \"user\",
+            array_remove(ARRAY_AGG(tags.*), NULL) AS \"tags\"
+        FROM game
+        LEFT JOIN game_tags ON game_tags.game_id = game.id
+        LEFT JOIN tags ON tags.name = game_tags.tag_name
+        LEFT JOIN users ON users.id = game.author
+        WHERE game.deleted_at IS NULL AND game.published AND game.last_played_at IS NOT NULL
+        GROUP BY game.id, users.id
+        ORDER BY game.last_played_at DESC, game.name ASC
+        LIMIT $1
+        ",
+    )
+    .bind(limit)
+    .fetch_all(&state.db)
+    .await?;
+    Ok(HttpResponse::Ok().json(games))
+}
+
+/// How often an idle event stream sends a comment, so proxies don't close it
+const LIVE_KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+/// Formats a server-sent event named after the kind of change
+fn live_event_message(event: &GameEvent) -> String {
+    match serde_json::to_string(event) {
+        Ok(data) => format!("event: {}\ndata: {data}\n\n", event.event.as_str()),
+        Err(e) => {
+            log::error!("Couldn't serialize game event: {e}");
+            String::new()
+        }
+    }
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Server-sent events, one per game created, updated or deleted from now on, named after the change and with the event as data. Drafts are left out. A `lagged` event means some were missed, so the list should be refetched.", content_type = "text/event-stream", body = GameEvent),
+    )
+)]
+#[get("/events")]
+pub async fn get_game_events(state: Data<AppState>) -> HttpResponse {
+    let events = stream::unfold(state.events.subscribe(), |mut events| async move {
+        loop {
+            let message = match timeout(LIVE_KEEP_ALIVE, events.recv()).await {
+                Err(_) => ": keep-alive\n\n".to_string(),
+                Ok(Ok(event)) if event.game.as_ref().is_some_and(|game| !game.published) => {
+                    continue
+                }
+                Ok(Ok(event)) => live_event_message(&event),
+                Ok(Err(RecvError::Lagged(missed))) => {
+                    format!("event: lagged\ndata: {missed}\n\n")
+                }
+                Ok(Err(RecvError::Closed)) => return None,
+            };
+            return Some((Ok::<_, Infallible>(Bytes::from(message)), events));
+        }
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .streaming(events)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SimilarQuery {
+    /// Maximum number of games to return (default 5, max 50)
+    limit: Option<i64>,
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Games sharing the most tags with the specified game", body = [GameWithTags]),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game"),
+        SimilarQuery,
+    )
+)]
+#[get("/{id}/similar")]
+pub async fn get_similar_games(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+    params: Query<SimilarQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    let limit = params.limit.unwrap_or(5).clamp(0, 50);
+    require_game(&state.db, &id).await?;
+    let games: Vec<GameWithTags> = query_as(
+        "
+        WITH overlap AS (
+            SELECT other.game_id, COUNT(*) AS shared
+            FROM game_tags AS own
+            JOIN game_tags AS other
+                ON other.tag_name = own.tag_name AND other.game_id <> own.game_id
+            WHERE own.game_id = $1
+            GROUP BY other.game_id
+        )
+        SELECT game.*,
+            ROW(users.*)::users AS \"user\",
+            array_remove(ARRAY_AGG(tags.*), NULL) AS \"tags\"
+        FROM overlap
+        JOIN game ON game.id = overlap.game_id
+        LEFT JOIN game_tags ON game_tags.game_id = game.id
+        LEFT JOIN tags ON tags.name = game_tags.tag_name
+        LEFT JOIN users ON users.id = game.author
+        WHERE game.published AND game.deleted_at IS NULL
+        GROUP BY game.id, users.id, overlap.shared
+        ORDER BY overlap.shared DESC, game.name ASC
+        LIMIT $2
+        ",
+    )
+    .bind(&id)
+    .bind(limit)
+    .fetch_all(&state.db)
+    .await?;
+    Ok(HttpResponse::Ok().json(games))
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    request_body(content=GameData, content_type="application/json", description="JSON with name, desc, and author"),
+    responses(
+        (status = 200, description = "Updated game, with its tags", body = GameWithTags),
+        (status = 400, description = "Invalid fields, including any tags or author that don't exist. A malformed game id is reported as an ErrorBody instead", body = ValidationErrors),
+        (status = 404, description = "Missing game", body = ErrorBody),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 412, description = "Game was modified after the version given in If-Match/If-Unmodified-Since", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the game version being edited"),
+        ("If-Unmodified-Since" = Option<String>, Header, description = "Only edit if the game has not changed since this HTTP date"),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[put("/{id}", wrap = "RequireApiKey")]
+pub async fn edit_game(
+    req: HttpRequest,
+    state: Data<AppState>,
+    path: Path<(String,)>,
+    game_data: Json<GameData>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    let GameData {
+        name,
+        description,
+        author,
+        tags,
+    } = game_data.into_inner();
+    let changes = GamePatch {
+        name: Some(name),
+        description: Some(description),
+        author,
+        tags: Some(tags),
+    };
+    update_game(&req, &state, id, changes).await
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    request_body(content=GamePatch, content_type="application/json", description="JSON with only the fields to change. Absent and null fields are both left unchanged, so tags are only replaced when a list is given."),
+    responses(
+        (status = 200, description = "Updated game, with its tags", body = GameWithTags),
+        (status = 400, description = "Invalid fields, including any tags or author that don't exist. A malformed game id is reported as an ErrorBody instead", body = ValidationErrors),
+        (status = 404, description = "Missing game", body = ErrorBody),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 412, description = "Game was modified after the version given in If-Match/If-Unmodified-Since", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the game version being edited"),
+        ("If-Unmodified-Since" = Option<String>, Header, description = "Only edit if the game has not changed since this HTTP date"),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[patch("/{id}", wrap = "RequireApiKey")]
+pub async fn patch_game(
+    req: HttpRequest,
+    state: Data<AppState>,
+    path: Path<(String,)>,
+    changes: Json<GamePatch>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    update_game(&req, &state, id, changes.into_inner()).await
+}
+
+/// Applies every field of `changes` that's set, validating them all before
+/// anything is written
+async fn update_game(
+    req: &HttpRequest,
+    state: &AppState,
+    id: String,
+    changes: GamePatch,
+) -> Result<HttpResponse, ApiError> {
+    let mut errors = ValidationErrors::default();
+    if let Some(name) = &changes.name {
+        validate_name("name", name, &mut errors);
+    }
+    if let Some(description) = &changes.description {
+        validate_description(description, &mut errors);
+    }
+    let tags = changes.tags.map(unique_tags);
+    if let Some(tags) = &tags {
+        validate_tag_count(tags.len(), &mut errors);
+    }
+    if let Some(author) = &changes.author {
+        validate_author(&state.db, author, &mut errors).await?;
+    }
+    let tags = match tags {
+        Some(tags) => Some(resolve_tags(&state.db, &tags, &mut errors).await?),
+        None => None,
+    };
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+    // Dropping the transaction on any error rolls back the whole edit
+    let mut transaction = state.db.begin().await?;
+    // Locked so no other edit can land between the version check and the update
+    let game =
+        query_as::<_, Game>("SELECT * FROM game WHERE id = $1 AND deleted_at IS NULL FOR UPDATE")
+            .bind(&id)
+            .fetch_optional(&mut transaction)
+            .await?
+            .ok_or_else(ApiError::game_not_found)?;
+    if let Err(response) = check_preconditions(req, &game.updated_at) {
+        return Ok(response);
+    }
+    let game = query_as::<_, Game>(
+        "UPDATE game SET name = COALESCE($1, name), description = COALESCE($2, description),
+            author = COALESCE($3, author), updated_at = now()
+        WHERE id = $4
+        RETURNING *",
+    )
+    .bind(&changes.name)
+    .bind(&changes.description)
+    .bind(&changes.author)
+    .bind(&id)
+    .fetch_one(&mut transaction)
+    .await?;
+    if let Some(tags) = tags {
+        query("DELETE FROM game_tags WHERE game_id = $1")
+            .bind(&id)
+            .execute(&mut transaction)
+            .await?;
+        for tag_name in tags {
+            query("INSERT INTO game_tags VALUES ($1, $2)")
+                .bind(&id)
+                .bind(tag_name)
+                .execute(&mut transaction)
+                .await?;
+        }
+    }
+    // Read back within the transaction so the response has exactly the tags
+    // this edit committed
+    let game_with_tags = query_as::<_, GameWithTags>(GAME_WITH_TAGS_BY_ID)
+        .bind(&id)
+        .fetch_one(&mut transaction)
+        .await?;
+    transaction.commit().await?;
+    state.events.publish(GameEvent::updated(&game)).await;
+    Ok(HttpResponse::Ok()
+        .insert_header(ETag(game_etag(&game.updated_at)))
+        .json(game_with_tags))
+}
+
+/// Game ids are hyphenated UUIDs, so anything else is turned away before it
+/// reaches the database and a 404 only ever means no game has the id
+pub fn check_game_id(id: &str) -> Result<(), ApiError> {
+    // Uuid also parses the simple, braced and URN forms, which are longer or
+    // shorter than the hyphenated one
+    if id.len() != 36 || Uuid::parse_str(id).is_err() {
+        return Err(ApiError::BadRequest("Invalid game id format".to_string()));
+    }
+    Ok(())
+}
+
+/// The game id from a `/{id}` path, checked by [`check_game_id`]
+fn game_id(path: Path<(String,)>) -> Result<String, ApiError> {
+    let (id,) = path.into_inner();
+    check_game_id(&id)?;
+    Ok(id)
+}
+
+/// Lets a route bail out with a 404 before touching S3 for a game that
+/// doesn't exist
+async fn require_game(db: &PgPool, id: &str) -> Result<(), ApiError> {
+    if !game_exists(db, id).await? {
+        return Err(ApiError::game_not_found());
+    }
+    Ok(())
+}
+
+async fn game_exists(db: &PgPool, id: &str) -> Result<bool, sqlx::Error> {
+    query_scalar("SELECT EXISTS (SELECT 1 FROM game WHERE id = $1 AND deleted_at IS NULL)")
+        .bind(id)
+        .fetch_one(db)
+        .await
+}
+
+/// Fetches a game, treating a missing row as a 404
+async fn find_game(db: &PgPool, id: &str) -> Result<Game, ApiError> {
+    match query_as::<_, Game>("SELECT * FROM game WHERE id = $1 AND deleted_at IS NULL")
+        .bind(id)
+        .fetch_one(db)
+        .await
+    {
+        Ok(game) => Ok(game),
+        Err(sqlx::Error::RowNotFound) => Err(ApiError::game_not_found()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// S3 treats deleting a key that doesn't exist as success, but some
+/// S3-compatible stores answer with NoSuchKey or a bare 404 instead
+fn is_missing_key<E: S3ErrorCode>(error: &SdkError<E>) -> bool {
+    match error {
+        SdkError::ServiceError { err, raw } => {
+            err.code() == Some("NoSuchKey") || raw.http().status().as_u16() == 404
+        }
+        _ => false,
+    }
+}
+
+/// The operation errors all carry a code, but the trait they share for it
+/// isn't exported by the SDK
+trait S3ErrorCode {
+    fn code(&self) -> Option<&str>;
+}
+
+impl S3ErrorCode for DeleteObjectError {
+    fn code(&self) -> Option<&str> {
+        self.meta().code()
+    }
+}
+
+impl S3ErrorCode for HeadObjectError {
+    fn code(&self) -> Option<&str> {
+        self.meta().code()
+    }
+}
+
+impl S3ErrorCode for GetObjectError {
+    fn code(&self) -> Option<&str> {
+        self.meta().code()
+    }
+}
+
+/// Fetches a game's object, answering with `missing` if it was never uploaded
+/// so that only a failure to reach S3 is a 500
+async fn get_game_object(
+    s3: &Client,
+    bucket: &str,
+    key: String,
+    missing: impl FnOnce() -> ApiError,
+) -> Result<GetObjectOutput, ApiError> {
+    get_game_object_range(s3, bucket, key, None, missing).await
+}
+
+/// Like [`get_game_object`], fetching only `range` (an HTTP `Range` value)
+/// when one is given
+async fn get_game_object_range(
+    s3: &Client,
+    bucket: &str,
+    key: String,
+    range: Option<String>,
+    missing: impl FnOnce() -> ApiError,
+) -> Result<GetObjectOutput, ApiError> {
+    let download = || {
+        s3.get_object()
+            .bucket(bucket)
+            .key(&key)
+            .set_range(range.clone())
+            .send()
+    };
+    match retry_s3("get_object", download).await {
+        Ok(objout) => Ok(objout),
+        Err(e) if is_missing_key(&e) => Err(missing()),
+        Err(SdkError::ServiceError { err, .. }) if err.code() == Some("InvalidRange") => Err(
+            ApiError::RangeNotSatisfiable("Requested range is beyond the end of the file".into()),
+        ),
+        Err(e) => Err(ApiError::internal(e)),
+    }
+}
+
+/// Every S3 object a game can have
+fn game_object_keys(id: &str) -> Vec<String> {
+    let mut keys: Vec<String> = FLATPAK_ARCHES
+        .iter()
+        .filter(|arch| *arch != DEFAULT_ARCH)
+        .map(|arch| flatpak_key(id, arch))
+        .collect();
+    keys.extend([
+        flatpak_key(id, DEFAULT_ARCH),
+        format!("{}/icon", id),
+        format!("{}/icon_thumb", id),
+        format!("{}/banner", id),
+        format!("{}/banner.webp", id),
+    ]);
+    keys
+}
+
+/// Deletes every object stored for a game. Objects that are already gone
+/// don't count as failures, so partially uploaded games can still be removed.
+async fn delete_recursively(
+    s3: &Client,
+    bucket: &str,
+    id: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for key in game_object_keys(id) {
+        let delete = || s3.delete_object().bucket(bucket).key(&key).send();
+        match retry_s3("delete_object", delete).await {
+            Err(e) if !is_missing_key(&e) => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DeleteQuery {
+    /// Purge the game's files and rows for good instead of hiding it until
+    /// it's restored (default false)
+    hard: Option<bool>,
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Delete game"),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game, or already deleted unless purging", body = ErrorBody),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 412, description = "Game was modified after the version given in If-Match/If-Unmodified-Since", body = ErrorBody),
+        (status = 500, description = "Error Created by Deletion", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the game version being deleted"),
+        ("If-Unmodified-Since" = Option<String>, Header, description = "Only delete if the game has not changed since this HTTP date"),
+        DeleteQuery,
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[delete("/{id}", wrap = "RequireApiKey")]
+pub async fn delete_game(
+    req: HttpRequest,
+    state: Data<AppState>,
+    path: Path<(String,)>,
+    params: Query<DeleteQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    let hard = params.hard.unwrap_or(false);
+    let game: Option<(DateTime<Utc>, bool)> =
+        query_as("SELECT updated_at, deleted_at IS NOT NULL FROM game WHERE id = $1")
+            .bind(&id)
+            .fetch_optional(&state.db)
+            .await?;
+    // A deleted game is gone as far as everything but restoring and purging
+    // is concerned
+    let Some((updated_at, already_deleted)) = game.filter(|(_, deleted)| hard || !deleted) else {
+        return Err(ApiError::game_not_found());
+    };
+    if let Err(response) = check_preconditions(&req, &updated_at) {
+        return Ok(response);
+    }
+    if hard {
+        delete_recursively(&state.s3, &state.games_bucket, &id)
+            .await
+            .map_err(ApiError::internal)?;
+        let blobs = version_keys(&state.db, std::slice::from_ref(&id)).await?;
+        query("DELETE FROM game WHERE id = $1")
+            .bind(&id)
+            .execute(&state.db)
+            .await?;
+        query("DELETE FROM game_tags WHERE game_id = $1")
+            .bind(&id)
+            .execute(&state.db)
+            .await?;
+        release_blobs(&state.db, &state.s3, &state.games_bucket, blobs).await;
+    } else {
+        query("UPDATE game SET deleted_at = now(), updated_at = now() WHERE id = $1")
+            .bind(&id)
+            .execute(&state.db)
+            .await?;
+    }
+    state.icon_sprite.invalidate();
+    if !already_deleted {
+        state.events.publish(GameEvent::deleted(&id)).await;
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Restored game", body = Game),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game, or it was purged", body = ErrorBody),
+        (status = 409, description = "Game isn't deleted, or another game has taken its name since", body = ErrorBody),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game"),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/{id}/restore", wrap = "RequireApiKey")]
+pub async fn restore_game(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    let mut transaction = state.db.begin().await?;
+    let name: Option<String> =
+        query_scalar("SELECT name FROM game WHERE id = $1 AND deleted_at IS NOT NULL FOR UPDATE")
+            .bind(&id)
+            .fetch_optional(&mut transaction)
+            .await?;
+    let Some(name) = name else {
+        return Err(if game_exists(&state.db, &id).await? {
+            ApiError::Conflict("Game is not deleted".to_string())
+        } else {
+            ApiError::game_not_found()
+        });
+    };
+    // Its name was free for the taking while it was deleted
+    claim_name(&mut transaction, &name, false).await?;
+    let game: Game =
+        query_as("UPDATE game SET deleted_at = NULL, updated_at = now() WHERE id = $1 RETURNING *")
+            .bind(&id)
+            .fetch_one(&mut transaction)
+            .await?;
+    transaction.commit().await?;
+    state.icon_sprite.invalidate();
+    // Consumers forgot the game when it was deleted, so it comes back new
+    state.events.publish(GameEvent::created(&game)).await;
+    Ok(HttpResponse::Ok().json(game))
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchDeleteStatus {
+    Deleted,
+    NotFound,
+    /// Removing the game's files or rows failed, so it may be partly deleted
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct BatchDeleteResult {
+    #[schema(example = "9b4e2ff4-a6d6-4fbe-8d5c-0d3e9a0d1c1e")]
+    pub id: String,
+    pub status: BatchDeleteStatus,
+}
+
+/// Deletes the rows of every game given in one transaction, returning the ids
+/// that were actually removed
+async fn delete_game_rows(db: &PgPool, ids: &[String]) -> Result<HashSet<String>, sqlx::Error> {
+    let mut transaction = db.begin().await?;
+    query("DELETE FROM game_tags WHERE game_id = ANY($1)")
+        .bind(ids)
+        .execute(&mut transaction)
+        .await?;
+    let deleted = query_scalar("DELETE FROM game WHERE id = ANY($1) RETURNING id")
+        .bind(ids)
+        .fetch_all(&mut transaction)
+        .await?;
+    transaction.commit().await?;
+    Ok(deleted.into_iter().collect())
+}
+
+/// Hides every game given that isn't already deleted, in one statement,
+/// returning the ids that were
+async fn soft_delete_games(db: &PgPool, ids: &[String]) -> Result<HashSet<String>, sqlx::Error> {
+    let deleted = query_scalar(
+        "UPDATE game SET deleted_at = now(), updated_at = now()
+        WHERE id = ANY($1) AND deleted_at IS NULL
+        RETURNING id",
+    )
+    .bind(ids)
+    .fetch_all(db)
+    .await?;
+    Ok(deleted.into_iter().collect())
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    request_body = Vec<String>,
+    responses(
+        (status = 200, description = "What happened to each game, in the order given. Games already deleted are not found unless purging", body = Vec<BatchDeleteResult>),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(DeleteQuery),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/batch-delete", wrap = "RequireApiKey")]
+pub async fn batch_delete_games(
+    state: Data<AppState>,
+    ids: Json<Vec<String>>,
+    params: Query<DeleteQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let hard = params.hard.unwrap_or(false);
+    let mut seen = HashSet::new();
+    let ids: Vec<String> = ids
+        .into_inner()
+        .into_iter()
+        .filter(|id| seen.insert(id.clone()))
+        .collect();
+    let mut statuses = BTreeMap::new();
+
+    if !hard {
+        match soft_delete_games(&state.db, &ids).await {
+            Ok(deleted) => {
+                for id in &ids {
+                    let status = if deleted.contains(id) {
+                        state.events.publish(GameEvent::deleted(id)).await;
+                        BatchDeleteStatus::Deleted
+                    } else {
+                        BatchDeleteStatus::NotFound
+                    };
+                    statuses.insert(id, status);
+                }
+                state.icon_sprite.invalidate();
+            }
+            Err(e) => {
+                log::error!("Failed to delete games {ids:?}: {e}");
+                for id in &ids {
+                    statuses.insert(id, BatchDeleteStatus::Failed);
+                }
+            }
+        }
+        return Ok(HttpResponse::Ok().json(batch_delete_results(&ids, &statuses)));
+    }
+
+    // Games that were already deleted can still be purged, but consumers
+    // forgot them back then
+    let existing: HashMap<String, bool> = query_as::<_, (String, bool)>(
+        "SELECT id, deleted_at IS NOT NULL FROM game WHERE id = ANY($1)",
+    )
+    .bind(&ids)
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .collect();
+
+    // A game whose files couldn't all be removed keeps its rows, so it can
+    // still be found and deleted again later
+    let mut removable = Vec::new();
+    for id in &ids {
+        if !existing.contains_key(id) {
+            statuses.insert(id, BatchDeleteStatus::NotFound);
+        } else if let Err(e) = delete_recursively(&state.s3, &state.games_bucket, id).await {
+            log::warn!("Failed to delete files of game {id}: {e}");
+            statuses.insert(id, BatchDeleteStatus::Failed);
+        } else {
+            removable.push(id.clone());
+        }
+    }
+    if !removable.is_empty() {
+        let blobs = version_keys(&state.db, &removable).await?;
+        match delete_game_rows(&state.db, &removable).await {
+            Ok(deleted) => {
+                release_blobs(&state.db, &state.s3, &state.games_bucket, blobs).await;
+                for id in &removable {
+                    let status = if deleted.contains(id) {
+                        if !existing[id] {
+                            state.events.publish(GameEvent::deleted(id)).await;
+                        }
+                        BatchDeleteStatus::Deleted
+                    } else {
+                        BatchDeleteStatus::NotFound
+                    };
+                    statuses.insert(id, status);
+                }
+                state.icon_sprite.invalidate();
+            }
+            Err(e) => {
+                log::error!("Failed to delete games {removable:?}: {e}");
+                for id in &removable {
+                    statuses.insert(id, BatchDeleteStatus::Failed);
+                }
+            }
+        }
+    }
+    Ok(HttpResponse::Ok().json(batch_delete_results(&ids, &statuses)))
+}
+
+fn batch_delete_results(
+    ids: &[String],
+    statuses: &BTreeMap<&String, BatchDeleteStatus>,
+) -> Vec<BatchDeleteResult> {
+    ids.iter()
+        .map(|id| BatchDeleteResult {
+            id: id.clone(),
+            status: statuses[id],
+        })
+        .collect()
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Provide game source flatpak", content_type="application/vnd.flatpak",
+            headers(
+                ("x-flatpak-sha256" = String, description = "Hash recorded at upload, to check the download against"),
+                ("Content-Disposition" = String, description = "Attachment named after the game"),
+                ("Accept-Ranges" = String, description = "Always `bytes`, since downloads can be resumed with a `Range` header"),
+            )),
+        (status = 206, description = "The requested range of the game's flatpak", content_type="application/vnd.flatpak",
+            headers(
+                ("Content-Range" = String, description = "Which bytes of the flatpak are sent, and its total size"),
+            )),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game or flatpak", body = ErrorBody),
+        (status = 416, description = "Range starts beyond the end of the flatpak", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game"),
+        ("Range" = Option<String>, Header, description = "A single byte range to resume a download from, e.g. `bytes=1048576-`"),
+        ArchQuery,
+    ),
+)]
+#[get("/{id}/game")]
+pub async fn get_binary(
+    req: HttpRequest,
+    state: Data<AppState>,
+    path: Path<(String,)>,
+    params: Query<ArchQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    let game = find_game(&state.db, &id).await?;
+    let hash = current_hash(&state.db, &game, params.arch()).await?;
+    let range = requested_range(&req);
+    // Resuming a download doesn't make it another one
+    let resumed = range
+        .as_deref()
+        .is_some_and(|range| !range.starts_with("bytes=0-"));
+    let objout = get_game_object_range(
+        &state.s3,
+        &state.games_bucket,
+        flatpak_key(&id, params.arch()),
+        range,
+        ApiError::flatpak_not_found,
+    )
+    .await?;
+    if !resumed {
+        count_download(&state.db, &id).await;
+    }
+    let mut response = match objout.content_range() {
+        Some(content_range) => {
+            let mut response = HttpResponse::PartialContent();
+            response.insert_header((header::CONTENT_RANGE, content_range));
+            response
+        }
+        None => HttpResponse::Ok(),
+    };
+    response.insert_header((header::ACCEPT_RANGES, "bytes"));
+    if let Ok(length) = u64::try_from(objout.content_length()) {
+        response.no_chunking(length);
+    }
+    response.insert_header(ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters: vec![DispositionParam::Filename(download_filename(
+            &game.name, &id,
+        ))],
+    });
+    if let Some(hash) = hash {
+        response.insert_header((FLATPAK_HASH_HEADER, hash));
+    }
+    Ok(response.streaming(objout.body))
+}
+
+/// The single `bytes=` range a `Range` header asks for, to pass on to S3.
+/// Anything else, including a list of ranges, is ignored and the whole file
+/// sent, which RFC 9110 allows.
+fn requested_range(req: &HttpRequest) -> Option<String> {
+    let range = req.headers().get(header::RANGE)?.to_str().ok()?.trim();
+    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+    // An empty bound is open, anything else has to be a plain number
+    let bound = |bound: &str| match bound {
+        "" => Some(None),
+        bound if bound.bytes().all(|b| b.is_ascii_digit()) => bound.parse::<u64>().ok().map(Some),
+        _ => None,
+    };
+    match (bound(start)?, bound(end)?) {
+        (None, None) => None,
+        (Some(start), Some(end)) if start > end => None,
+        _ => Some(range.to_string()),
+    }
+}
+
+#[test]
+fn requested_range_accepts_only_single_byte_ranges() {
+    let range = |value: &str| {
+        requested_range(
+            &actix_web::test::TestRequest::default()
+                .insert_header((header::RANGE, value))
+                .to_http_request(),
+        )
+    };
+    assert_eq!(range("bytes=100-"), Some("bytes=100-".to_string()));
+    assert_eq!(range("bytes=0-99"), Some("bytes=0-99".to_string()));
+    assert_eq!(range("bytes=-500"), Some("bytes=-500".to_string()));
+    assert_eq!(range("bytes=-"), None);
+    assert_eq!(range("bytes=99-0"), None);
+    assert_eq!(range("bytes=0-9,20-29"), None);
+    assert_eq!(range("items=0-9"), None);
+    assert_eq!(range("bytes=+1-"), None);
+}
+
+/// A lost count isn't worth failing the download over
+async fn count_download(db: &PgPool, id: &str) {
+    if let Err(e) = query("UPDATE game SET downloads = downloads + 1 WHERE id = $1")
+        .bind(id)
+        .execute(db)
+        .await
+    {
+        log::warn!("Failed to count download of game {id}: {e}");
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct DownloadUrl {
+    /// Presigned S3 URL of the flatpak, usable without credentials until it
+    /// expires
+    #[schema(
+        example = "https://s3.csh.rit.edu/devcade-games/9b4e2ff4-a6d6-4fbe-8d5c-0d3e9a0d1c1e/9b4e2ff4-a6d6-4fbe-8d5c-0d3e9a0d1c1e.flatpak?X-Amz-Signature=..."
+    )]
+    pub url: String,
+    #[schema(example = "2023-04-02T18:35:00Z")]
+    pub expires_at: DateTime<Utc>,
+    /// Hex SHA-256 commit checksum of the flatpak, as recorded at upload
+    #[schema(example = "5ec8f244899431af8effad9e7ec9b2543226c78f")]
+    pub hash: String,
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Short-lived URL to download the game's flatpak straight from S3", body = DownloadUrl),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game or flatpak", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game"),
+        ArchQuery,
+    ),
+)]
+#[get("/{id}/download-url")]
+pub async fn get_download_url(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+    params: Query<ArchQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    let game = find_game(&state.db, &id).await?;
+    let hash = current_hash(&state.db, &game, params.arch())
+        .await?
+        .ok_or_else(ApiError::flatpak_not_found)?;
+    let expiry = *DOWNLOAD_URL_EXPIRY;
+    // Signing happens locally, so the download is counted here rather than
+    // when S3 serves it
+    let request = state
+        .s3
+        .get_object()
+        .bucket(&state.games_bucket)
+        .key(flatpak_key(&id, params.arch()))
+        .response_content_disposition(format!(
+            "attachment; filename=\"{}\"",
+            download_filename(&game.name, &id)
+        ))
+        .presigned(PresigningConfig::expires_in(expiry).map_err(ApiError::internal)?)
+        .await
+        .map_err(ApiError::internal)?;
+    count_download(&state.db, &id).await;
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(DownloadUrl {
+            url: request.uri().to_string(),
+            expires_at: Utc::now() + chrono::Duration::from_std(expiry).unwrap(),
+            hash,
+        }))
+}
+
+/// Names the downloaded flatpak after the game, replacing anything that isn't
+/// safe in a filename (or a header) on every platform
+fn download_filename(name: &str, id: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_ascii_graphic() || c == ' ' => c,
+            _ => '_',
+        })
+        .collect();
+    // Leading dots would hide the file, and Windows drops trailing ones
+    let name = name.trim().trim_matches('.');
+    if name.is_empty() {
+        format!("{id}.flatpak")
+    } else {
+        format!("{name}.flatpak")
+    }
+}
+
+#[test]
+fn shape_problem_hints_at_swapped_images() {
+    assert!(shape_problem(&ImageComponent::Banner, 1920, 1080).is_none());
+    assert!(shape_problem(&ImageComponent::Icon, 512, 512).is_none());
+    let problem = shape_problem(&ImageComponent::Banner, 512, 512).unwrap();
+    assert!(
+        problem.ends_with("It's shaped like an icon, so check the banner and icon weren't swapped")
+    );
+    let problem = shape_problem(&ImageComponent::Icon, 1920, 1080).unwrap();
+    assert!(problem.contains("shaped like a banner"));
+    // Too tall for either
+    let problem = shape_problem(&ImageComponent::Icon, 100, 400).unwrap();
+    assert!(!problem.contains("swapped"));
+}
+
+#[test]
+fn accepts_webp_only_when_listed() {
+    let accepts = |accept: &str| {
+        let req = actix_web::test::TestRequest::default()
+            .insert_header((header::ACCEPT, accept))
+            .to_http_request();
+        accepts_webp(&req)
+    };
+    assert!(accepts("image/avif,image/webp,*/*;q=0.8"));
+    assert!(!accepts("image/png,*/*;q=0.8"));
+    assert!(!accepts("image/webp;q=0"));
+    assert!(!accepts_webp(
+        &actix_web::test::TestRequest::default().to_http_request()
+    ));
+}
+
+#[test]
+fn download_filename_is_sanitized_game_name() {
+    let id = "9b4e2ff4-a6d6-4fbe-8d5c-0d3e9a0d1c1e";
+    assert_eq!(
+        download_filename("Brick Breaker", id),
+        "Brick Breaker.flatpak"
+    );
+    assert_eq!(
+        download_filename("AC/DC: \"Live\"?", id),
+        "AC_DC_ _Live__.flatpak"
+    );
+    assert_eq!(download_filename("Café\n", id), "Caf__.flatpak");
+    assert_eq!(download_filename(" .. ", id), format!("{id}.flatpak"));
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct FlatpakHash {
+    #[schema(example = "9b4e2ff4-a6d6-4fbe-8d5c-0d3e9a0d1c1e")]
+    pub id: String,
+    /// Hex SHA-256 commit checksum of the flatpak, as recorded at upload
+    #[schema(example = "5ec8f244899431af8effad9e7ec9b2543226c78f")]
+    pub hash: String,
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Hash recorded when the game's flatpak was uploaded", body = FlatpakHash),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game, or the game has no flatpak yet", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game"),
+        ArchQuery,
+    ),
+)]
+#[get("/{id}/hash")]
+pub async fn get_binary_hash(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+    params: Query<ArchQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    let game = find_game(&state.db, &id).await?;
+    let hash = current_hash(&state.db, &game, params.arch())
+        .await?
+        .ok_or_else(ApiError::flatpak_not_found)?;
+    Ok(HttpResponse::Ok().json(FlatpakHash { id: game.id, hash }))
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct AssetStatus {
+    pub present: bool,
+    /// Size of the stored object in bytes, when present
+    #[schema(example = 52428800)]
+    pub size: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct GameStatus {
+    #[schema(example = "9b4e2ff4-a6d6-4fbe-8d5c-0d3e9a0d1c1e")]
+    pub id: String,
+    /// Hash recorded when the flatpak was uploaded
+    #[schema(example = "5ec8f244899431af8effad9e7ec9b2543226c78f")]
+    pub hash: Option<String>,
+    /// Size recorded when the flatpak was uploaded
+    #[schema(example = 356515840)]
+    pub size_bytes: Option<i64>,
+    pub flatpak: AssetStatus,
+    pub banner: AssetStatus,
+    pub icon: AssetStatus,
+}
+
+pub async fn asset_status(s3: &Client, bucket: &str, key: String) -> Result<AssetStatus, ApiError> {
+    let head = || s3.head_object().bucket(bucket).key(&key).send();
+    match retry_s3("head_object", head).await {
+        Ok(head) => Ok(AssetStatus {
+            present: true,
+            size: Some(head.content_length()),
+        }),
+        Err(e) if is_missing_key(&e) => Ok(AssetStatus {
+            present: false,
+            size: None,
+        }),
+        Err(e) => Err(ApiError::internal(e)),
+    }
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Which of the game's files are stored, and how large they are, with the flatpak being the one served for the architecture asked for", body = GameStatus),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game", body = ErrorBody),
+        (status = 500, description = "Error Created by Query, or S3 couldn't be reached", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game"),
+        ArchQuery,
+    ),
+)]
+#[get("/{id}/status")]
+pub async fn get_game_status(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+    params: Query<ArchQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    let game = find_game(&state.db, &id).await?;
+    let (hash, size_bytes) = current_build(&state.db, &game, params.arch())
+        .await?
+        .map_or((None, None), |(hash, size)| (Some(hash), size));
+    let (flatpak, banner, icon) = futures::try_join!(
+        asset_status(
+            &state.s3,
+            &state.games_bucket,
+            flatpak_key(&id, params.arch())
+        ),
+        asset_status(&state.s3, &state.games_bucket, format!("{}/banner", id)),
+        asset_status(&state.s3, &state.games_bucket, format!("{}/icon", id)),
+    )?;
+    Ok(HttpResponse::Ok().json(GameStatus {
+        id: game.id,
+        hash,
+        size_bytes,
+        flatpak,
+        banner,
+        icon,
+    }))
+}
+
+/// Copies an object from S3 into a temporary file, where an upload of it
+/// would have been spooled
+pub async fn download_object(
+    s3: &Client,
+    bucket: &str,
+    key: &str,
+) -> Result<NamedTempFile, Box<dyn std::error::Error>> {
+    let download = || s3.get_object().bucket(bucket).key(key).send();
+    let mut body = retry_s3("get_object", download).await?.body;
+    let mut file = NamedTempFile::new_in(upload_tmp_dir())?;
+    while let Some(chunk) = body.next().await {
+        file.write_all(&chunk?)?;
+    }
+    file.flush()?;
+    Ok(file)
+}
+
+/// Reads the header of a game's stored flatpak, fetching only the ranges of
+/// the bundle that hold it rather than the whole thing
+async fn read_flatpak_header(
+    s3: &Client,
+    bucket: &str,
+    id: &str,
+) -> Result<FlatpakHeader, Box<dyn std::error::Error>> {
+    let reader = S3RangeReader::open(s3, bucket, &format!("{}/{}.flatpak", id, id)).await?;
+    Ok(web::block(move || FlatpakFile::load_header_only(reader)).await??)
+}
+
+async fn read_flatpak_metadata(
+    s3: &Client,
+    bucket: &str,
+    id: &str,
+) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+    Ok(read_flatpak_header(s3, bucket, id).await?.metadata())
+}
+
+async fn read_flatpak_hash(
+    s3: &Client,
+    bucket: &str,
+    id: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(read_flatpak_header(s3, bucket, id).await?.get_hash())
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityProblem {
+    /// The game has a recorded hash but no flatpak in the bucket
+    Missing,
+    /// The stored flatpak couldn't be downloaded or decoded
+    Unreadable,
+    /// The stored flatpak's hash differs from the recorded one
+    Mismatch,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct IntegrityFailure {
+    #[schema(example = "9b4e2ff4-a6d6-4fbe-8d5c-0d3e9a0d1c1e")]
+    pub id: String,
+    pub problem: IntegrityProblem,
+    /// The hash recorded at upload
+    pub expected: String,
+    /// The hash of the stored flatpak, when it could be read
+    pub actual: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct IntegrityReport {
+    /// How many games with an uploaded flatpak were checked
+    pub checked: u64,
+    pub failures: Vec<IntegrityFailure>,
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Every game whose stored flatpak doesn't match its recorded hash. Games missing their banner or icon are reported by /admin/games/incomplete.", body = IntegrityReport),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/verify-integrity", wrap = "RequireApiKey")]
+pub async fn verify_integrity(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let games: Vec<(String, String)> = query_as(
+        "SELECT id, hash FROM game WHERE hash IS NOT NULL AND deleted_at IS NULL ORDER BY id",
+    )
+    .fetch_all(&state.db)
+    .await?;
+    let keys = list_bucket_keys(&state.s3, &state.games_bucket)
+        .await
+        .map_err(ApiError::internal)?;
+    let mut report = IntegrityReport {
+        checked: 0,
+        failures: vec![],
+    };
+    // One at a time, so checking a large catalog doesn't flood S3 with requests
+    for (id, expected) in games {
+        report.checked += 1;
+        let (problem, actual) = if !keys.contains(&format!("{id}/{id}.flatpak")) {
+            (IntegrityProblem::Missing, None)
+        } else {
+            match read_flatpak_hash(&state.s3, &state.games_bucket, &id).await {
+                Ok(actual) if actual == expected => continue,
+                Ok(actual) => (IntegrityProblem::Mismatch, Some(actual)),
+                Err(e) => {
+                    log::warn!("Failed to read flatpak of game {id}: {e}");
+                    (IntegrityProblem::Unreadable, None)
+                }
+            }
+        };
+        report.failures.push(IntegrityFailure {
+            id,
+            problem,
+            expected,
+            actual,
+        });
+    }
+    Ok(HttpResponse::Ok().json(report))
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Games missing a flatpak, banner or icon in the bucket, with which ones each is missing. The same report as /admin/games/incomplete.", body = [IncompleteGame]),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/missing-assets", wrap = "RequireApiKey")]
+pub async fn get_missing_assets(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(incomplete_games(&state).await?))
+}
+
+/// How many games are read from the database for each chunk of an export
+const EXPORT_PAGE_SIZE: i64 = 100;
+
+/// A game as exported for backup, with the keys of the objects it has stored
+/// in S3 rather than their contents
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct GameExport {
+    #[serde(flatten)]
+    pub game: GameWithTags,
+    #[schema(example = json!(["a1c6cef6-d987-4225-8bc4-def387e8b5bf/banner"]))]
+    pub objects: Vec<String>,
+}
+
+/// Reads the games after `after` in id order, as newline-delimited JSON
+async fn export_page(
+    db: &PgPool,
+    keys: &HashSet<String>,
+    after: &str,
+    page_size: i64,
+) -> Result<(Bytes, Option<String>), sqlx::Error> {
+    let games = query_as::<_, GameWithTags>(
+        "
+        SELECT game.*,
+            ROW(users.*)::users AS \"user\",
+            array_remove(ARRAY_AGG(tags.*), NULL) AS \"tags\"
+        FROM game
+        LEFT JOIN game_tags ON game_tags.game_id = game.id
+        LEFT JOIN tags ON tags.name = game_tags.tag_name
+        LEFT JOIN users ON users.id = game.author
+        WHERE game.id > $1 AND game.deleted_at IS NULL
+        GROUP BY game.id, users.id
+        ORDER BY game.id
+        LIMIT $2
+        ",
+    )
+    .bind(after)
+    .bind(page_size)
+    .fetch_all(db)
+    .await?;
+    let last = games.last().map(|game| game.id.clone());
+    let mut chunk = Vec::new();
+    for game in games {
+        let objects = game_object_keys(&game.id)
+            .into_iter()
+            .filter(|key| keys.contains(key))
+            .collect();
+        let export = GameExport { game, objects };
+        // Serializing plain data into a Vec can't fail
+        serde_json::to_writer(&mut chunk, &export).unwrap();
+        chunk.push(b'\n');
+    }
+    Ok((Bytes::from(chunk), last))
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Every game, including drafts, as newline-delimited JSON", body = [GameExport], content_type = "application/x-ndjson"),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[get("/export", wrap = "RequireApiKey")]
+pub async fn export_games(
+    req: HttpRequest,
+    state: Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let keys = list_bucket_keys(&state.s3, &state.games_bucket)
+        .await
+        .map_err(ApiError::internal)?;
+    let socket = req
+        .conn_data::<ConnectionSocket>()
+        .map(ConnectionSocket::try_clone)
+        .transpose()
+        .map_err(ApiError::internal)?;
+    let pages = export_stream(
+        state.db.clone(),
+        Arc::new(keys),
+        EXPORT_PAGE_SIZE,
+        *EXPORT_TIMEOUT,
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(hang_up_after(pages, socket, *EXPORT_TIMEOUT)))
+}
+
+/// Pages through every game as newline-delimited JSON. A page is only read
+/// once the client has taken the previous one, so only one is ever held in
+/// memory however slowly it reads. Once `timeout` has passed the stream
+/// fails, which drops the connection rather than ending the response, so the
+/// client can't mistake a cut-off export for a complete one.
+pub fn export_stream(
+    db: PgPool,
+    keys: Arc<HashSet<String>>,
+    page_size: i64,
+    timeout: Duration,
+) -> impl futures::Stream<Item = Result<Bytes, Box<dyn Error>>> {
+    let deadline = Instant::now() + timeout;
+    // Ids are never empty, so "" sorts before all of them
+    stream::try_unfold(String::new(), move |after| {
+        let db = db.clone();
+        let keys = keys.clone();
+        async move {
+            if Instant::now() >= deadline {
+                log::warn!("Game export timed out after game {after:?}");
+                return Err(format!("Export took longer than {timeout:?}").into());
+            }
+            let (chunk, last) = export_page(&db, &keys, &after, page_size)
+                .await
+                .inspect_err(|e| log::error!("Game export failed after game {after:?}: {e}"))?;
+            Ok::<_, Box<dyn Error>>(last.map(|last| (chunk, last)))
+        }
+    })
+}
+
+/// Shuts `socket` down once `timeout` has passed, unless `body` has ended or
+/// been dropped by then. A client that stops reading fills the socket's
+/// buffers, after which the body isn't polled again, so a deadline the body
+/// checks itself would never come due.
+pub fn hang_up_after<S: futures::Stream>(
+    body: S,
+    socket: Option<std::net::TcpStream>,
+    timeout: Duration,
+) -> impl futures::Stream<Item = S::Item> {
+    let hang_up = socket.map(|socket| {
+        AbortOnDrop(actix_web::rt::spawn(async move {
+            actix_web::rt::time::sleep(timeout).await;
+            log::warn!("Closing a response still unfinished after {timeout:?}");
+            if let Err(e) = socket.shutdown(std::net::Shutdown::Both) {
+                log::warn!("Failed to close connection: {e}");
+            }
+        }))
+    });
+    body.map(move |item| {
+        // Held until the body is dropped, which the server does as soon as
+        // it ends
+        let _ = &hang_up;
+        item
+    })
+}
+
+struct AbortOnDrop(actix_web::rt::task::JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Largest export we'll read back in, well beyond thousands of games
+const MAX_IMPORT_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq, Default)]
+pub struct ImportResult {
+    pub inserted: u64,
+    pub updated: u64,
+    /// Games that already matched the import exactly
+    pub skipped: u64,
+}
+
+/// Parses and validates every line of an export up front, so nothing is
+/// applied unless the whole import is usable
+fn parse_import(body: &[u8]) -> Result<Vec<GameExport>, ApiError> {
+    let body = std::str::from_utf8(body)
+        .map_err(|e| ApiError::BadRequest(format!("Import is not valid UTF-8: {e}")))?;
+    let mut games = Vec::new();
+    for (index, line) in body.lines().enumerate() {
+        let line_number = index + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let export: GameExport = serde_json::from_str(line)
+            .map_err(|e| ApiError::BadRequest(format!("Line {line_number}: {e}")))?;
+        let game = &export.game;
+        let mut errors = ValidationErrors::default();
+        if let Err(ApiError::BadRequest(message)) = check_game_id(&game.id) {
+            errors.add("id", &message);
+        }
+        validate_text_fields("name", &game.name, &game.description, &mut errors);
+        validate_tag_count(game.tags.len(), &mut errors);
+        if game.user.id != game.author {
+            errors.add("user", "User must be the game's author");
+        }
+        if !errors.errors.is_empty() {
+            let messages: Vec<String> = errors
+                .errors
+                .into_iter()
+                .map(|error| format!("{}: {}", error.field, error.message))
+                .collect();
+            return Err(ApiError::BadRequest(format!(
+                "Line {line_number}: {}",
+                messages.join(", ")
+            )));
+        }
+        games.push(export);
+    }
+    Ok(games)
+}
+
+fn sorted_tag_names(game: &GameWithTags) -> Vec<&str> {
+    let mut names: Vec<&str> = game.tags.iter().map(|tag| tag.name.as_str()).collect();
+    names.sort_unstable();
+    names
+}
+
+fn same_game(existing: &GameWithTags, imported: &GameWithTags) -> bool {
+    existing.author == imported.author
+        && existing.upload_date == imported.upload_date
+        && existing.name == imported.name
+        && existing.hash == imported.hash
+        && existing.description == imported.description
+        && existing.downloads == imported.downloads
+        && existing.play_count == imported.play_count
+        && existing.last_played_at == imported.last_played_at
+        && existing.updated_at == imported.updated_at
+        && existing.published == imported.published
+        && sorted_tag_names(existing) == sorted_tag_names(imported)
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    request_body(content = [GameExport], content_type = "application/x-ndjson", description = "Newline-delimited JSON, as produced by the export"),
+    responses(
+        (status = 200, description = "How many games were inserted, updated or already up to date. Deleted games are updated but stay deleted", body = ImportResult),
+        (status = 400, description = "A line is malformed or invalid, and nothing was imported", body = ErrorBody),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 413, description = "Import exceeds the maximum size", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/import", wrap = "RequireApiKey")]
+pub async fn import_games(
+    state: Data<AppState>,
+    mut payload: Payload,
+) -> Result<HttpResponse, ApiError> {
+    let mut body = Vec::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| ApiError::BadRequest(e.to_string()))?;
+        if body.len() + chunk.len() > MAX_IMPORT_BYTES {
+            return Err(ApiError::PayloadTooLarge(format!(
+                "Import exceeds the maximum size of {MAX_IMPORT_BYTES} bytes"
+            )));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let games = parse_import(&body)?;
+    let mut result = ImportResult::default();
+    let mut events = Vec::new();
+    // Dropping the transaction on any error rolls back the whole import
+    let mut transaction = state.db.begin().await?;
+    for GameExport { game, .. } in games {
+        // A fresh environment won't have the authors or tags the games
+        // reference yet, but existing ones are left as they are
+        let user = &game.user;
+        query("INSERT INTO users VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT (id) DO NOTHING")
+            .bind(&user.id)
+            .bind(&user.user_type)
+            .bind(&user.first_name)
+            .bind(&user.last_name)
+            .bind(&user.picture)
+            .bind(user.admin)
+            .bind(&user.email)
+            .execute(&mut transaction)
+            .await?;
+        for tag in &game.tags {
+            query("INSERT INTO tags VALUES ($1, $2, $3) ON CONFLICT ((lower(name))) DO NOTHING")
+                .bind(tag.name.trim())
+                .bind(&tag.description)
+                .bind(&tag.color)
+                .execute(&mut transaction)
+                .await?;
+        }
+        let existing = query_as::<_, GameWithTags>(GAME_WITH_TAGS_BY_ID)
+            .bind(&game.id)
+            .fetch_optional(&mut transaction)
+            .await?;
+        // A game deleted here is updated like any other, but stays deleted
+        // until it's restored rather than reappearing with the import
+        let deleted = existing.is_none()
+            && query_scalar("SELECT EXISTS (SELECT 1 FROM game WHERE id = $1)")
+                .bind(&game.id)
+                .fetch_one(&mut transaction)
+                .await?;
+        match existing {
+            Some(existing) if same_game(&existing, &game) => {
+                result.skipped += 1;
+                continue;
+            }
+            Some(_) => result.updated += 1,
+            None if deleted => result.updated += 1,
+            None => result.inserted += 1,
+        }
+        let imported = query_as::<_, Game>(
+            "INSERT INTO game (id, author, upload_date, name, hash, description, downloads,
+                updated_at, published, play_count, last_played_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (id) DO UPDATE SET author = $2, upload_date = $3, name = $4, hash = $5,
+                description = $6, downloads = $7, updated_at = $8, published = $9,
+                play_count = $10, last_played_at = $11
+            RETURNING *",
+        )
+        .bind(&game.id)
+        .bind(&game.author)
+        .bind(game.upload_date)
+        .bind(&game.name)
+        .bind(&game.hash)
+        .bind(&game.description)
+        .bind(game.downloads)
+        .bind(game.updated_at)
+        .bind(game.published)
+        .bind(game.play_count)
+        .bind(game.last_played_at)
+        .fetch_one(&mut transaction)
+        .await?;
+        match existing {
+            Some(_) => events.push(GameEvent::updated(&imported)),
+            None if !deleted => events.push(GameEvent::created(&imported)),
+            None => {}
+        }
+        query("DELETE FROM game_tags WHERE game_id = $1")
+            .bind(&game.id)
+            .execute(&mut transaction)
+            .await?;
+        for tag_name in unique_tags(game.tags.into_iter().map(|tag| tag.name)) {
+            // The tag may already exist under another spelling
+            query("INSERT INTO game_tags SELECT $1, name FROM tags WHERE lower(name) = $2")
+                .bind(&game.id)
+                .bind(Tag::normalize_name(&tag_name))
+                .execute(&mut transaction)
+                .await?;
+        }
+    }
+    transaction.commit().await?;
+    if result.inserted + result.updated > 0 {
+        state.icon_sprite.invalidate();
+    }
+    for event in events {
+        state.events.publish(event).await;
+    }
+    Ok(HttpResponse::Ok().json(result))
 }
 
 #[utoipa::path(
     context_path = "/games",
-    request_body(content=GameUploadDoc, content_type="multipart/form-data", description="Multipart Form. Contains flatpak file of game, banner, icon, name, description, and author"),
     responses(
-        (status = 201, description = "Created new game"),
-        (status = 400, description = "Invalid format of file upload"),
-        (status = 401, description = "Invalid/Missing API Key"),
-        (status = 500, description = "Error Created by Query"),
+        (status = 200, description = "Full metadata dictionary of the stored flatpak", body = BTreeMap<String, String>),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game", body = ErrorBody),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game")
     ),
     security(
         ("api_key" = [])
     )
 )]
-#[post("/", wrap = "RequireApiKey")]
-pub async fn add_game(
+#[get("/{id}/flatpak/metadata", wrap = "RequireApiKey")]
+pub async fn get_flatpak_metadata(
     state: Data<AppState>,
-    MultipartForm(form): MultipartForm<GameUpload>,
-) -> impl Responder {
-    let uuid = Uuid::new_v4().to_string();
-    match verify_and_upload(form.banner, form.icon, &state.s3, &uuid).await {
-        Ok(()) => {
-            let date = Local::now().date_naive();
-            match query("INSERT INTO game VALUES ($1, $2, $3, $4, $5, $6)")
-                .bind(&uuid)
-                .bind(form.author.clone())
-                .bind(date)
-                .bind(form.title.clone())
-                .bind(&None::<String>)
-                .bind(form.description.clone())
-                .execute(&state.db)
-                .await
-            {
-                Ok(_) => {
-                    for tag_name in form.tags.split(',') {
-                        if tag_name.is_empty() {
-                            continue;
-                        }
-                        if let Err(e) = query("INSERT INTO game_tags VALUES ($1, $2)")
-                            .bind(&uuid)
-                            .bind(tag_name)
-                            .execute(&state.db)
-                            .await
-                        {
-                            return HttpResponse::BadRequest().body(e.to_string());
-                        }
-                    }
-                    HttpResponse::Created().json(Game {
-                        id: uuid,
-                        author: form.author.clone(),
-                        upload_date: date,
-                        name: form.title.clone(),
-                        hash: None,
-                        description: form.description.clone(),
-                    })
-                }
-                Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-            }
-        }
-        Err(e) => HttpResponse::NotAcceptable().body(e.to_string()),
-    }
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    require_game(&state.db, &id).await?;
+    let metadata = read_flatpak_metadata(&state.s3, &state.games_bucket, &id)
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(HttpResponse::Ok().json(metadata))
+}
+
+async fn read_flatpak_summary(
+    s3: &Client,
+    bucket: &str,
+    id: &str,
+) -> Result<FlatpakSummary, Box<dyn std::error::Error>> {
+    Ok(read_flatpak_header(s3, bucket, id).await?.summary()?)
 }
 
 #[utoipa::path(
     context_path = "/games",
     responses(
-        (status = 200, description = "Get specified game", body = GameWithTags),
-        (status = 400, description = "Missing game"),
-        (status = 500, description = "Error Created by Query"),
-    )
+        (status = 200, description = "Selected metadata of the stored flatpak", body = FlatpakSummary),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game, or the game has no flatpak yet", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game")
+    ),
 )]
-#[get("/{id}")]
-pub async fn get_game(state: Data<AppState>, path: Path<(String,)>) -> impl Responder {
-    let (id,) = path.into_inner();
-    match query_as::<_, GameWithTags>(
-        "
-        SELECT game.*,
-            ROW(users.*)::users AS \"user\",
-            array_remove(ARRAY_AGG(tags.*), NULL) AS \"tags\"
-        FROM game
-        LEFT JOIN game_tags ON game_tags.game_id = game.id
-        LEFT JOIN tags ON tags.name = game_tags.tag_name
-        LEFT JOIN users ON users.id = game.author
-        WHERE game.id = $1
-        GROUP BY game.id, users.id
-        ",
-    )
-    .bind(id)
-    .fetch_one(&state.db)
-    .await
-    {
-        Ok(game) => HttpResponse::Ok().json(game),
-        Err(e) => HttpResponse::BadRequest().body(e.to_string()),
+#[get("/{id}/metadata")]
+pub async fn get_flatpak_summary(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    let game = find_game(&state.db, &id).await?;
+    let hash = game.hash.ok_or_else(ApiError::flatpak_not_found)?;
+    if let Some(summary) = state.flatpak_summaries.get(&hash) {
+        return Ok(HttpResponse::Ok().json(summary));
     }
+    let summary = read_flatpak_summary(&state.s3, &state.games_bucket, &id)
+        .await
+        .map_err(ApiError::internal)?;
+    state.flatpak_summaries.insert(hash, summary.clone());
+    Ok(HttpResponse::Ok().json(summary))
 }
 
 #[utoipa::path(
     context_path = "/games",
-    request_body(content=GameData, content_type="application/json", description="JSON with name, desc, and author"),
+    request_body(content=FileUploadDoc, content_type="multipart/form-data", description="Flatpak of game publish folder"),
     responses(
-        (status = 200, description = "Updated game"),
-        (status = 400, description = "Missing game"),
-        (status = 401, description = "Invalid/Missing API Key"),
-        (status = 500, description = "Error Created by Query"),
+        (status = 200, description = "Updated Game Binary, kept as a new version"),
+        (status = 400, description = "Flatpak is corrupt or was built for a different game, or the game id is malformed", body = ErrorBody),
+        (status = 404, description = "Missing game", body = ErrorBody),
+        (status = 409, description = "Flatpak app id belongs to another game", body = ErrorBody),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 403, description = "Author is not permitted to publish games", body = ErrorBody),
+        (status = 413, description = "Upload exceeds the maximum size", body = ErrorBody),
+        (status = 415, description = "Upload is the wrong kind of file, going by its content type", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game")
     ),
     security(
         ("api_key" = [])
     )
 )]
-#[put("/{id}", wrap = "RequireApiKey")]
-pub async fn edit_game(
+#[put("/{id}/game", wrap = "RequireApiKey")]
+pub async fn update_binary(
     state: Data<AppState>,
     path: Path<(String,)>,
-    game_data: Json<GameData>,
-) -> impl Responder {
-    let (id,) = path.into_inner();
-    let mut transaction = state.db.begin().await.unwrap();
-    match query_as::<_, Game>("SELECT * FROM game WHERE id = $1")
+    MultipartForm(form): MultipartForm<FileUpload>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    let game = find_game(&state.db, &id).await?;
+    check_author_permitted(&game.author)?;
+    check_upload_size(&form.file, *MAX_GAME_BYTES, "Game")?;
+    let uploaded = async {
+        let flatpak = load_flatpak(form.file).await?;
+        verify_and_upload_game(flatpak, &state.db, &state.s3, &state.games_bucket, &id).await
+    }
+    .await
+    .inspect_err(|e| {
+        if e.status_code().is_client_error() {
+            record_flatpak_rejection();
+        }
+    })?;
+    let mut stored_blob = uploaded.uploaded.clone();
+    let recorded = async {
+        let mut transaction = state.db.begin().await?;
+        // Locking the game until the upload is recorded keeps concurrent
+        // uploads from claiming the same version
+        query("SELECT id FROM game WHERE id = $1 FOR UPDATE")
+            .bind(&id)
+            .execute(&mut transaction)
+            .await?;
+        let version: i32 = query_scalar(
+            "SELECT COALESCE(MAX(version), 0) + 1 FROM game_versions WHERE game_id = $1",
+        )
         .bind(&id)
         .fetch_one(&mut transaction)
-        .await
-    {
-        Ok(game) => {
-            match query("UPDATE game SET name = $1, description = $2 WHERE id = $3")
-                .bind(game_data.name.clone())
-                .bind(game_data.description.clone())
-                .bind(&id)
-                .execute(&mut transaction)
-                .await
-            {
-                Ok(_) => {
-                    if let Err(e) = query("DELETE FROM game_tags WHERE game_id =  $1")
-                        .bind(&id)
-                        .execute(&mut transaction)
-                        .await
-                    {
-                        let _ = transaction.rollback().await;
-                        return HttpResponse::InternalServerError().body(e.to_string());
-                    };
-                    for tag_name in game_data.tags.clone() {
-                        if let Err(e) = query("INSERT INTO game_tags VALUES ($1, $2)")
-                            .bind(&id)
-                            .bind(tag_name)
-                            .execute(&mut transaction)
-                            .await
-                        {
-                            let _ = transaction.rollback().await;
-                            return HttpResponse::BadRequest().body(e.to_string());
-                        }
-                    }
-                    let _ = transaction.commit().await;
-                    HttpResponse::Ok().json(Game {
-                        id,
-                        author: game.author,
-                        upload_date: game.upload_date,
-                        name: game_data.name.clone(),
-                        hash: game.hash,
-                        description: game_data.description.clone(),
-                    })
-                }
-                Err(e) => {
-                    let _ = transaction.rollback().await;
-                    HttpResponse::InternalServerError().body(e.to_string())
-                }
-            }
-        }
-        Err(_) => {
-            let _ = transaction.rollback().await;
-            HttpResponse::BadRequest().body("Game ID Does Not Exist")
-        }
-    }
-}
-
-async fn delete_recursively(s3: &Client, id: &str) -> Result<(), Box<dyn std::error::Error>> {
-    s3.delete_object()
-        .bucket(&GAMES_BUCKET.to_string())
-        .key(format!("{}/{}.flatpak", id, id))
-        .send()
         .await?;
-    s3.delete_object()
-        .bucket(&GAMES_BUCKET.to_string())
-        .key(format!("{}/icon", id))
-        .send()
+        let stored = record_flatpak(
+            &mut transaction,
+            &state.s3,
+            &state.games_bucket,
+            &id,
+            uploaded,
+        )
         .await?;
-    s3.delete_object()
-        .bucket(&GAMES_BUCKET.to_string())
-        .key(format!("{}/banner", id))
-        .send()
+        stored_blob = stored.created.then(|| stored.key.clone());
+        make_flatpak_current(
+            &state.s3,
+            &state.games_bucket,
+            &id,
+            &stored.arch,
+            &stored.key,
+        )
         .await?;
-    Ok(())
+        query(
+            "INSERT INTO game_versions (game_id, version, hash, key, arch, branch)
+            VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(&id)
+        .bind(version)
+        .bind(&stored.hash)
+        .bind(&stored.key)
+        .bind(&stored.arch)
+        .bind(&stored.branch)
+        .execute(&mut transaction)
+        .await?;
+        let current = set_current_build(
+            &mut transaction,
+            &id,
+            &stored.arch,
+            &stored.hash,
+            Some(stored.size),
+        )
+        .await?;
+        query("UPDATE game SET published = true WHERE id = $1")
+            .bind(&id)
+            .execute(&mut transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok::<_, ApiError>(current)
+    }
+    .await;
+    let (updated_at, hash, size_bytes) = match recorded {
+        Ok(current) => current,
+        Err(e) => {
+            if let Some(key) = stored_blob {
+                discard_blob(&state.s3, &state.games_bucket, &key).await;
+            }
+            return Err(e);
+        }
+    };
+    let game = Game {
+        hash,
+        updated_at,
+        published: true,
+        size_bytes,
+        ..game
+    };
+    state.events.publish(GameEvent::updated(&game)).await;
+    Ok(HttpResponse::Ok().json(game))
 }
 
 #[utoipa::path(
     context_path = "/games",
     responses(
-        (status = 200, description = "Delete game"),
-        (status = 400, description = "Missing game"),
-        (status = 401, description = "Invalid/Missing API Key"),
-        (status = 500, description = "Error Created by Deletion"),
+        (status = 200, description = "Every flatpak uploaded for the game, newest first", body = [GameVersion]),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     ),
     params(
         ("id", description = "Unique id of game")
     ),
+)]
+#[get("/{id}/versions")]
+pub async fn get_game_versions(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    require_game(&state.db, &id).await?;
+    let versions: Vec<GameVersion> = query_as(
+        "SELECT version, hash, arch, branch, uploaded_at FROM game_versions WHERE game_id = $1
+        ORDER BY version DESC",
+    )
+    .bind(&id)
+    .fetch_all(&state.db)
+    .await?;
+    Ok(HttpResponse::Ok().json(versions))
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "The game, now serving the given version's flatpak", body = Game),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game or version", body = ErrorBody),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 403, description = "Author is not permitted to publish games", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game"),
+        ("version", description = "Version to make current"),
+    ),
     security(
         ("api_key" = [])
     )
 )]
-#[delete("/{id}", wrap = "RequireApiKey")]
-pub async fn delete_game(state: Data<AppState>, path: Path<(String,)>) -> impl Responder {
-    let (id,) = path.into_inner();
-    if query_as::<_, Game>("SELECT * FROM game WHERE id = $1")
+#[post("/{id}/rollback/{version}", wrap = "RequireApiKey")]
+pub async fn rollback_game(
+    state: Data<AppState>,
+    path: Path<(String, i32)>,
+) -> Result<HttpResponse, ApiError> {
+    let (id, version) = path.into_inner();
+    check_game_id(&id)?;
+    let game = find_game(&state.db, &id).await?;
+    check_author_permitted(&game.author)?;
+    let mut transaction = state.db.begin().await?;
+    query("SELECT id FROM game WHERE id = $1 FOR UPDATE")
         .bind(&id)
-        .fetch_one(&state.db)
-        .await
-        .is_err()
-    {
-        return HttpResponse::BadRequest().body("Game ID Does Not Exist");
+        .execute(&mut transaction)
+        .await?;
+    let (hash, key, arch, size): (String, String, String, Option<i64>) = query_as(
+        "SELECT hash, game_versions.key, arch, size_bytes
+        FROM game_versions JOIN blobs ON blobs.key = game_versions.key
+        WHERE game_id = $1 AND version = $2",
+    )
+    .bind(&id)
+    .bind(version)
+    .fetch_optional(&mut transaction)
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("Game has no version {version}")))?;
+    make_flatpak_current(&state.s3, &state.games_bucket, &id, &arch, &key).await?;
+    let (updated_at, hash, size_bytes) =
+        set_current_build(&mut transaction, &id, &arch, &hash, size).await?;
+    transaction.commit().await?;
+    let game = Game {
+        hash,
+        updated_at,
+        size_bytes,
+        ..game
+    };
+    state.events.publish(GameEvent::updated(&game)).await;
+    Ok(HttpResponse::Ok().json(game))
+}
+
+const MIN_SCORE: i16 = 1;
+const MAX_SCORE: i16 = 5;
+const MAX_DEVICE_ID_LENGTH: usize = 64;
+
+/// A player's rating of a game
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewRating {
+    #[schema(example = 4, minimum = 1, maximum = 5)]
+    pub score: i16,
+    /// Identifies the cabinet or browser rating the game without saying who
+    /// is playing. Rating a game again from the same device replaces its
+    /// earlier score, while ratings without one are always counted.
+    #[schema(example = "cabinet-2", max_length = 64)]
+    #[serde(default)]
+    pub device_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq, FromRow)]
+pub struct GameRating {
+    /// Mean score out of 5, or `null` until the game is first rated
+    #[schema(example = 4.25)]
+    pub average: Option<f64>,
+    #[schema(example = 12)]
+    pub count: i64,
+}
+
+async fn game_rating(db: &PgPool, id: &str) -> Result<GameRating, sqlx::Error> {
+    query_as(
+        "SELECT AVG(score)::double precision AS average, COUNT(*) AS count
+        FROM game_ratings WHERE game_id = $1",
+    )
+    .bind(id)
+    .fetch_one(db)
+    .await
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    request_body = NewRating,
+    responses(
+        (status = 201, description = "The game's rating, counting the new score", body = GameRating),
+        (status = 200, description = "The game's rating, with the device's earlier score replaced", body = GameRating),
+        (status = 400, description = "Score outside 1 to 5 or invalid device id. A malformed game id is reported as an ErrorBody instead", body = ValidationErrors),
+        (status = 404, description = "Missing game", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game")
+    ),
+)]
+#[post("/{id}/rate")]
+pub async fn rate_game(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+    rating: Json<NewRating>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    let NewRating { score, device_id } = rating.into_inner();
+    let mut errors = ValidationErrors::default();
+    if !(MIN_SCORE..=MAX_SCORE).contains(&score) {
+        errors.add(
+            "score",
+            format!("Score must be between {MIN_SCORE} and {MAX_SCORE}"),
+        );
     }
-    match delete_recursively(&state.s3, &id).await {
-        Ok(_) => {
-            match query("DELETE FROM game WHERE id = $1")
-                .bind(&id)
-                .execute(&state.db)
-                .await
-            {
-                Ok(_) => match query("DELETE FROM game_tags WHERE game_id = $1")
-                    .bind(&id)
-                    .execute(&state.db)
-                    .await
-                {
-                    Ok(_) => HttpResponse::Ok().finish(),
-                    Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-                },
-                Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-            }
+    if let Some(device_id) = &device_id {
+        if device_id.is_empty() || device_id.chars().count() > MAX_DEVICE_ID_LENGTH {
+            errors.add(
+                "device_id",
+                format!("Device id must be between 1 and {MAX_DEVICE_ID_LENGTH} characters"),
+            );
         }
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
     }
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+    require_game(&state.db, &id).await?;
+    // Rows without a device id never conflict, since NULLs are distinct in
+    // the unique index, and xmax = 0 only holds for freshly inserted rows
+    let inserted: bool = query_scalar(
+        "INSERT INTO game_ratings (game_id, device_id, score) VALUES ($1, $2, $3)
+        ON CONFLICT (game_id, device_id) DO UPDATE SET score = $3, rated_at = now()
+        RETURNING xmax = 0",
+    )
+    .bind(&id)
+    .bind(&device_id)
+    .bind(score)
+    .fetch_one(&state.db)
+    .await?;
+    let rating = game_rating(&state.db, &id).await?;
+    Ok(if inserted {
+        HttpResponse::Created().json(rating)
+    } else {
+        HttpResponse::Ok().json(rating)
+    })
 }
 
 #[utoipa::path(
     context_path = "/games",
     responses(
-        (status = 200, description = "Provide game source flatpak", content_type="application/vnd.flatpak"),
-        (status = 400, description = "Missing game"),
-        (status = 500, description = "Error Created by Query"),
+        (status = 200, description = "The game's average score and how many ratings it has", body = GameRating),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     ),
     params(
         ("id", description = "Unique id of game")
     ),
 )]
-#[get("/{id}/game")]
-pub async fn get_binary(state: Data<AppState>, path: Path<(String,)>) -> impl Responder {
-    let (id,) = path.into_inner();
-    if query_as::<_, Game>("SELECT * FROM game WHERE id = $1")
-        .bind(&id)
-        .fetch_one(&state.db)
-        .await
-        .is_err()
-    {
-        return HttpResponse::BadRequest().body("Game ID Does Not Exist");
-    }
-    match state
-        .s3
-        .get_object()
-        .bucket(&GAMES_BUCKET.to_string())
-        .key(format!("{}/{}.flatpak", id, id))
-        .send()
-        .await
-    {
-        Ok(objout) => HttpResponse::Ok().streaming(objout.body),
-        Err(e) => HttpResponse::InternalServerError().body(format!("Error getting object: {}", e)),
-    }
+#[get("/{id}/rating")]
+pub async fn get_game_rating(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    require_game(&state.db, &id).await?;
+    Ok(HttpResponse::Ok().json(game_rating(&state.db, &id).await?))
 }
 
 #[utoipa::path(
     context_path = "/games",
-    request_body(content=FileUploadDoc, content_type="multipart/form-data", description="Flatpak of game publish folder"),
     responses(
-        (status = 200, description = "Updated Game Binary"),
-        (status = 400, description = "Missing game"),
-        (status = 401, description = "Invalid/Missing API Key"),
-        (status = 500, description = "Error Created by Query"),
+        (status = 204, description = "Counted a session of the game, which cabinets report whenever one starts since they keep the flatpaks they download"),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game, or one not yet published", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     ),
     params(
         ("id", description = "Unique id of game")
     ),
-    security(
-        ("api_key" = [])
-    )
 )]
-#[put("/{id}/game", wrap = "RequireApiKey")]
-pub async fn update_binary(
+#[post("/{id}/played")]
+pub async fn record_play(
     state: Data<AppState>,
     path: Path<(String,)>,
-    MultipartForm(form): MultipartForm<FileUpload>,
-) -> impl Responder {
-    let (id,) = path.into_inner();
-    match query_as::<_, Game>("SELECT * FROM game WHERE id = $1")
-        .bind(&id)
-        .fetch_one(&state.db)
-        .await
-    {
-        Ok(game) => match verify_and_upload_game(form.file, &state.s3, &id).await {
-            Ok(hash) => {
-                match query("UPDATE game SET hash = $1 WHERE id = $2")
-                    .bind(&hash)
-                    .bind(&id)
-                    .execute(&state.db)
-                    .await
-                {
-                    Ok(_) => HttpResponse::Ok().json(Game {
-                        id,
-                        author: game.author,
-                        upload_date: game.upload_date,
-                        name: game.name,
-                        hash: Some(hash),
-                        description: game.description,
-                    }),
-                    Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-                }
-            }
-            Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-        },
-        Err(_) => HttpResponse::BadRequest().body("Game ID Does Not Exist"),
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    // A play isn't an edit, so updated_at and the game's ETag stay as they
+    // are. Drafts can't be downloaded, so can't have been played either.
+    let counted = query(
+        "UPDATE game SET play_count = play_count + 1, last_played_at = now()
+        WHERE id = $1 AND deleted_at IS NULL AND published",
+    )
+    .bind(&id)
+    .execute(&state.db)
+    .await?;
+    if counted.rows_affected() == 0 {
+        return Err(ApiError::game_not_found());
     }
+    Ok(HttpResponse::NoContent().finish())
 }
 
 #[utoipa::path(
     context_path = "/games",
     responses(
-        (status = 200, description = "Provide game banner"),
-        (status = 400, description = "Missing game"),
-        (status = 500, description = "Error Created by Query"),
+        (status = 200, description = "Provide game banner, as WebP if the `webp` feature is enabled, the client accepts it and a copy exists"),
+        (status = 304, description = "The client's cached copy is current"),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game or banner", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     ),
     params(
-        ("id", description = "Unique id of game")
+        ("id", description = "Unique id of game"),
+        ("Accept" = Option<String>, Header, description = "Include image/webp to be served the WebP copy"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of the client's cached copy"),
     ),
 )]
 #[get("/{id}/banner")]
-pub async fn get_banner(state: Data<AppState>, path: Path<(String,)>) -> impl Responder {
-    let (id,) = path.into_inner();
-    if query_as::<_, Game>("SELECT * FROM game WHERE id = $1")
-        .bind(&id)
-        .fetch_one(&state.db)
-        .await
-        .is_err()
-    {
-        return HttpResponse::BadRequest().body("Game ID Does Not Exist");
-    }
-    match state
-        .s3
-        .get_object()
-        .bucket(&GAMES_BUCKET.to_string())
-        .key(format!("{}/banner", id))
-        .send()
-        .await
-    {
-        Ok(objout) => {
-            let bytestream = objout.body.collect().await;
-            match bytestream {
-                Ok(bytes) => HttpResponse::Ok().body(bytes.into_bytes()),
-                Err(e) => HttpResponse::InternalServerError()
-                    .body(format!("Error getting object body: {}", e)),
-            }
+pub async fn get_banner(
+    req: HttpRequest,
+    state: Data<AppState>,
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    require_game(&state.db, &id).await?;
+    let missing = || ApiError::NotFound("Game has no banner uploaded".to_string());
+    let webp = if is_enabled("webp") && accepts_webp(&req) {
+        let key = format!("{}/banner.webp", id);
+        match get_game_object(&state.s3, &state.games_bucket, key, missing).await {
+            Ok(objout) => Some(objout),
+            Err(ApiError::NotFound(_)) => None,
+            Err(e) => return Err(e),
         }
-        Err(e) => HttpResponse::InternalServerError().body(format!("Error getting object: {}", e)),
-    }
+    } else {
+        None
+    };
+    let objout = match webp {
+        Some(objout) => objout,
+        None => {
+            get_game_object(
+                &state.s3,
+                &state.games_bucket,
+                format!("{}/banner", id),
+                missing,
+            )
+            .await?
+        }
+    };
+    let mut response = stream_image(&req, objout);
+    response
+        .headers_mut()
+        .insert(header::VARY, HeaderValue::from_static("accept"));
+    Ok(response)
 }
 
 #[utoipa::path(
@@ -599,9 +4244,12 @@ pub async fn get_banner(state: Data<AppState>, path: Path<(String,)>) -> impl Re
     request_body(content=FileUploadDoc, content_type="multipart/form-data", description="Game banner"),
     responses(
         (status = 200, description = "Updated Game Banner"),
-        (status = 400, description = "Missing game"),
-        (status = 401, description = "Invalid/Missing API Key"),
-        (status = 500, description = "Error Created by Query"),
+        (status = 400, description = "Image too large to process or the wrong shape, or the game id is malformed", body = ErrorBody),
+        (status = 413, description = "Upload exceeds the maximum size", body = ErrorBody),
+        (status = 415, description = "Upload is the wrong kind of file, going by its content type", body = ErrorBody),
+        (status = 404, description = "Missing game", body = ErrorBody),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     ),
     params(
         ("id", description = "Unique id of game")
@@ -615,63 +4263,121 @@ pub async fn update_banner(
     state: Data<AppState>,
     path: Path<(String,)>,
     MultipartForm(form): MultipartForm<FileUpload>,
-) -> impl Responder {
-    let (id,) = path.into_inner();
-    match query_as::<_, Game>("SELECT * FROM game WHERE id = $1")
-        .bind(&id)
-        .fetch_one(&state.db)
-        .await
-    {
-        Ok(_) => {
-            match verify_and_upload_image(form.file, &state.s3, ImageComponent::Banner, &id).await {
-                Ok(_) => HttpResponse::Ok().finish(),
-                Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-            }
-        }
-        Err(_) => HttpResponse::BadRequest().body("Game ID Does Not Exist"),
-    }
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    require_game(&state.db, &id).await?;
+    check_upload_size(&form.file, *MAX_IMAGE_BYTES, "Banner")?;
+    check_image_pixels(&form.file)?;
+    check_image_shape(&form.file, &ImageComponent::Banner)?;
+    verify_and_upload_image(
+        form.file,
+        &state.s3,
+        &state.games_bucket,
+        ImageComponent::Banner,
+        &id,
+    )
+    .await?;
+    let game = touch_game(&state.db, &id).await?;
+    state.events.publish(GameEvent::updated(&game)).await;
+    Ok(HttpResponse::Ok().finish())
 }
 
 #[utoipa::path(
     context_path = "/games",
     responses(
         (status = 200, description = "Provide game icon"),
-        (status = 400, description = "Missing game"),
-        (status = 500, description = "Error Created by Query"),
+        (status = 304, description = "The client's cached copy is current"),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game or icon", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     ),
     params(
-        ("id", description = "Unique id of game")
+        ("id", description = "Unique id of game"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of the client's cached copy"),
     ),
 )]
 #[get("/{id}/icon")]
-pub async fn get_icon(state: Data<AppState>, path: Path<(String,)>) -> impl Responder {
-    let (id,) = path.into_inner();
-    if query_as::<_, Game>("SELECT * FROM game WHERE id = $1")
-        .bind(&id)
-        .fetch_one(&state.db)
-        .await
-        .is_err()
-    {
-        return HttpResponse::BadRequest().body("Game ID Does Not Exist");
-    }
-    match state
-        .s3
-        .get_object()
-        .bucket(&GAMES_BUCKET.to_string())
-        .key(format!("{}/icon", id))
-        .send()
-        .await
-    {
-        Ok(objout) => {
-            let bytestream = objout.body.collect().await;
-            match bytestream {
-                Ok(bytes) => HttpResponse::Ok().body(bytes.into_bytes()),
-                Err(e) => HttpResponse::InternalServerError()
-                    .body(format!("Error getting object body: {}", e)),
-            }
-        }
-        Err(e) => HttpResponse::InternalServerError().body(format!("Error getting object: {}", e)),
-    }
+pub async fn get_icon(
+    req: HttpRequest,
+    state: Data<AppState>,
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    require_game(&state.db, &id).await?;
+    let objout = get_game_object(
+        &state.s3,
+        &state.games_bucket,
+        format!("{}/icon", id),
+        || ApiError::NotFound("Game has no icon uploaded".to_string()),
+    )
+    .await?;
+    Ok(stream_image(&req, objout))
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Provide a small PNG of the game icon for the menu grid"),
+        (status = 304, description = "The client's cached copy is current"),
+        (status = 400, description = "Malformed game id", body = ErrorBody),
+        (status = 404, description = "Missing game or icon", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("id", description = "Unique id of game"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of the client's cached copy"),
+    ),
+)]
+#[get("/{id}/icon/thumb")]
+pub async fn get_icon_thumb(
+    req: HttpRequest,
+    state: Data<AppState>,
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    require_game(&state.db, &id).await?;
+    let objout = get_game_object(
+        &state.s3,
+        &state.games_bucket,
+        format!("{}/icon_thumb", id),
+        || ApiError::NotFound("Game has no icon uploaded".to_string()),
+    )
+    .await?;
+    Ok(stream_image(&req, objout))
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Every game's icon packed into one PNG, laid out as described by the atlas"),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    )
+)]
+#[get("/icons/sprite")]
+pub async fn get_icon_sprite(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let sprite = state
+        .icon_sprite
+        .get_or_build(&state.db, &state.s3, &state.games_bucket)
+        .await?;
+    Ok(HttpResponse::Ok()
+        .content_type("image/png")
+        .body(sprite.png.clone()))
+}
+
+#[utoipa::path(
+    context_path = "/games",
+    responses(
+        (status = 200, description = "Where each game's icon is in the sprite sheet", body = IconAtlas),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    )
+)]
+#[get("/icons/atlas")]
+pub async fn get_icon_atlas(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let sprite = state
+        .icon_sprite
+        .get_or_build(&state.db, &state.s3, &state.games_bucket)
+        .await?;
+    Ok(HttpResponse::Ok().json(&sprite.atlas))
 }
 
 #[utoipa::path(
@@ -679,9 +4385,12 @@ pub async fn get_icon(state: Data<AppState>, path: Path<(String,)>) -> impl Resp
     request_body(content=FileUploadDoc, content_type="multipart/form-data", description="Game Icon"),
     responses(
         (status = 200, description = "Updated Game Icon"),
-        (status = 400, description = "Missing game"),
-        (status = 401, description = "Invalid/Missing API Key"),
-        (status = 500, description = "Error Created by Query"),
+        (status = 400, description = "Image too large to process or the wrong shape, or the game id is malformed", body = ErrorBody),
+        (status = 413, description = "Upload exceeds the maximum size", body = ErrorBody),
+        (status = 415, description = "Upload is the wrong kind of file, going by its content type", body = ErrorBody),
+        (status = 404, description = "Missing game", body = ErrorBody),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     ),
     params(
         ("id", description = "Unique id of game")
@@ -695,19 +4404,22 @@ pub async fn update_icon(
     state: Data<AppState>,
     path: Path<(String,)>,
     MultipartForm(form): MultipartForm<FileUpload>,
-) -> impl Responder {
-    let (id,) = path.into_inner();
-    match query_as::<_, Game>("SELECT * FROM game WHERE id = $1")
-        .bind(&id)
-        .fetch_one(&state.db)
-        .await
-    {
-        Ok(_) => {
-            match verify_and_upload_image(form.file, &state.s3, ImageComponent::Icon, &id).await {
-                Ok(_) => HttpResponse::Ok().finish(),
-                Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-            }
-        }
-        Err(_) => HttpResponse::BadRequest().body("Game ID Does Not Exist"),
-    }
+) -> Result<HttpResponse, ApiError> {
+    let id = game_id(path)?;
+    require_game(&state.db, &id).await?;
+    check_upload_size(&form.file, *MAX_IMAGE_BYTES, "Icon")?;
+    check_image_pixels(&form.file)?;
+    check_image_shape(&form.file, &ImageComponent::Icon)?;
+    verify_and_upload_image(
+        form.file,
+        &state.s3,
+        &state.games_bucket,
+        ImageComponent::Icon,
+        &id,
+    )
+    .await?;
+    let game = touch_game(&state.db, &id).await?;
+    state.icon_sprite.invalidate();
+    state.events.publish(GameEvent::updated(&game)).await;
+    Ok(HttpResponse::Ok().finish())
 }
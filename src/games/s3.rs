@@ -0,0 +1,174 @@
+use crate::metrics::time_s3;
+use actix_web::rt::time::sleep;
+use aws_sdk_s3::{error::HeadObjectError, types::SdkError, Client};
+use std::{
+    error::Error,
+    future::Future,
+    io::{self, Read, Seek, SeekFrom},
+    time::Duration,
+};
+use tokio::runtime::Handle;
+
+/// How many times an S3 request is sent before its error is given up on
+const MAX_ATTEMPTS: u32 = 3;
+/// How long to wait before the first retry, doubled before each one after
+const INITIAL_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Sends an S3 request, retrying with exponential backoff if it fails in a way
+/// that might not happen again, e.g.
+/// `retry_s3("get_object", || s3.get_object().bucket(b).key(k).send())`.
+/// The client's own retries are disabled so this is the only place they
+/// happen, and each attempt is timed separately.
+pub async fn retry_s3<T, E, F, Fut>(operation: &str, mut request: F) -> Result<T, SdkError<E>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, SdkError<E>>>,
+    E: Error,
+{
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match time_s3(operation, request()).await {
+            Err(e) if attempt < MAX_ATTEMPTS && is_retryable(&e) => {
+                log::warn!("S3 {operation} failed on attempt {attempt}, retrying: {e}");
+                sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Timeouts, dropped connections, throttling and S3's own failures are worth
+/// another try. Anything else, like a missing key or being refused access,
+/// would fail the same way again.
+fn is_retryable<E>(error: &SdkError<E>) -> bool {
+    match error {
+        SdkError::TimeoutError(_) | SdkError::ResponseError { .. } => true,
+        SdkError::DispatchFailure(e) => !e.is_user(),
+        SdkError::ServiceError { raw, .. } => {
+            let status = raw.http().status();
+            status.is_server_error() || status.as_u16() == 429
+        }
+        SdkError::ConstructionFailure(_) => false,
+    }
+}
+
+/// Reads an object through ranged `get_object` requests, so only the parts
+/// that are read get downloaded, e.g. the header of a flatpak bundle. Reads
+/// block until their request finishes, so use it inside `web::block`.
+pub struct S3RangeReader {
+    s3: Client,
+    bucket: String,
+    key: String,
+    size: u64,
+    position: u64,
+    runtime: Handle,
+}
+
+impl S3RangeReader {
+    /// Looks up the object's size. Its requests are later run on the runtime
+    /// this is called from.
+    pub async fn open(
+        s3: &Client,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Self, SdkError<HeadObjectError>> {
+        let head = retry_s3("head_object", || {
+            s3.head_object().bucket(bucket).key(key).send()
+        })
+        .await?;
+        Ok(S3RangeReader {
+            s3: s3.clone(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            size: u64::try_from(head.content_length()).unwrap_or(0),
+            position: 0,
+            runtime: Handle::current(),
+        })
+    }
+}
+
+impl Read for S3RangeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = self
+            .size
+            .saturating_sub(self.position)
+            .min(buf.len() as u64);
+        if length == 0 {
+            return Ok(0);
+        }
+        let range = format!("bytes={}-{}", self.position, self.position + length - 1);
+        let bytes = self.runtime.block_on(async {
+            let object = retry_s3("get_object", || {
+                self.s3
+                    .get_object()
+                    .bucket(&self.bucket)
+                    .key(&self.key)
+                    .range(&range)
+                    .send()
+            })
+            .await
+            .map_err(io::Error::other)?;
+            let data = object.body.collect().await.map_err(io::Error::other)?;
+            Ok::<_, io::Error>(data.into_bytes())
+        })?;
+        let read = bytes.len().min(buf.len());
+        buf[..read].copy_from_slice(&bytes[..read]);
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for S3RangeReader {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let position = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Seeked before the start")
+        })?;
+        Ok(self.position)
+    }
+}
+
+#[actix_web::test]
+async fn retries_only_retryable_errors() {
+    use aws_sdk_s3::error::GetObjectError;
+    use std::cell::Cell;
+
+    let attempts = Cell::new(0);
+    let result = retry_s3("get_object", || async {
+        attempts.set(attempts.get() + 1);
+        match attempts.get() {
+            1 => Err(SdkError::<GetObjectError>::TimeoutError("timed out".into())),
+            _ => Ok(()),
+        }
+    })
+    .await;
+    assert!(result.is_ok());
+    assert_eq!(attempts.get(), 2);
+
+    attempts.set(0);
+    let result = retry_s3("get_object", || async {
+        attempts.set(attempts.get() + 1);
+        Err::<(), _>(SdkError::<GetObjectError>::TimeoutError("timed out".into()))
+    })
+    .await;
+    assert!(result.is_err());
+    assert_eq!(attempts.get(), MAX_ATTEMPTS);
+
+    attempts.set(0);
+    let result = retry_s3("get_object", || async {
+        attempts.set(attempts.get() + 1);
+        Err::<(), _>(SdkError::<GetObjectError>::ConstructionFailure(
+            "bad request".into(),
+        ))
+    })
+    .await;
+    assert!(result.is_err());
+    assert_eq!(attempts.get(), 1);
+}
@@ -0,0 +1,153 @@
+use crate::{error::ApiError, games::flatpak::FlatpakFile};
+use actix_web::web;
+use lazy_static::lazy_static;
+use std::{
+    env,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+use tempfile::NamedTempFile;
+
+lazy_static! {
+    /// A GPG keyring holding the keys trusted to sign flatpaks. While it's
+    /// unset, unsigned bundles are still accepted so existing workflows keep
+    /// working during the migration to signed builds.
+    pub static ref TRUSTED_KEYRING: Option<PathBuf> = env::var("FLATPAK_TRUSTED_KEYRING")
+        .ok()
+        .filter(|path| !path.is_empty())
+        // gpgv looks relative keyring paths up in its home directory
+        .map(|path| std::fs::canonicalize(&path).unwrap_or_else(|e| {
+            panic!("FLATPAK_TRUSTED_KEYRING {path} can't be read: {e}")
+        }));
+}
+
+/// Rejects a bundle that isn't signed by a key in [`TRUSTED_KEYRING`], if one
+/// is configured
+pub async fn check_signature(flatpak: &FlatpakFile) -> Result<(), ApiError> {
+    let Some(keyring) = TRUSTED_KEYRING.as_deref() else {
+        return Ok(());
+    };
+    let (commit, signatures) = flatpak.commit_signatures();
+    if signatures.is_empty() {
+        return Err(ApiError::BadRequest(
+            "Flatpak bundle isn't signed".to_string(),
+        ));
+    }
+    if !is_signed_by(keyring.to_path_buf(), commit, signatures).await? {
+        return Err(ApiError::BadRequest(
+            "Flatpak bundle isn't signed by a trusted key".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Whether any of the signatures is by a key in the keyring, running `gpgv`
+/// on the blocking pool so it doesn't stall the worker
+async fn is_signed_by(
+    keyring: PathBuf,
+    commit: Vec<u8>,
+    signatures: Vec<Vec<u8>>,
+) -> Result<bool, ApiError> {
+    web::block(move || {
+        for signature in &signatures {
+            if verify_signature(&keyring, &commit, signature)? {
+                return Ok(true);
+            }
+        }
+        Ok::<_, io::Error>(false)
+    })
+    .await
+    .map_err(ApiError::internal)?
+    .map_err(ApiError::internal)
+}
+
+/// OSTree signs the serialized commit with detached OpenPGP signatures, which
+/// `gpgv` checks against the keyring without needing a GPG home directory
+fn verify_signature(keyring: &Path, commit: &[u8], signature: &[u8]) -> io::Result<bool> {
+    let mut commit_file = NamedTempFile::new()?;
+    commit_file.write_all(commit)?;
+    let mut signature_file = NamedTempFile::new()?;
+    signature_file.write_all(signature)?;
+    let status = Command::new("gpgv")
+        .arg("--keyring")
+        .arg(keyring)
+        .arg(signature_file.path())
+        .arg(commit_file.path())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    Ok(status.success())
+}
+
+#[test]
+fn commit_signatures_reads_the_detached_metadata() {
+    use crate::games::flatpak::sample_bundle_with_metadata;
+    use glib::{ToVariant, VariantDict};
+
+    let unsigned = FlatpakFile::load(sample_bundle_with_metadata(
+        vec![("ref", "app/test/x86_64/master".to_variant())],
+        0,
+    ))
+    .unwrap();
+    assert!(unsigned.commit_signatures().1.is_empty());
+
+    let detached = VariantDict::new(None);
+    let signatures = vec![vec![1u8, 2, 3], vec![4u8, 5]];
+    detached.insert_value("ostree.gpgsigs", &signatures.to_variant());
+    let signed = FlatpakFile::load(sample_bundle_with_metadata(
+        vec![
+            ("ref", "app/test/x86_64/master".to_variant()),
+            ("ostree.commitmeta", detached.end()),
+        ],
+        0,
+    ))
+    .unwrap();
+    let (commit, found) = signed.commit_signatures();
+    assert_eq!(commit, unsigned.commit_signatures().0);
+    assert_eq!(found, signatures);
+}
+
+#[actix_web::test]
+async fn signatures_by_untrusted_keys_are_rejected() {
+    let home = tempfile::tempdir().unwrap();
+    let gpg = |args: &[&str]| {
+        let output = Command::new("gpg")
+            .arg("--homedir")
+            .arg(home.path())
+            .args(["--batch", "--pinentry-mode", "loopback", "--passphrase", ""])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        output.stdout
+    };
+    for user in ["trusted@example.com", "stranger@example.com"] {
+        gpg(&["--quick-gen-key", user, "ed25519", "sign", "never"]);
+    }
+    let keyring = home.path().join("trusted.gpg");
+    std::fs::write(&keyring, gpg(&["--export", "trusted@example.com"])).unwrap();
+    let commit = b"commit".to_vec();
+    let mut commit_file = NamedTempFile::new().unwrap();
+    commit_file.write_all(&commit).unwrap();
+    let sign = |user: &str| {
+        gpg(&[
+            "--local-user",
+            user,
+            "--detach-sign",
+            "--output",
+            "-",
+            commit_file.path().to_str().unwrap(),
+        ])
+    };
+    let (trusted, untrusted) = (sign("trusted@example.com"), sign("stranger@example.com"));
+
+    assert!(
+        !is_signed_by(keyring.clone(), commit.clone(), vec![untrusted.clone()])
+            .await
+            .unwrap()
+    );
+    assert!(is_signed_by(keyring, commit, vec![untrusted, trusted])
+        .await
+        .unwrap());
+}
@@ -0,0 +1,218 @@
+use crate::games::s3::retry_s3;
+use actix_web::web;
+use aws_sdk_s3::Client;
+use bytes::Bytes;
+use futures::{lock::Mutex, stream, StreamExt, TryStreamExt};
+use image::{imageops, DynamicImage, ImageError, ImageOutputFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+use sqlx::{query_scalar, PgPool};
+use std::{collections::BTreeMap, error::Error, io::Cursor, sync::Arc};
+use utoipa::ToSchema;
+
+/// Width and height every icon is scaled to within the sprite sheet
+pub const ICON_CELL_SIZE: u32 = 128;
+/// How many icons are fetched from S3 at once while building the sheet
+const CONCURRENT_DOWNLOADS: usize = 8;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub struct SpriteRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct IconAtlas {
+    #[schema(example = 1024)]
+    pub width: u32,
+    #[schema(example = 512)]
+    pub height: u32,
+    /// The region of the sprite sheet holding each game's icon, by game id
+    pub icons: BTreeMap<String, SpriteRect>,
+}
+
+pub struct IconSprite {
+    pub png: Vec<u8>,
+    pub atlas: IconAtlas,
+}
+
+impl IconSprite {
+    /// Packs icons into a roughly square grid of [`ICON_CELL_SIZE`] cells
+    pub fn pack(icons: Vec<(String, DynamicImage)>) -> Result<Self, ImageError> {
+        let columns = (icons.len() as f64).sqrt().ceil().max(1.0) as u32;
+        let rows = (icons.len() as u32).div_ceil(columns).max(1);
+        let mut sheet = RgbaImage::new(columns * ICON_CELL_SIZE, rows * ICON_CELL_SIZE);
+        let mut atlas = IconAtlas {
+            width: sheet.width(),
+            height: sheet.height(),
+            icons: BTreeMap::new(),
+        };
+        for (index, (id, icon)) in icons.into_iter().enumerate() {
+            let cell = SpriteRect {
+                x: (index as u32 % columns) * ICON_CELL_SIZE,
+                y: (index as u32 / columns) * ICON_CELL_SIZE,
+                width: ICON_CELL_SIZE,
+                height: ICON_CELL_SIZE,
+            };
+            let icon = icon
+                .resize_exact(
+                    ICON_CELL_SIZE,
+                    ICON_CELL_SIZE,
+                    imageops::FilterType::Triangle,
+                )
+                .to_rgba8();
+            imageops::replace(&mut sheet, &icon, cell.x as i64, cell.y as i64);
+            atlas.icons.insert(id, cell);
+        }
+        let mut png = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(sheet).write_to(&mut png, ImageOutputFormat::Png)?;
+        Ok(IconSprite {
+            png: png.into_inner(),
+            atlas,
+        })
+    }
+
+    /// Fetches and decodes every game's icon, leaving out games whose icon is
+    /// missing or unreadable rather than failing the whole sheet. Decoding
+    /// and packing are left to the blocking pool.
+    pub async fn build(db: &PgPool, s3: &Client, bucket: &str) -> Result<Self, Box<dyn Error>> {
+        let ids: Vec<String> =
+            query_scalar("SELECT id FROM game WHERE published AND deleted_at IS NULL ORDER BY id")
+                .fetch_all(db)
+                .await?;
+        let icons: Vec<(String, Bytes)> = stream::iter(ids)
+            .map(|id| async move {
+                let icon = download_icon(s3, bucket, &id).await.ok();
+                Ok::<_, Box<dyn Error>>(icon.map(|icon| (id, icon)))
+            })
+            .buffered(CONCURRENT_DOWNLOADS)
+            .try_filter_map(|icon| async move { Ok(icon) })
+            .try_collect()
+            .await?;
+        Ok(web::block(move || {
+            let icons = icons
+                .into_iter()
+                .filter_map(|(id, icon)| decode_icon(icon).ok().map(|icon| (id, icon)))
+                .collect();
+            Self::pack(icons)
+        })
+        .await??)
+    }
+}
+
+async fn download_icon(s3: &Client, bucket: &str, id: &str) -> Result<Bytes, Box<dyn Error>> {
+    let download = || {
+        s3.get_object()
+            .bucket(bucket)
+            .key(format!("{}/icon", id))
+            .send()
+    };
+    let object = retry_s3("get_object", download).await?;
+    Ok(object.body.collect().await?.into_bytes())
+}
+
+fn decode_icon(bytes: Bytes) -> Result<DynamicImage, ImageError> {
+    // The decoder's default allocation limit keeps a hostile icon from
+    // exhausting memory
+    image::io::Reader::new(Cursor::new(bytes))
+        .with_guessed_format()?
+        .decode()
+}
+
+/// The most recently built sprite sheet, rebuilt on demand after any icon
+/// changes. Invalidating bumps a generation counter instead of waiting on an
+/// in-flight build, and a build that started before the bump is handed back
+/// to its caller but never cached, so an invalidation can't be lost to a
+/// build that read the old icons.
+#[derive(Default)]
+pub struct IconSpriteCache {
+    cached: std::sync::Mutex<CachedSprite>,
+    /// Held while building so concurrent misses share one build
+    building: Mutex<()>,
+}
+
+#[derive(Default)]
+struct CachedSprite {
+    generation: u64,
+    sprite: Option<Arc<IconSprite>>,
+}
+
+impl IconSpriteCache {
+    pub async fn get_or_build(
+        &self,
+        db: &PgPool,
+        s3: &Client,
+        bucket: &str,
+    ) -> Result<Arc<IconSprite>, Box<dyn Error>> {
+        if let Some(sprite) = self.current().1 {
+            return Ok(sprite);
+        }
+        let _building = self.building.lock().await;
+        let (generation, sprite) = self.current();
+        if let Some(sprite) = sprite {
+            return Ok(sprite);
+        }
+        let sprite = Arc::new(IconSprite::build(db, s3, bucket).await?);
+        self.store(generation, sprite.clone());
+        Ok(sprite)
+    }
+
+    pub fn invalidate(&self) {
+        let mut cached = self.cached.lock().unwrap();
+        cached.generation += 1;
+        cached.sprite = None;
+    }
+
+    fn current(&self) -> (u64, Option<Arc<IconSprite>>) {
+        let cached = self.cached.lock().unwrap();
+        (cached.generation, cached.sprite.clone())
+    }
+
+    /// Caches a sprite built at `generation`, unless it was invalidated since
+    fn store(&self, generation: u64, sprite: Arc<IconSprite>) {
+        let mut cached = self.cached.lock().unwrap();
+        if cached.generation == generation {
+            cached.sprite = Some(sprite);
+        }
+    }
+}
+
+#[test]
+fn pack_places_icons_in_a_square_grid() {
+    let icons = (0..5)
+        .map(|index| {
+            let icon = RgbaImage::from_pixel(16, 16, image::Rgba([index * 40, 0, 0, 255]));
+            (format!("game-{index}"), DynamicImage::ImageRgba8(icon))
+        })
+        .collect();
+    let sprite = IconSprite::pack(icons).unwrap();
+    assert_eq!(sprite.atlas.width, 3 * ICON_CELL_SIZE);
+    assert_eq!(sprite.atlas.height, 2 * ICON_CELL_SIZE);
+    assert_eq!(
+        sprite.atlas.icons["game-4"],
+        SpriteRect {
+            x: ICON_CELL_SIZE,
+            y: ICON_CELL_SIZE,
+            width: ICON_CELL_SIZE,
+            height: ICON_CELL_SIZE,
+        }
+    );
+    let sheet = image::load_from_memory(&sprite.png).unwrap().to_rgba8();
+    assert_eq!(
+        sheet.get_pixel(ICON_CELL_SIZE + 1, ICON_CELL_SIZE + 1),
+        &image::Rgba([160, 0, 0, 255])
+    );
+}
+
+#[test]
+fn invalidate_discards_a_build_started_before_it() {
+    let cache = IconSpriteCache::default();
+    let sprite = Arc::new(IconSprite::pack(vec![]).unwrap());
+    let (generation, _) = cache.current();
+    cache.invalidate();
+    cache.store(generation, sprite.clone());
+    assert!(cache.current().1.is_none());
+    cache.store(cache.current().0, sprite);
+    assert!(cache.current().1.is_some());
+}
@@ -1,25 +1,45 @@
-use std::{fs::File, io::Read};
+use std::{
+    fs::File,
+    io::{Read, Seek, Write},
+};
 
-use crate::app::{configure_app, get_app_data};
+use crate::app::{
+    configure_app, get_app_data, normalize_path, record_connection, ConnectionSocket,
+};
 #[cfg(test)]
 use crate::{
-    models::GameWithTags,
+    error::ErrorBody,
+    events::{GameEvent, GameEventKind},
+    games::{
+        flatpak::{sample_bundle, sample_bundle_with_metadata, FlatpakSummary},
+        routes::{
+            export_stream, hang_up_after, BatchDeleteResult, BatchDeleteStatus, DownloadUrl,
+            FlatpakHash, GameExport, GameRating, GameStatus, ImportResult, IntegrityProblem,
+            IntegrityReport,
+        },
+        sprite::IconAtlas,
+    },
+    models::{Game, GameSummary, GameVersion, GameWithTags},
     tests::{
         get_test_server, TEST_GAME_A, TEST_GAME_A_WITH_TAGS, TEST_GAME_B, TEST_GAME_B_WITH_TAGS,
-        TEST_GAME_C, TEST_GAME_D, TEST_GAME_E,
+        TEST_GAME_C, TEST_GAME_D, TEST_GAME_E, TEST_TAG_1,
     },
 };
 
-use actix_web::{test, App};
+use actix_web::{
+    body::{BodySize, MessageBody},
+    test, App,
+};
+use glib::ToVariant;
 
 #[derive(Debug)]
 pub struct GameUploadTest {
-    pub game: File,
     pub banner: File,
     pub icon: File,
     pub title: String,
     pub description: String,
     pub author: String,
+    pub tags: String,
 }
 
 impl GameUploadTest {
@@ -46,19 +66,19 @@ impl GameUploadTest {
             "Content-Disposition: form-data; name=\"author\"\r\n\r\n{}\r\n",
             self.author
         ));
+        out_str.push_str(boundary);
+        out_str.push('\r');
+        out_str.push('\n');
+        out_str.push_str(&format!(
+            "Content-Disposition: form-data; name=\"tags\"\r\n\r\n{}\r\n",
+            self.tags
+        ));
 
         out_str.push_str(boundary);
         out_str.push('\r');
         out_str.push('\n');
-        out_str.push_str("Content-Disposition: form-data; name=\"game\"; filename=\"game.zip\"\r\nContent-Type: application/zip\r\n\r\n");
+        out_str.push_str("Content-Disposition: form-data; name=\"banner\"; filename=\"banner\"\r\nContent-Type: image/png\r\n\r\n");
         let mut out_vec: Vec<u8> = Vec::from(out_str.as_bytes());
-        let game_file = &mut self.game;
-        let _ = game_file.read_to_end(&mut out_vec);
-        let x = format!(
-            "\r\n\r\n{}\r\nContent-Disposition: form-data; name=\"banner\"; filename=\"banner\"\r\nContent-Type: image/png\r\n\r\n",
-            boundary
-        );
-        out_vec.append(&mut Vec::from(x.as_bytes()));
         let banner_file = &mut self.banner;
         let _ = banner_file.read_to_end(&mut out_vec);
         let x = format!(
@@ -92,7 +112,8 @@ impl FileUploadTest {
         let mut out_vec: Vec<u8> = Vec::from(out_str.as_bytes());
         let file = &mut self.file;
         let _ = file.read_to_end(&mut out_vec);
-        let x = format!("\r\n\r\n{}--", boundary);
+        // The CRLF before the closing boundary belongs to the delimiter, not the file
+        let x = format!("\r\n{}--", boundary);
         out_vec.append(&mut Vec::from(x.as_bytes()));
         out_vec
     }
@@ -101,7 +122,7 @@ impl FileUploadTest {
 #[actix_web::test]
 async fn test_get_all_games() {
     let srv = get_test_server().await;
-    let req = srv.get("/games/");
+    let req = srv.get("/api/games/");
     let mut res = req.send().await.unwrap();
     println!(
         "{} | {}",
@@ -111,34 +132,71 @@ async fn test_get_all_games() {
     assert!(res.status().is_success());
 }
 
+#[actix_web::test]
+async fn test_malformed_game_id() {
+    let srv = get_test_server().await;
+    for uri in [
+        "/api/games/not-a-uuid",
+        "/api/games/AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA",
+        "/api/games/GGGGGGGG-GGGG-GGGG-GGGG-GGGGGGGGGGGG/game",
+        "/api/games/1'%20OR%20'1'='1/banner",
+    ] {
+        let mut res = srv.get(uri).send().await.unwrap();
+        assert_eq!(res.status().as_u16(), 400, "{uri}");
+        let error: ErrorBody = res.json().await.unwrap();
+        assert_eq!(error.detail, "Invalid game id format");
+    }
+    let res = srv
+        .get("/api/games/00000000-0000-0000-0000-000000000000")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+}
+
 #[actix_web::test]
 async fn test_get_game_no_tags() {
     let srv = get_test_server().await;
-    let req = srv.get(format!("/games/{}", TEST_GAME_B.id));
+    let req = srv.get(format!("/api/games/{}", TEST_GAME_B.id));
     let mut res = req.send().await.unwrap();
     assert!(res.status().is_success());
     let game_data: GameWithTags = res.json::<GameWithTags>().await.unwrap();
+    // Other tests replace this game's images, which moves updated_at on
+    assert!(game_data.updated_at >= TEST_GAME_B_WITH_TAGS.updated_at);
+    let game_data = GameWithTags {
+        updated_at: TEST_GAME_B_WITH_TAGS.updated_at,
+        ..game_data
+    };
     assert_eq!(game_data, *TEST_GAME_B_WITH_TAGS);
 }
 
 #[actix_web::test]
 async fn test_get_game_with_tags() {
     let srv = get_test_server().await;
-    let req = srv.get(format!("/games/{}", TEST_GAME_A.id));
+    let req = srv.get(format!("/api/games/{}", TEST_GAME_A.id));
     let mut res = req.send().await.unwrap();
     assert!(res.status().is_success());
     let game_data: GameWithTags = res.json::<GameWithTags>().await.unwrap();
+    // Other tests replace this game's images, which moves updated_at on
+    assert!(game_data.updated_at >= TEST_GAME_A_WITH_TAGS.updated_at);
+    let game_data = GameWithTags {
+        updated_at: TEST_GAME_A_WITH_TAGS.updated_at,
+        ..game_data
+    };
     assert_eq!(game_data, *TEST_GAME_A_WITH_TAGS);
 }
 
 #[actix_web::test]
 async fn test_edit_game_data() {
     let srv = get_test_server().await;
-    let mut edited_game = TEST_GAME_C.clone();
-    edited_game.name = "I changed the name!".to_string();
-    edited_game.description = "I changed the description!".to_string();
+    let edited_game = serde_json::json!({
+        "name": "I changed the name!",
+        "description": "I changed the description!",
+        "author": TEST_GAME_C.author,
+        "tags": ["TestTag4"],
+    });
     let req = srv
-        .put(format!("/games/{}", edited_game.id))
+        .put(format!("/api/games/{}", TEST_GAME_C.id))
         .insert_header(("frontend_api_key", "TESTING"));
     let mut res = req.send_json(&edited_game).await.unwrap();
     println!(
@@ -155,7 +213,7 @@ async fn test_edit_game_data_unauthorized() {
     let mut edited_game = TEST_GAME_C.clone();
     edited_game.name = "I changed the name!".to_string();
     edited_game.description = "I changed the description!".to_string();
-    let req = srv.put(format!("/games/{}", edited_game.id));
+    let req = srv.put(format!("/api/games/{}", edited_game.id));
     let mut res = req.send_json(&edited_game).await.unwrap();
     println!(
         "{} | {}",
@@ -169,7 +227,7 @@ async fn test_edit_game_data_unauthorized() {
 async fn test_delete_game() {
     let srv = get_test_server().await;
     let req = srv
-        .delete(format!("/games/{}", TEST_GAME_D.id))
+        .delete(format!("/api/games/{}", TEST_GAME_D.id))
         .insert_header(("frontend_api_key", "TESTING"));
     let res = req.send().await.unwrap();
     assert!(res.status().is_success());
@@ -178,7 +236,7 @@ async fn test_delete_game() {
 #[actix_web::test]
 async fn test_delete_game_unauthorized() {
     let srv = get_test_server().await;
-    let req = srv.delete(format!("/games/{}", TEST_GAME_D.id));
+    let req = srv.delete(format!("/api/games/{}", TEST_GAME_D.id));
     let mut res = req.send().await.unwrap();
     println!(
         "{} | {}",
@@ -188,30 +246,138 @@ async fn test_delete_game_unauthorized() {
     assert_eq!(res.status().as_u16(), 401);
 }
 
+#[actix_web::test]
+async fn test_delete_game_precondition_failed() {
+    let srv = get_test_server().await;
+    let req = srv
+        .delete(format!("/api/games/{}", TEST_GAME_E.id))
+        .insert_header(("frontend_api_key", "TESTING"))
+        .insert_header(("If-Unmodified-Since", "Thu, 01 Jan 2015 00:00:00 GMT"));
+    let mut res = req.send().await.unwrap();
+    println!(
+        "{} | {}",
+        res.status(),
+        std::str::from_utf8(&res.body().await.unwrap()).unwrap_or("Unreadable")
+    );
+    assert_eq!(res.status().as_u16(), 412);
+}
+
+#[actix_web::test]
+async fn test_soft_delete_and_restore_game() {
+    let srv = get_test_server().await;
+    let id = "99999999-9999-9999-9999-999999999999";
+    let delete = |query: &str| {
+        srv.delete(format!("/api/games/{id}{query}"))
+            .insert_header(("frontend_api_key", "TESTING"))
+            .send()
+    };
+    let restore = || {
+        srv.post(format!("/api/games/{id}/restore"))
+            .insert_header(("frontend_api_key", "TESTING"))
+            .send()
+    };
+    let get = || srv.get(format!("/api/games/{id}")).send();
+
+    assert!(delete("").await.unwrap().status().is_success());
+    assert_eq!(get().await.unwrap().status().as_u16(), 404);
+    let mut res = srv.get("/api/games?limit=100").send().await.unwrap();
+    let games: Vec<GameWithTags> = res.json().await.unwrap();
+    assert!(!games.iter().any(|game| game.id == id));
+    assert_eq!(delete("").await.unwrap().status().as_u16(), 404);
+
+    assert!(restore().await.unwrap().status().is_success());
+    assert!(get().await.unwrap().status().is_success());
+    assert_eq!(restore().await.unwrap().status().as_u16(), 409);
+
+    assert!(delete("?hard=true").await.unwrap().status().is_success());
+    assert_eq!(restore().await.unwrap().status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn test_restore_game_name_taken() {
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    let create = || {
+        let payload = new_game_payload(
+            "------------------43123453263245325234",
+            "Restored Twice",
+            None,
+        );
+        test::TestRequest::post()
+            .uri("/api/games")
+            .append_header(("frontend_api_key", "TESTING"))
+            .append_header((
+                "Content-Type",
+                "mutlipart/form-data; boundary=----------------43123453263245325234",
+            ))
+            .append_header(("Content-Length", payload.len()))
+            .set_payload(payload)
+            .to_request()
+    };
+    let original: Game = test::call_and_read_body_json(&app, create()).await;
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/games/{}", original.id))
+        .append_header(("frontend_api_key", "TESTING"))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let replacement: Game = test::call_and_read_body_json(&app, create()).await;
+
+    let restore = || {
+        test::TestRequest::post()
+            .uri(&format!("/api/games/{}/restore", original.id))
+            .append_header(("frontend_api_key", "TESTING"))
+            .to_request()
+    };
+    assert_eq!(
+        test::call_service(&app, restore()).await.status().as_u16(),
+        409
+    );
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{}", original.id))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 404);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/games/{}?hard=true", replacement.id))
+        .append_header(("frontend_api_key", "TESTING"))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    assert!(test::call_service(&app, restore())
+        .await
+        .status()
+        .is_success());
+}
+
 #[actix_web::test]
 async fn test_add_game() {
-    let gamefile = File::open("TESTING/data/FFFFFFFF-FFFF-FFFF-FFFF-FFFFFFFFFFFF/FFFFFFFF-FFFF-FFFF-FFFF-FFFFFFFFFFFF.zip").unwrap();
     let bannerfile =
         File::open("TESTING/data/FFFFFFFF-FFFF-FFFF-FFFF-FFFFFFFFFFFF/banner").unwrap();
     let iconfile = File::open("TESTING/data/FFFFFFFF-FFFF-FFFF-FFFF-FFFFFFFFFFFF/icon").unwrap();
     let mut gameupload = GameUploadTest {
-        game: gamefile,
         banner: bannerfile,
         icon: iconfile,
         title: "Chom".to_string(),
         description: "Chom".to_string(),
         author: "skyz".to_string(),
+        tags: TEST_TAG_1.name.clone(),
     };
     let app_data = get_app_data().await;
     let app = test::init_service(
         App::new()
+            .wrap(normalize_path())
             .configure(configure_app)
             .app_data(app_data.clone()),
     )
     .await;
     let payload = gameupload.to_payload("------------------43123453263245325234");
     let req = test::TestRequest::post()
-        .uri("/games/")
+        .uri("/api/games/")
         .append_header(("frontend_api_key", "TESTING"))
         .append_header((
             "Content-Type",
@@ -229,28 +395,28 @@ async fn test_add_game() {
 
 #[actix_web::test]
 async fn test_add_game_unauthorized() {
-    let gamefile = File::open("TESTING/data/FFFFFFFF-FFFF-FFFF-FFFF-FFFFFFFFFFFF/FFFFFFFF-FFFF-FFFF-FFFF-FFFFFFFFFFFF.zip").unwrap();
     let bannerfile =
         File::open("TESTING/data/FFFFFFFF-FFFF-FFFF-FFFF-FFFFFFFFFFFF/banner").unwrap();
     let iconfile = File::open("TESTING/data/FFFFFFFF-FFFF-FFFF-FFFF-FFFFFFFFFFFF/icon").unwrap();
     let mut gameupload = GameUploadTest {
-        game: gamefile,
         banner: bannerfile,
         icon: iconfile,
         title: "Chom".to_string(),
         description: "Chom".to_string(),
         author: "skyz".to_string(),
+        tags: TEST_TAG_1.name.clone(),
     };
     let app_data = get_app_data().await;
     let app = test::init_service(
         App::new()
+            .wrap(normalize_path())
             .configure(configure_app)
             .app_data(app_data.clone()),
     )
     .await;
     let payload = gameupload.to_payload("------------------43123453263245325234");
     let req = test::TestRequest::post()
-        .uri("/games/")
+        .uri("/api/games/")
         .append_header((
             "Content-Type",
             "mutlipart/form-data; boundary=----------------43123453263245325234",
@@ -267,32 +433,74 @@ async fn test_add_game_unauthorized() {
 
 #[actix_web::test]
 async fn test_get_game_binary() {
-    let srv = get_test_server().await;
-    let req = srv.get(format!("/games/{}/game", TEST_GAME_E.id));
-    let mut res = req.send().await.unwrap();
-    println!(
-        "{} | {}",
-        res.status(),
-        std::str::from_utf8(&res.body().await.unwrap()).unwrap_or("Unreadable")
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    // None of the seeded games has a flatpak, so this one is published with
+    // its own
+    let id = uuid::Uuid::new_v4().to_string();
+    let bundle = sample_bundle(
+        &format!("app/edu.rit.csh.devcade.game.id-{id}/x86_64/master"),
+        0,
     );
-    assert!(res.status().is_success());
+    let payload = new_game_payload(
+        "------------------43123453263245325234",
+        "Downloadable",
+        Some(bundle.clone()),
+    );
+    let req = test::TestRequest::post()
+        .uri("/api/games/publish")
+        .append_header(("frontend_api_key", "TESTING"))
+        .append_header((
+            "Content-Type",
+            "mutlipart/form-data; boundary=----------------43123453263245325234",
+        ))
+        .append_header(("Content-Length", payload.len()))
+        .set_payload(payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 201);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{id}/game"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    println!("{} | {:?}", resp.status(), resp.response().body());
+    assert!(resp.status().is_success());
+    assert_eq!(test::read_body(resp).await, bundle);
 }
 
 #[actix_web::test]
 async fn test_edit_game_binary() {
-    let gamefile = File::open("TESTING/data/HHHHHHHH-HHHH-HHHH-HHHH-HHHHHHHHHHHH/HHHHHHHH-HHHH-HHHH-HHHH-HHHHHHHHHHHH.zip").unwrap();
+    let id = "77777777-7777-7777-7777-777777777777";
+    let mut gamefile = tempfile::tempfile().unwrap();
+    gamefile
+        .write_all(&sample_bundle(
+            &format!("app/edu.rit.csh.devcade.game.id-{id}/x86_64/master"),
+            0,
+        ))
+        .unwrap();
+    gamefile.rewind().unwrap();
     let mut fileupload = FileUploadTest { file: gamefile };
     let app_data = get_app_data().await;
     let app = test::init_service(
         App::new()
+            .wrap(normalize_path())
             .configure(configure_app)
             .app_data(app_data.clone()),
     )
     .await;
-    let payload =
-        fileupload.to_payload("------------------43123453263245325234", "application/zip");
+    let payload = fileupload.to_payload(
+        "------------------43123453263245325234",
+        "application/vnd.flatpak",
+    );
     let req = test::TestRequest::put()
-        .uri("/games/GGGGGGGG-GGGG-GGGG-GGGG-GGGGGGGGGGGG/game")
+        .uri(&format!("/api/games/{id}/game"))
         .append_header(("frontend_api_key", "TESTING"))
         .append_header((
             "Content-Type",
@@ -310,11 +518,12 @@ async fn test_edit_game_binary() {
 
 #[actix_web::test]
 async fn test_edit_game_binary_unauthorized() {
-    let gamefile = File::open("TESTING/data/HHHHHHHH-HHHH-HHHH-HHHH-HHHHHHHHHHHH/HHHHHHHH-HHHH-HHHH-HHHH-HHHHHHHHHHHH.zip").unwrap();
+    let gamefile = File::open("TESTING/data/88888888-8888-8888-8888-888888888888/88888888-8888-8888-8888-888888888888.zip").unwrap();
     let mut fileupload = FileUploadTest { file: gamefile };
     let app_data = get_app_data().await;
     let app = test::init_service(
         App::new()
+            .wrap(normalize_path())
             .configure(configure_app)
             .app_data(app_data.clone()),
     )
@@ -322,7 +531,7 @@ async fn test_edit_game_binary_unauthorized() {
     let payload =
         fileupload.to_payload("------------------43123453263245325234", "application/zip");
     let req = test::TestRequest::put()
-        .uri("/games/GGGGGGGG-GGGG-GGGG-GGGG-GGGGGGGGGGGG/game")
+        .uri("/api/games/77777777-7777-7777-7777-777777777777/game")
         .append_header((
             "Content-Type",
             "mutlipart/form-data; boundary=----------------43123453263245325234",
@@ -340,7 +549,7 @@ async fn test_edit_game_binary_unauthorized() {
 #[actix_web::test]
 async fn test_get_game_banner() {
     let srv = get_test_server().await;
-    let req = srv.get(format!("/games/{}/banner", TEST_GAME_E.id));
+    let req = srv.get(format!("/api/games/{}/banner", TEST_GAME_E.id));
     let mut res = req.send().await.unwrap();
     println!(
         "{} | {}",
@@ -350,21 +559,133 @@ async fn test_get_game_banner() {
     assert!(res.status().is_success());
 }
 
+#[actix_web::test]
+async fn test_get_game_banner_falls_back_without_webp() {
+    let srv = get_test_server().await;
+    let original =
+        std::fs::read("TESTING/data/EEEEEEEE-EEEE-EEEE-EEEE-EEEEEEEEEEEE/banner").unwrap();
+    let mut res = srv
+        .get(format!("/api/games/{}/banner", TEST_GAME_E.id))
+        .insert_header(("Accept", "image/webp,*/*;q=0.8"))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.headers().get("vary").unwrap(), "accept");
+    assert_eq!(res.body().await.unwrap(), original);
+}
+
+#[actix_web::test]
+async fn test_get_game_banner_as_webp() {
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    let mut bannerfile = tempfile::tempfile().unwrap();
+    image::RgbImage::new(640, 240)
+        .write_to(&mut bannerfile, image::ImageOutputFormat::Png)
+        .unwrap();
+    bannerfile.rewind().unwrap();
+    let mut events = app_data.events.subscribe();
+    let mut fileupload = FileUploadTest { file: bannerfile };
+    let payload = fileupload.to_payload("------------------43123453263245325234", "image/png");
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/games/{}/banner", TEST_GAME_C.id))
+        .append_header(("frontend_api_key", "TESTING"))
+        .append_header((
+            "Content-Type",
+            "mutlipart/form-data; boundary=----------------43123453263245325234",
+        ))
+        .append_header(("Content-Length", payload.len()))
+        .set_payload(payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let event = events.recv().await.unwrap();
+    assert_eq!(event.event, GameEventKind::Updated);
+    assert_eq!(event.game_id, TEST_GAME_C.id);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{}/banner", TEST_GAME_C.id))
+        .append_header(("Accept", "image/webp,*/*;q=0.8"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert_eq!(resp.headers().get("content-type").unwrap(), "image/webp");
+    let body = test::read_body(resp).await;
+    assert_eq!(
+        image::guess_format(&body).unwrap(),
+        image::ImageFormat::WebP
+    );
+}
+
+#[actix_web::test]
+async fn test_get_game_assets_not_uploaded() {
+    let srv = get_test_server().await;
+    // The draft game has a row but nothing in S3 yet
+    let draft = "88888888-8888-8888-8888-888888888888";
+    for (asset, message) in [
+        ("game", "Game has no flatpak uploaded"),
+        ("banner", "Game has no banner uploaded"),
+        ("icon", "Game has no icon uploaded"),
+    ] {
+        let mut res = srv
+            .get(format!("/api/games/{draft}/{asset}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status().as_u16(), 404);
+        let error: ErrorBody = res.json().await.unwrap();
+        assert_eq!(error, ErrorBody::new("not_found", message));
+    }
+}
+
+#[actix_web::test]
+async fn test_get_game_banner_not_modified() {
+    let srv = get_test_server().await;
+    let url = format!("/api/games/{}/banner", TEST_GAME_E.id);
+    let res = srv.get(&url).send().await.unwrap();
+    assert!(res.status().is_success());
+    assert!(res.headers().contains_key("cache-control"));
+    let etag = res.headers().get("etag").unwrap().clone();
+    let mut res = srv
+        .get(&url)
+        .insert_header(("If-None-Match", etag.clone()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 304);
+    assert_eq!(res.headers().get("etag"), Some(&etag));
+    assert!(res.body().await.unwrap().is_empty());
+    let res = srv
+        .get(&url)
+        .insert_header(("If-None-Match", "\"stale\""))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+}
+
 #[actix_web::test]
 async fn test_edit_game_banner() {
     let bannerfile =
-        File::open("TESTING/data/HHHHHHHH-HHHH-HHHH-HHHH-HHHHHHHHHHHH/banner").unwrap();
+        File::open("TESTING/data/88888888-8888-8888-8888-888888888888/banner").unwrap();
     let mut fileupload = FileUploadTest { file: bannerfile };
     let app_data = get_app_data().await;
     let app = test::init_service(
         App::new()
+            .wrap(normalize_path())
             .configure(configure_app)
             .app_data(app_data.clone()),
     )
     .await;
     let payload = fileupload.to_payload("------------------43123453263245325234", "image/png");
     let req = test::TestRequest::put()
-        .uri("/games/GGGGGGGG-GGGG-GGGG-GGGG-GGGGGGGGGGGG/banner")
+        .uri("/api/games/77777777-7777-7777-7777-777777777777/banner")
         .append_header(("frontend_api_key", "TESTING"))
         .append_header((
             "Content-Type",
@@ -380,21 +701,189 @@ async fn test_edit_game_banner() {
     assert!(resp.status().is_success());
 }
 
+#[actix_web::test]
+async fn test_large_banner_streams() {
+    // Noise doesn't compress, so this is a banner of several megabytes
+    let mut seed: u32 = 1;
+    let banner = image::RgbImage::from_fn(2400, 800, |_, _| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        let [r, g, b, _] = seed.to_le_bytes();
+        image::Rgb([r, g, b])
+    });
+    let mut bannerfile = tempfile::tempfile().unwrap();
+    banner
+        .write_to(&mut bannerfile, image::ImageOutputFormat::Png)
+        .unwrap();
+    bannerfile.rewind().unwrap();
+    let mut expected = vec![];
+    bannerfile.read_to_end(&mut expected).unwrap();
+    assert!(expected.len() > 4 * 1024 * 1024);
+    bannerfile.rewind().unwrap();
+    let mut fileupload = FileUploadTest { file: bannerfile };
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    let payload = fileupload.to_payload("------------------43123453263245325234", "image/png");
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/games/{}/banner", TEST_GAME_B.id))
+        .append_header(("frontend_api_key", "TESTING"))
+        .append_header((
+            "Content-Type",
+            "mutlipart/form-data; boundary=----------------43123453263245325234",
+        ))
+        .append_header(("Content-Length", payload.len()))
+        .set_payload(payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{}/banner", TEST_GAME_B.id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert_eq!(resp.headers().get("content-type").unwrap(), "image/png");
+    assert_eq!(resp.response().body().size(), BodySize::Stream);
+    let body = test::read_body(resp).await;
+    assert!(body == expected);
+}
+
+#[actix_web::test]
+async fn test_banner_content_type_round_trips() {
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    for (format, mimetype) in [
+        (image::ImageOutputFormat::Png, "image/png"),
+        (image::ImageOutputFormat::Jpeg(80), "image/jpeg"),
+    ] {
+        let mut bannerfile = tempfile::tempfile().unwrap();
+        image::RgbImage::new(640, 240)
+            .write_to(&mut bannerfile, format)
+            .unwrap();
+        bannerfile.rewind().unwrap();
+        let mut fileupload = FileUploadTest { file: bannerfile };
+        let payload = fileupload.to_payload("------------------43123453263245325234", mimetype);
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/games/{}/banner", TEST_GAME_C.id))
+            .append_header(("frontend_api_key", "TESTING"))
+            .append_header((
+                "Content-Type",
+                "mutlipart/form-data; boundary=----------------43123453263245325234",
+            ))
+            .append_header(("Content-Length", payload.len()))
+            .set_payload(payload)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/games/{}/banner", TEST_GAME_C.id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("content-type").unwrap(), mimetype);
+    }
+}
+
+#[actix_web::test]
+async fn test_misshapen_images_rejected() {
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    for (component, width, height) in [("banner", 100, 100), ("icon", 300, 100)] {
+        let mut imagefile = tempfile::tempfile().unwrap();
+        image::RgbImage::new(width, height)
+            .write_to(&mut imagefile, image::ImageOutputFormat::Png)
+            .unwrap();
+        imagefile.rewind().unwrap();
+        let mut fileupload = FileUploadTest { file: imagefile };
+        let payload = fileupload.to_payload("------------------43123453263245325234", "image/png");
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/games/{}/{}", TEST_GAME_A.id, component))
+            .append_header(("frontend_api_key", "TESTING"))
+            .append_header((
+                "Content-Type",
+                "mutlipart/form-data; boundary=----------------43123453263245325234",
+            ))
+            .append_header(("Content-Length", payload.len()))
+            .set_payload(payload)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 400);
+        let body = test::read_body(resp).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains(&format!("{width}x{height}")), "{body}");
+    }
+}
+
+#[actix_web::test]
+async fn test_oversized_banner_rejected() {
+    let mut bannerfile = tempfile::tempfile().unwrap();
+    bannerfile.write_all(&vec![0; 11 * 1024 * 1024]).unwrap();
+    bannerfile.rewind().unwrap();
+    let mut fileupload = FileUploadTest { file: bannerfile };
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    let payload = fileupload.to_payload("------------------43123453263245325234", "image/png");
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/games/{}/banner", TEST_GAME_A.id))
+        .append_header(("frontend_api_key", "TESTING"))
+        .append_header((
+            "Content-Type",
+            "mutlipart/form-data; boundary=----------------43123453263245325234",
+        ))
+        .append_header(("Content-Length", payload.len()))
+        .set_payload(payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 413);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{}/banner", TEST_GAME_A.id))
+        .to_request();
+    let body = test::read_body(test::call_service(&app, req).await).await;
+    let original =
+        std::fs::read("TESTING/data/AAAAAAAA-AAAA-AAAA-AAAA-AAAAAAAAAAAA/banner").unwrap();
+    assert!(body == original);
+}
+
 #[actix_web::test]
 async fn test_edit_game_banner_unauthorized() {
     let bannerfile =
-        File::open("TESTING/data/HHHHHHHH-HHHH-HHHH-HHHH-HHHHHHHHHHHH/banner").unwrap();
+        File::open("TESTING/data/88888888-8888-8888-8888-888888888888/banner").unwrap();
     let mut fileupload = FileUploadTest { file: bannerfile };
     let app_data = get_app_data().await;
     let app = test::init_service(
         App::new()
+            .wrap(normalize_path())
             .configure(configure_app)
             .app_data(app_data.clone()),
     )
     .await;
     let payload = fileupload.to_payload("------------------43123453263245325234", "image/png");
     let req = test::TestRequest::put()
-        .uri("/games/GGGGGGGG-GGGG-GGGG-GGGG-GGGGGGGGGGGG/banner")
+        .uri("/api/games/77777777-7777-7777-7777-777777777777/banner")
         .append_header((
             "Content-Type",
             "mutlipart/form-data; boundary=----------------43123453263245325234",
@@ -412,7 +901,7 @@ async fn test_edit_game_banner_unauthorized() {
 #[actix_web::test]
 async fn test_get_game_icon() {
     let srv = get_test_server().await;
-    let req = srv.get(format!("/games/{}/icon", TEST_GAME_E.id));
+    let req = srv.get(format!("/api/games/{}/icon", TEST_GAME_E.id));
     let mut res = req.send().await.unwrap();
     println!(
         "{} | {}",
@@ -424,18 +913,20 @@ async fn test_get_game_icon() {
 
 #[actix_web::test]
 async fn test_edit_game_icon() {
-    let iconfile = File::open("TESTING/data/HHHHHHHH-HHHH-HHHH-HHHH-HHHHHHHHHHHH/icon").unwrap();
+    let iconfile = File::open("TESTING/data/88888888-8888-8888-8888-888888888888/icon").unwrap();
     let mut fileupload = FileUploadTest { file: iconfile };
     let app_data = get_app_data().await;
     let app = test::init_service(
         App::new()
+            .wrap(normalize_path())
             .configure(configure_app)
             .app_data(app_data.clone()),
     )
     .await;
+    let mut events = app_data.events.subscribe();
     let payload = fileupload.to_payload("------------------43123453263245325234", "image/png");
     let req = test::TestRequest::put()
-        .uri("/games/GGGGGGGG-GGGG-GGGG-GGGG-GGGGGGGGGGGG/icon")
+        .uri("/api/games/77777777-7777-7777-7777-777777777777/icon")
         .append_header(("frontend_api_key", "TESTING"))
         .append_header((
             "Content-Type",
@@ -449,22 +940,62 @@ async fn test_edit_game_icon() {
     let resp = test::call_service(&app, req).await;
     println!("{} | {:?}", resp.status(), resp.response().body());
     assert!(resp.status().is_success());
+    let event = events.recv().await.unwrap();
+    assert_eq!(event.event, GameEventKind::Updated);
+    assert_eq!(event.game_id, "77777777-7777-7777-7777-777777777777");
+}
+
+#[actix_web::test]
+async fn test_icon_thumbnail_generated() {
+    let iconfile = File::open("TESTING/data/88888888-8888-8888-8888-888888888888/icon").unwrap();
+    let mut fileupload = FileUploadTest { file: iconfile };
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    let payload = fileupload.to_payload("------------------43123453263245325234", "image/png");
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/games/{}/icon", TEST_GAME_B.id))
+        .append_header(("frontend_api_key", "TESTING"))
+        .append_header((
+            "Content-Type",
+            "mutlipart/form-data; boundary=----------------43123453263245325234",
+        ))
+        .append_header(("Content-Length", payload.len()))
+        .set_payload(payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{}/icon/thumb", TEST_GAME_B.id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert_eq!(resp.headers().get("content-type").unwrap(), "image/png");
+    let thumb = image::load_from_memory(&test::read_body(resp).await).unwrap();
+    assert!(thumb.width() <= 128 && thumb.height() <= 128);
 }
 
 #[actix_web::test]
 async fn test_edit_game_icon_unauthorized() {
-    let iconfile = File::open("TESTING/data/HHHHHHHH-HHHH-HHHH-HHHH-HHHHHHHHHHHH/icon").unwrap();
+    let iconfile = File::open("TESTING/data/88888888-8888-8888-8888-888888888888/icon").unwrap();
     let mut fileupload = FileUploadTest { file: iconfile };
     let app_data = get_app_data().await;
     let app = test::init_service(
         App::new()
+            .wrap(normalize_path())
             .configure(configure_app)
             .app_data(app_data.clone()),
     )
     .await;
     let payload = fileupload.to_payload("------------------43123453263245325234", "image/png");
     let req = test::TestRequest::put()
-        .uri("/games/GGGGGGGG-GGGG-GGGG-GGGG-GGGGGGGGGGGG/icon")
+        .uri("/api/games/77777777-7777-7777-7777-777777777777/icon")
         .append_header((
             "Content-Type",
             "mutlipart/form-data; boundary=----------------43123453263245325234",
@@ -478,3 +1009,1590 @@ async fn test_edit_game_icon_unauthorized() {
     println!("{} | {:?}", resp.status(), resp.response().body());
     assert_eq!(resp.status().as_u16(), 401);
 }
+
+#[actix_web::test]
+async fn test_get_popular_games() {
+    let srv = get_test_server().await;
+    let req = srv.get("/api/games/popular?limit=3");
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<GameWithTags> = res.json::<Vec<GameWithTags>>().await.unwrap();
+    assert!(games.len() <= 3);
+    assert!(games
+        .windows(2)
+        .all(|pair| pair[0].downloads >= pair[1].downloads));
+}
+
+#[actix_web::test]
+async fn test_rate_game() {
+    let srv = get_test_server().await;
+    let id = "77777777-7777-7777-7777-777777777777";
+    let rate = |body: serde_json::Value| srv.post(format!("/api/games/{id}/rate")).send_json(&body);
+
+    let mut res = rate(serde_json::json!({"score": 2, "device_id": "cabinet-1"}))
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 201);
+    let rating: GameRating = res.json().await.unwrap();
+    assert_eq!(
+        rating,
+        GameRating {
+            average: Some(2.0),
+            count: 1
+        }
+    );
+
+    // The same device rating again replaces its score rather than adding one
+    let mut res = rate(serde_json::json!({"score": 5, "device_id": "cabinet-1"}))
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 200);
+    let rating: GameRating = res.json().await.unwrap();
+    assert_eq!(
+        rating,
+        GameRating {
+            average: Some(5.0),
+            count: 1
+        }
+    );
+
+    let res = rate(serde_json::json!({"score": 5})).await.unwrap();
+    assert_eq!(res.status().as_u16(), 201);
+    for body in [
+        serde_json::json!({"score": 0}),
+        serde_json::json!({"score": 6}),
+        serde_json::json!({"score": 3, "device_id": ""}),
+    ] {
+        let res = rate(body).await.unwrap();
+        assert_eq!(res.status().as_u16(), 400);
+    }
+
+    let mut res = srv
+        .get(format!("/api/games/{id}/rating"))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let rating: GameRating = res.json().await.unwrap();
+    assert_eq!(
+        rating,
+        GameRating {
+            average: Some(5.0),
+            count: 2
+        }
+    );
+
+    let res = srv
+        .post("/api/games/00000000-0000-0000-0000-000000000000/rate")
+        .send_json(&serde_json::json!({"score": 3}))
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+
+    let mut res = srv
+        .get("/api/games/popular?sort=rating&limit=50")
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<GameWithTags> = res.json().await.unwrap();
+    assert_eq!(games[0].id, id);
+}
+
+#[actix_web::test]
+async fn test_record_play() {
+    let srv = get_test_server().await;
+    let id = "77777777-7777-7777-7777-777777777777";
+    let mut res = srv.get(format!("/api/games/{id}")).send().await.unwrap();
+    let before: GameWithTags = res.json().await.unwrap();
+    for _ in 0..2 {
+        let res = srv
+            .post(format!("/api/games/{id}/played"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status().as_u16(), 204);
+    }
+    let mut res = srv.get(format!("/api/games/{id}")).send().await.unwrap();
+    let after: GameWithTags = res.json().await.unwrap();
+    assert_eq!(after.play_count, before.play_count + 2);
+    assert!(after.last_played_at.is_some());
+    assert_eq!(after.downloads, before.downloads);
+
+    let mut res = srv.get("/api/games/recently-played").send().await.unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<GameWithTags> = res.json().await.unwrap();
+    assert_eq!(games[0].id, id);
+    assert!(games.iter().all(|game| game.last_played_at.is_some()));
+
+    let res = srv
+        .post("/api/games/00000000-0000-0000-0000-000000000000/played")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn test_drafts_not_played() {
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    let payload = new_game_payload(
+        "------------------43123453263245325234",
+        "Played Draft",
+        None,
+    );
+    let req = test::TestRequest::post()
+        .uri("/api/games")
+        .append_header(("frontend_api_key", "TESTING"))
+        .append_header((
+            "Content-Type",
+            "mutlipart/form-data; boundary=----------------43123453263245325234",
+        ))
+        .append_header(("Content-Length", payload.len()))
+        .set_payload(payload)
+        .to_request();
+    let draft: Game = test::call_and_read_body_json(&app, req).await;
+    assert!(!draft.published);
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/games/{}/played", draft.id))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 404);
+
+    // Nor is a draft that has a play recorded some other way
+    sqlx::query("UPDATE game SET last_played_at = now() + interval '1 hour' WHERE id = $1")
+        .bind(&draft.id)
+        .execute(&app_data.db)
+        .await
+        .unwrap();
+    let req = test::TestRequest::get()
+        .uri("/api/games/recently-played?limit=50")
+        .to_request();
+    let games: Vec<GameWithTags> = test::call_and_read_body_json(&app, req).await;
+    assert!(games.iter().all(|game| game.id != draft.id));
+}
+
+#[actix_web::test]
+async fn test_get_game_events() {
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/api/games/events")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+    let mut body = Box::pin(resp.into_body());
+
+    // Drafts aren't announced
+    let mut draft = TEST_GAME_A.clone();
+    draft.published = false;
+    app_data.events.publish(GameEvent::updated(&draft)).await;
+    app_data
+        .events
+        .publish(GameEvent::deleted(&TEST_GAME_A.id))
+        .await;
+    let message = std::future::poll_fn(|cx| body.as_mut().poll_next(cx))
+        .await
+        .unwrap()
+        .unwrap();
+    let message = std::str::from_utf8(&message).unwrap();
+    let data = message
+        .strip_prefix("event: deleted\ndata: ")
+        .and_then(|message| message.strip_suffix("\n\n"))
+        .unwrap_or_else(|| panic!("{message}"));
+    let data: serde_json::Value = serde_json::from_str(data).unwrap();
+    assert_eq!(data["game_id"], TEST_GAME_A.id);
+}
+
+#[actix_web::test]
+async fn test_get_similar_games() {
+    let srv = get_test_server().await;
+    let req = srv.get(format!("/api/games/{}/similar?limit=3", TEST_GAME_A.id));
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<GameWithTags> = res.json::<Vec<GameWithTags>>().await.unwrap();
+    assert!(games.len() <= 3);
+    assert!(games.iter().all(|game| game.id != TEST_GAME_A.id));
+}
+
+#[actix_web::test]
+async fn test_get_similar_games_missing_game() {
+    let srv = get_test_server().await;
+    let req = srv.get("/api/games/00000000-0000-0000-0000-000000000000/similar");
+    let mut res = req.send().await.unwrap();
+    println!(
+        "{} | {}",
+        res.status(),
+        std::str::from_utf8(&res.body().await.unwrap()).unwrap_or("Unreadable")
+    );
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn test_missing_game_not_found() {
+    let srv = get_test_server().await;
+    let id = "00000000-0000-0000-0000-000000000000";
+    for path in ["", "/game", "/banner", "/icon"] {
+        let res = srv
+            .get(format!("/api/games/{id}{path}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status().as_u16(), 404, "GET /api/games/{id}{path}");
+    }
+    let res = srv
+        .delete(format!("/api/games/{id}"))
+        .insert_header(("frontend_api_key", "TESTING"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn test_edit_game_data_invalid_fields() {
+    let srv = get_test_server().await;
+    let edited_game = serde_json::json!({
+        "name": "",
+        "description": TEST_GAME_C.description,
+        "author": TEST_GAME_C.author,
+        "tags": ["definitely-not-a-tag"],
+    });
+    let req = srv
+        .put(format!("/api/games/{}", TEST_GAME_C.id))
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req.send_json(&edited_game).await.unwrap();
+    assert_eq!(res.status().as_u16(), 400);
+    let errors: serde_json::Value = res.json().await.unwrap();
+    let fields: Vec<&str> = errors["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, vec!["name", "tags"]);
+}
+
+#[actix_web::test]
+async fn test_edit_game_data_too_long() {
+    let srv = get_test_server().await;
+    let tags: Vec<String> = (0..21).map(|n| format!("TestTag{n}")).collect();
+    let edited_game = serde_json::json!({
+        "name": "C".repeat(129),
+        "description": "C".repeat(4097),
+        "author": TEST_GAME_C.author,
+        "tags": tags,
+    });
+    let req = srv
+        .put(format!("/api/games/{}", TEST_GAME_C.id))
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req.send_json(&edited_game).await.unwrap();
+    assert_eq!(res.status().as_u16(), 400);
+    let errors: serde_json::Value = res.json().await.unwrap();
+    let fields: Vec<&str> = errors["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, vec!["name", "description", "tags"]);
+}
+
+#[actix_web::test]
+async fn test_edit_game_author() {
+    let srv = get_test_server().await;
+    let id = "77777777-7777-7777-7777-777777777777";
+    let edited_game = serde_json::json!({
+        "name": "TestGameG",
+        "description": "TestGameG Description",
+        "author": "nonexistent-user",
+        "tags": [],
+    });
+    let req = srv
+        .put(format!("/api/games/{id}"))
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req.send_json(&edited_game).await.unwrap();
+    assert_eq!(res.status().as_u16(), 400);
+    let errors: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(errors["errors"][0]["field"], "author");
+
+    let mut edited_game = edited_game;
+    edited_game["author"] = "evan".into();
+    let req = srv
+        .put(format!("/api/games/{id}"))
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req.send_json(&edited_game).await.unwrap();
+    assert!(res.status().is_success());
+    let game: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(game["author"], "evan");
+
+    // Leaving the author out keeps the current one, tags land on their stored
+    // spelling and repeated or blank ones are folded away
+    edited_game.as_object_mut().unwrap().remove("author");
+    edited_game["tags"] = serde_json::json!([
+        format!(" {} ", TEST_TAG_1.name.to_lowercase()),
+        TEST_TAG_1.name,
+        ""
+    ]);
+    let req = srv
+        .put(format!("/api/games/{id}"))
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req.send_json(&edited_game).await.unwrap();
+    assert!(res.status().is_success());
+    let game: GameWithTags = res.json().await.unwrap();
+    assert_eq!(game.author, "evan");
+    assert_eq!(game.user.id, "evan");
+    assert_eq!(game.tags, vec![TEST_TAG_1.clone()]);
+}
+
+#[actix_web::test]
+async fn test_patch_game() {
+    let srv = get_test_server().await;
+    let url = "/api/games/10101010-1010-1010-1010-101010101010";
+    let req = srv
+        .patch(url)
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req
+        .send_json(&serde_json::json!({ "tags": [TEST_TAG_1.name] }))
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let game: GameWithTags = res.json().await.unwrap();
+    assert_eq!(game.name, "TestGameJ");
+    assert_eq!(game.tags, vec![TEST_TAG_1.clone()]);
+
+    // Absent and null fields are left alone
+    let req = srv
+        .patch(url)
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req
+        .send_json(&serde_json::json!({ "description": "Patched", "tags": null }))
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let game: GameWithTags = res.json().await.unwrap();
+    assert_eq!(game.name, "TestGameJ");
+    assert_eq!(game.description, "Patched");
+    assert_eq!(game.tags, vec![TEST_TAG_1.clone()]);
+
+    let req = srv
+        .patch(url)
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req
+        .send_json(&serde_json::json!({ "name": "", "tags": [] }))
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 400);
+    let errors: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(errors["errors"][0]["field"], "name");
+}
+
+#[actix_web::test]
+async fn test_edit_game_if_match() {
+    let srv = get_test_server().await;
+    let url = format!("/api/games/{}", TEST_GAME_E.id);
+    let res = srv.get(&url).send().await.unwrap();
+    let etag = res.headers().get("etag").unwrap().clone();
+    let unchanged = serde_json::json!({
+        "name": TEST_GAME_E.name,
+        "description": TEST_GAME_E.description,
+        "tags": [],
+    });
+    let res = srv
+        .put(&url)
+        .insert_header(("frontend_api_key", "TESTING"))
+        .insert_header(("If-Match", etag.clone()))
+        .send_json(&unchanged)
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_ne!(res.headers().get("etag"), Some(&etag));
+
+    // The first edit moved the game past the version this client read
+    let res = srv
+        .put(&url)
+        .insert_header(("frontend_api_key", "TESTING"))
+        .insert_header(("If-Match", etag))
+        .send_json(&unchanged)
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 412);
+}
+
+#[actix_web::test]
+async fn test_export_games() {
+    let srv = get_test_server().await;
+    let res = srv.get("/api/games/export").send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 401);
+    let mut res = srv
+        .get("/api/games/export")
+        .insert_header(("frontend_api_key", "TESTING"))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let body = res.body().await.unwrap();
+    let games: Vec<GameExport> = std::str::from_utf8(&body)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let e = games
+        .iter()
+        .find(|export| export.game.id == TEST_GAME_E.id)
+        .unwrap();
+    assert!(e.objects.contains(&format!("{}/banner", TEST_GAME_E.id)));
+    assert!(e.objects.contains(&format!("{}/icon", TEST_GAME_E.id)));
+    assert!(!e.objects.iter().any(|key| key.ends_with(".flatpak")));
+    // Drafts are backed up too
+    assert!(games
+        .iter()
+        .any(|export| export.game.id == "88888888-8888-8888-8888-888888888888"));
+}
+
+#[actix_web::test]
+async fn test_export_drops_slow_reader() {
+    use futures::StreamExt;
+    use std::{sync::Arc, time::Duration};
+
+    let app_data = get_app_data().await;
+    let db = app_data.db.clone();
+    // Reading one game at a time, the next page isn't fetched until the
+    // reader takes this one, and by then the export has run out of time
+    let mut pages = Box::pin(export_stream(
+        db.clone(),
+        Arc::default(),
+        1,
+        Duration::from_millis(200),
+    ));
+    let first = pages.next().await.unwrap().unwrap();
+    assert_eq!(first.iter().filter(|&&byte| byte == b'\n').count(), 1);
+    actix_web::rt::time::sleep(Duration::from_millis(300)).await;
+    assert!(pages.next().await.unwrap().is_err());
+
+    // A client that stops reading leaves the body unpolled once the socket's
+    // buffers fill, so the connection is cut from outside it
+    let server = actix_web::HttpServer::new(|| {
+        App::new().route(
+            "/export",
+            actix_web::web::get().to(|req: actix_web::HttpRequest| async move {
+                let socket = req.conn_data::<ConnectionSocket>().unwrap().try_clone();
+                let endless = futures::stream::repeat_with(|| {
+                    Ok::<_, std::io::Error>(bytes::Bytes::from(vec![b'x'; 64 * 1024]))
+                });
+                actix_web::HttpResponse::Ok().streaming(hang_up_after(
+                    endless,
+                    Some(socket.unwrap()),
+                    Duration::from_millis(300),
+                ))
+            }),
+        )
+    })
+    .workers(1)
+    .on_connect(record_connection)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    let addr = server.addrs()[0];
+    let server = server.run();
+    let handle = server.handle();
+    actix_web::rt::spawn(server);
+    let (head, closed) = actix_web::web::block(move || {
+        use std::io::{ErrorKind, Read, Write};
+        let mut socket = std::net::TcpStream::connect(addr).unwrap();
+        socket
+            .write_all(b"GET /export HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut head = [0; 16];
+        socket.read_exact(&mut head).unwrap();
+        std::thread::sleep(Duration::from_millis(600));
+        // Whatever was already sent can still be read, but then it has to end
+        let mut buffer = vec![0; 64 * 1024];
+        let reading = std::time::Instant::now();
+        let closed = loop {
+            match socket.read(&mut buffer) {
+                Ok(0) => break true,
+                Ok(_) if reading.elapsed() < Duration::from_secs(5) => {}
+                Err(e) if e.kind() == ErrorKind::ConnectionReset => break true,
+                _ => break false,
+            }
+        };
+        (String::from_utf8_lossy(&head).into_owned(), closed)
+    })
+    .await
+    .unwrap();
+    handle.stop(false).await;
+    assert!(head.starts_with("HTTP/1.1 200"));
+    assert!(closed);
+}
+
+#[actix_web::test]
+async fn test_import_games() {
+    let srv = get_test_server().await;
+    let import = |body: String| {
+        srv.post("/api/games/import")
+            .insert_header(("frontend_api_key", "TESTING"))
+            .insert_header(("Content-Type", "application/x-ndjson"))
+            .send_body(body)
+    };
+    let exported = |id: &str, name: &str| {
+        let mut game = TEST_GAME_A_WITH_TAGS.clone();
+        game.id = id.to_string();
+        game.name = name.to_string();
+        serde_json::to_string(&GameExport {
+            game,
+            objects: vec![],
+        })
+        .unwrap()
+    };
+    let id = "11111111-1111-1111-1111-111111111111";
+    let mut res = import(exported(id, "Imported")).await.unwrap();
+    assert!(res.status().is_success());
+    let result: ImportResult = res.json().await.unwrap();
+    assert_eq!(result.inserted, 1);
+
+    let body = format!(
+        "{}\n\n{}\n",
+        exported(id, "Imported"),
+        exported(id, "Renamed")
+    );
+    let mut res = import(body).await.unwrap();
+    let result: ImportResult = res.json().await.unwrap();
+    assert_eq!(
+        result,
+        ImportResult {
+            inserted: 0,
+            updated: 1,
+            skipped: 1,
+        }
+    );
+    let mut res = srv.get(format!("/api/games/{id}")).send().await.unwrap();
+    let game: GameWithTags = res.json().await.unwrap();
+    assert_eq!(game.name, "Renamed");
+    assert_eq!(game.tags, TEST_GAME_A_WITH_TAGS.tags);
+
+    // A bad line anywhere means nothing is imported
+    let other = "22222222-2222-2222-2222-222222222222";
+    let body = format!("{}\n{{\"id\": \"not a game\"}}\n", exported(other, "Other"));
+    let mut res = import(body).await.unwrap();
+    assert_eq!(res.status().as_u16(), 400);
+    let error: ErrorBody = res.json().await.unwrap();
+    assert!(error.detail.starts_with("Line 2:"), "{error:?}");
+    let res = srv.get(format!("/api/games/{other}")).send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+
+    let mut res = import(exported("not-a-uuid", "Other")).await.unwrap();
+    assert_eq!(res.status().as_u16(), 400);
+    let error: ErrorBody = res.json().await.unwrap();
+    assert!(error.detail.starts_with("Line 1: id:"), "{error:?}");
+
+    // Importing over a deleted game doesn't bring it back
+    let res = srv
+        .delete(format!("/api/games/{id}"))
+        .insert_header(("frontend_api_key", "TESTING"))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let mut res = import(exported(id, "Imported again")).await.unwrap();
+    let result: ImportResult = res.json().await.unwrap();
+    assert_eq!(result.updated, 1);
+    let res = srv.get(format!("/api/games/{id}")).send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn test_import_games_announces_changes() {
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    let mut events = app_data.events.subscribe();
+    let id = "12121212-1212-1212-1212-121212121212";
+    for (name, kind) in [
+        ("Announced", GameEventKind::Created),
+        ("Renamed", GameEventKind::Updated),
+    ] {
+        let mut game = TEST_GAME_A_WITH_TAGS.clone();
+        game.id = id.to_string();
+        game.name = name.to_string();
+        let body = serde_json::to_string(&GameExport {
+            game,
+            objects: vec![],
+        })
+        .unwrap();
+        let req = test::TestRequest::post()
+            .uri("/api/games/import")
+            .append_header(("frontend_api_key", "TESTING"))
+            .append_header(("Content-Type", "application/x-ndjson"))
+            .set_payload(body)
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.event, kind);
+        assert_eq!(event.game.unwrap().name, name);
+    }
+}
+
+#[actix_web::test]
+async fn test_get_all_games_trailing_slash() {
+    let srv = get_test_server().await;
+    for path in ["/api/games", "/api/games/", "/api/games//"] {
+        let res = srv.get(path).send().await.unwrap();
+        assert!(
+            res.status().is_success(),
+            "{path} returned {}",
+            res.status()
+        );
+    }
+}
+
+#[actix_web::test]
+async fn test_get_icon_sprite_and_atlas() {
+    let srv = get_test_server().await;
+    let mut res = srv.get("/api/games/icons/atlas").send().await.unwrap();
+    assert!(res.status().is_success());
+    let atlas: IconAtlas = res.json().await.unwrap();
+    assert!(atlas.icons.contains_key(&TEST_GAME_A.id));
+    let mut res = srv.get("/api/games/icons/sprite").send().await.unwrap();
+    assert!(res.status().is_success());
+    let sprite = image::load_from_memory(&res.body().limit(1 << 26).await.unwrap()).unwrap();
+    assert_eq!(
+        (sprite.width(), sprite.height()),
+        (atlas.width, atlas.height)
+    );
+}
+
+#[actix_web::test]
+async fn test_get_all_games_by_author() {
+    let srv = get_test_server().await;
+    let req = srv.get(format!("/api/games?author={}", TEST_GAME_A.author));
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<GameWithTags> = res.json::<Vec<GameWithTags>>().await.unwrap();
+    assert!(!games.is_empty());
+    assert!(games.iter().all(|game| game.author == TEST_GAME_A.author));
+
+    let req = srv.get("/api/games?author=nobody");
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    assert!(res.json::<Vec<GameWithTags>>().await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_get_all_games_paginated() {
+    let srv = get_test_server().await;
+    let mut res = srv.get("/api/games?limit=2&offset=1").send().await.unwrap();
+    assert!(res.status().is_success());
+    let total: usize = res
+        .headers()
+        .get("x-total-count")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let games: Vec<GameWithTags> = res.json::<Vec<GameWithTags>>().await.unwrap();
+    assert_eq!(games.len(), 2.min(total.saturating_sub(1)));
+
+    let res = srv.get("/api/games?limit=-1").send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[actix_web::test]
+async fn test_get_all_games_summary() {
+    let srv = get_test_server().await;
+    let mut res = srv
+        .get("/api/games?fields=summary&author=skyz&limit=200")
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<GameSummary> = res.json().await.unwrap();
+    let game_a = games
+        .into_iter()
+        .find(|game| game.id == TEST_GAME_A.id)
+        .unwrap();
+    assert_eq!(
+        game_a,
+        GameSummary {
+            id: TEST_GAME_A.id.clone(),
+            name: TEST_GAME_A.name.clone(),
+            icon_url: format!("/api/games/{}/icon", TEST_GAME_A.id),
+            author_name: "Joe Abbate".to_string(),
+            tags: vec![TEST_TAG_1.name.clone()],
+            size_bytes: None,
+        }
+    );
+
+    let res = srv
+        .get("/api/games?fields=everything")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[actix_web::test]
+async fn test_get_all_games_by_cursor() {
+    let srv = get_test_server().await;
+    let mut ids: Vec<String> = vec![];
+    let mut cursor = Some(String::new());
+    while let Some(after) = cursor {
+        let mut res = srv
+            .get(format!("/api/games?limit=2&cursor={after}"))
+            .send()
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+        cursor = res
+            .headers()
+            .get("x-next-cursor")
+            .map(|cursor| cursor.to_str().unwrap().to_string());
+        let games: Vec<GameWithTags> = res.json().await.unwrap();
+        assert!(games.len() <= 2);
+        ids.extend(games.into_iter().map(|game| game.id));
+    }
+    assert!(!ids.is_empty());
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let res = srv
+        .get("/api/games?cursor=&sort=-date")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[actix_web::test]
+async fn test_get_all_games_sorted() {
+    let srv = get_test_server().await;
+    let position =
+        |games: &[GameWithTags], id: &str| games.iter().position(|game| game.id == id).unwrap();
+    for (sort, first, second) in [
+        ("-name", &TEST_GAME_B.id, &TEST_GAME_A.id),
+        ("author", &TEST_GAME_B.id, &TEST_GAME_A.id),
+        ("-author", &TEST_GAME_A.id, &TEST_GAME_B.id),
+    ] {
+        let mut res = srv
+            .get(format!("/api/games?sort={sort}"))
+            .send()
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+        let games: Vec<GameWithTags> = res.json::<Vec<GameWithTags>>().await.unwrap();
+        assert!(position(&games, first) < position(&games, second), "{sort}");
+    }
+
+    let mut res = srv.get("/api/games?sort=-updated").send().await.unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<GameWithTags> = res.json::<Vec<GameWithTags>>().await.unwrap();
+    assert!(games
+        .windows(2)
+        .all(|pair| pair[0].updated_at >= pair[1].updated_at));
+
+    let res = srv
+        .get("/api/games?sort=name;DROP%20TABLE%20game")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[actix_web::test]
+async fn test_search_games() {
+    let srv = get_test_server().await;
+    let req = srv.get(format!("/api/games/search?q={}", TEST_GAME_A.name));
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<GameWithTags> = res.json::<Vec<GameWithTags>>().await.unwrap();
+    assert_eq!(games[0].id, TEST_GAME_A.id);
+
+    let res = srv.get("/api/games/search?q=%20%20").send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[actix_web::test]
+async fn test_get_all_games_by_tags() {
+    let srv = get_test_server().await;
+    let tag = &TEST_GAME_A_WITH_TAGS.tags[0].name;
+    let req = srv.get(format!("/api/games?tags={tag},NoSuchTag"));
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<GameWithTags> = res.json::<Vec<GameWithTags>>().await.unwrap();
+    assert!(games.iter().any(|game| game.id == TEST_GAME_A.id));
+    assert!(games
+        .iter()
+        .all(|game| game.tags.iter().any(|game_tag| &game_tag.name == tag)));
+
+    let req = srv.get(format!("/api/games?tags={tag},NoSuchTag&match=all"));
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    assert!(res.json::<Vec<GameWithTags>>().await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_batch_delete_games() {
+    let srv = get_test_server().await;
+    let deleted = "FFFFFFFF-FFFF-FFFF-FFFF-FFFFFFFFFFFF";
+    let missing = "00000000-0000-0000-0000-000000000000";
+    let req = srv
+        .post("/api/games/batch-delete")
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req.send_json(&vec![deleted, missing]).await.unwrap();
+    assert!(res.status().is_success());
+    let results: Vec<BatchDeleteResult> = res.json().await.unwrap();
+    assert_eq!(
+        results,
+        vec![
+            BatchDeleteResult {
+                id: deleted.to_string(),
+                status: BatchDeleteStatus::Deleted,
+            },
+            BatchDeleteResult {
+                id: missing.to_string(),
+                status: BatchDeleteStatus::NotFound,
+            },
+        ]
+    );
+    let res = srv
+        .get(format!("/api/games/{deleted}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+
+    // It's only hidden, so deleting it again finds nothing, but it can still
+    // be purged
+    let batch_delete = |query: &str| {
+        srv.post(format!("/api/games/batch-delete{query}"))
+            .insert_header(("frontend_api_key", "TESTING"))
+    };
+    let mut res = batch_delete("").send_json(&vec![deleted]).await.unwrap();
+    let results: Vec<BatchDeleteResult> = res.json().await.unwrap();
+    assert_eq!(results[0].status, BatchDeleteStatus::NotFound);
+    let mut res = batch_delete("?hard=true")
+        .send_json(&vec![deleted])
+        .await
+        .unwrap();
+    let results: Vec<BatchDeleteResult> = res.json().await.unwrap();
+    assert_eq!(results[0].status, BatchDeleteStatus::Deleted);
+    let res = srv
+        .post(format!("/api/games/{deleted}/restore"))
+        .insert_header(("frontend_api_key", "TESTING"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn test_batch_delete_games_unauthorized() {
+    let srv = get_test_server().await;
+    let req = srv.post("/api/games/batch-delete");
+    let res = req.send_json(&vec![TEST_GAME_A.id.clone()]).await.unwrap();
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[actix_web::test]
+async fn test_edit_game_binary_wrong_ref() {
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    // A flatpak for a game that doesn't exist is just wrong, but one for
+    // another existing game would clobber it
+    let missing = "00000000-0000-0000-0000-000000000000";
+    let flatpak_ref = |id: &str, arch: &str, branch: &str| {
+        format!("app/edu.rit.csh.devcade.game.id-{id}/{arch}/{branch}")
+    };
+    for (flatpak_ref, status, message) in [
+        (
+            flatpak_ref(missing, "x86_64", "master"),
+            400,
+            format!("built for game {missing}, not"),
+        ),
+        (
+            flatpak_ref(&TEST_GAME_A.id, "x86_64", "master"),
+            409,
+            format!("belongs to game {}", TEST_GAME_A.id),
+        ),
+        (
+            flatpak_ref(&TEST_GAME_B.id, "aarch64", "master"),
+            400,
+            "architecture aarch64 must be one of x86_64".to_string(),
+        ),
+        (
+            flatpak_ref(&TEST_GAME_B.id, "x86_64", "beta"),
+            400,
+            "branch beta must be one of master".to_string(),
+        ),
+    ] {
+        let bundle = sample_bundle(&flatpak_ref, 0);
+        let mut gamefile = tempfile::tempfile().unwrap();
+        gamefile.write_all(&bundle).unwrap();
+        gamefile.rewind().unwrap();
+        let mut fileupload = FileUploadTest { file: gamefile };
+        let payload = fileupload.to_payload(
+            "------------------43123453263245325234",
+            "application/vnd.flatpak",
+        );
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/games/{}/game", TEST_GAME_B.id))
+            .append_header(("frontend_api_key", "TESTING"))
+            .append_header((
+                "Content-Type",
+                "mutlipart/form-data; boundary=----------------43123453263245325234",
+            ))
+            .append_header(("Content-Length", payload.len()))
+            .set_payload(payload)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), status);
+        let body = test::read_body(resp).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains(&message), "{body}");
+    }
+}
+
+#[actix_web::test]
+async fn test_get_game_status() {
+    let srv = get_test_server().await;
+    let mut res = srv
+        .get(format!("/api/games/{}/status", TEST_GAME_E.id))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let status: GameStatus = res.json().await.unwrap();
+    assert_eq!(status.hash, TEST_GAME_E.hash);
+    assert!(!status.flatpak.present);
+    assert_eq!(status.flatpak.size, None);
+    assert!(status.banner.present);
+    assert!(status.banner.size.unwrap() > 0);
+    assert!(status.icon.present);
+    let res = srv
+        .get("/api/games/00000000-0000-0000-0000-000000000000/status")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn test_get_game_download_url() {
+    let srv = get_test_server().await;
+    // Counted as a download, so kept off the games other tests compare whole
+    let id = "77777777-7777-7777-7777-777777777777";
+    let mut res = srv
+        .get(format!("/api/games/{id}/download-url"))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let download: DownloadUrl = res.json().await.unwrap();
+    assert!(download.url.contains(&format!("{id}/{id}.flatpak")));
+    assert!(download.url.contains("X-Amz-Signature="));
+    assert!(download.url.contains("X-Amz-Expires=300"));
+    assert!(download.expires_at > chrono::Utc::now());
+    assert!(!download.hash.is_empty());
+
+    // Drafts without a flatpak have nothing to sign
+    let res = srv
+        .get("/api/games/88888888-8888-8888-8888-888888888888/download-url")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+    let res = srv
+        .get("/api/games/00000000-0000-0000-0000-000000000000/download-url")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn test_get_game_binary_hash() {
+    let srv = get_test_server().await;
+    let mut res = srv
+        .get(format!("/api/games/{}/hash", TEST_GAME_A.id))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let hash: FlatpakHash = res.json().await.unwrap();
+    assert_eq!(
+        hash,
+        FlatpakHash {
+            id: TEST_GAME_A.id.clone(),
+            hash: TEST_GAME_A.hash.clone().unwrap(),
+        }
+    );
+    let res = srv
+        .get("/api/games/00000000-0000-0000-0000-000000000000/hash")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn test_verify_integrity() {
+    let srv = get_test_server().await;
+    let req = srv
+        .post("/api/games/verify-integrity")
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    let report: IntegrityReport = res.json().await.unwrap();
+    assert!(report.checked >= 1);
+    // The seeded games are stored as zips, so none have a flatpak
+    let failure = report
+        .failures
+        .iter()
+        .find(|failure| failure.id == TEST_GAME_A.id)
+        .unwrap();
+    assert_eq!(failure.problem, IntegrityProblem::Missing);
+    assert_eq!(Some(&failure.expected), TEST_GAME_A.hash.as_ref());
+
+    let res = srv
+        .post("/api/games/verify-integrity")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[actix_web::test]
+async fn test_verify_integrity_skips_deleted() {
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    let payload = new_game_payload(
+        "------------------43123453263245325234",
+        "Deleted Unchecked",
+        None,
+    );
+    let req = test::TestRequest::post()
+        .uri("/api/games")
+        .append_header(("frontend_api_key", "TESTING"))
+        .append_header((
+            "Content-Type",
+            "mutlipart/form-data; boundary=----------------43123453263245325234",
+        ))
+        .append_header(("Content-Length", payload.len()))
+        .set_payload(payload)
+        .to_request();
+    let game: Game = test::call_and_read_body_json(&app, req).await;
+    // Deleted with a flatpak recorded, but none stored
+    sqlx::query("UPDATE game SET hash = $2, deleted_at = now() WHERE id = $1")
+        .bind(&game.id)
+        .bind("cd".repeat(32))
+        .execute(&app_data.db)
+        .await
+        .unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/games/verify-integrity")
+        .append_header(("frontend_api_key", "TESTING"))
+        .to_request();
+    let report: IntegrityReport = test::call_and_read_body_json(&app, req).await;
+    assert!(report.failures.iter().all(|failure| failure.id != game.id));
+}
+
+#[actix_web::test]
+async fn test_get_flatpak_summary() {
+    let flatpak_ref = format!(
+        "app/edu.rit.csh.devcade.game.id-{}/x86_64/master",
+        TEST_GAME_C.id
+    );
+    let bundle = sample_bundle_with_metadata(
+        vec![
+            ("ref", flatpak_ref.to_variant()),
+            (
+                "metadata",
+                "[Application]\nruntime=org.freedesktop.Platform/x86_64/22.08\n".to_variant(),
+            ),
+        ],
+        0,
+    );
+    let mut gamefile = tempfile::tempfile().unwrap();
+    gamefile.write_all(&bundle).unwrap();
+    gamefile.rewind().unwrap();
+    let mut fileupload = FileUploadTest { file: gamefile };
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    let payload = fileupload.to_payload(
+        "------------------43123453263245325234",
+        "application/vnd.flatpak",
+    );
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/games/{}/game", TEST_GAME_C.id))
+        .append_header(("frontend_api_key", "TESTING"))
+        .append_header((
+            "Content-Type",
+            "mutlipart/form-data; boundary=----------------43123453263245325234",
+        ))
+        .append_header(("Content-Length", payload.len()))
+        .set_payload(payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    // The second request is answered from the cache
+    for _ in 0..2 {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/games/{}/metadata", TEST_GAME_C.id))
+            .to_request();
+        let summary: FlatpakSummary = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(summary.flatpak_ref, flatpak_ref);
+        assert_eq!(
+            summary.runtime.as_deref(),
+            Some("org.freedesktop.Platform/x86_64/22.08")
+        );
+        assert_eq!(summary.required_flatpak, None);
+    }
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{}/flatpak/metadata", TEST_GAME_C.id))
+        .append_header(("frontend_api_key", "TESTING"))
+        .to_request();
+    let metadata: std::collections::BTreeMap<String, String> =
+        test::call_and_read_body_json(&app, req).await;
+    assert_eq!(metadata["ref"], flatpak_ref);
+}
+
+#[actix_web::test]
+async fn test_edit_game_binary_not_a_flatpak() {
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    // The wrong kind of file is told apart from a corrupt flatpak
+    for (content_type, status, expected) in [
+        (
+            "image/png",
+            415,
+            ErrorBody::new("unsupported_media_type", "Game provided is not a Flatpak!"),
+        ),
+        (
+            "application/vnd.flatpak",
+            400,
+            ErrorBody::new("bad_request", "Incorrect flatpak bundle format"),
+        ),
+    ] {
+        let gamefile =
+            File::open("TESTING/data/BBBBBBBB-BBBB-BBBB-BBBB-BBBBBBBBBBBB/banner").unwrap();
+        let mut fileupload = FileUploadTest { file: gamefile };
+        let payload = fileupload.to_payload("------------------43123453263245325234", content_type);
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/games/{}/game", TEST_GAME_B.id))
+            .append_header(("frontend_api_key", "TESTING"))
+            .append_header((
+                "Content-Type",
+                "mutlipart/form-data; boundary=----------------43123453263245325234",
+            ))
+            .append_header(("Content-Length", payload.len()))
+            .set_payload(payload)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), status);
+        let error: ErrorBody = test::read_body_json(resp).await;
+        assert_eq!(error, expected);
+    }
+}
+
+#[actix_web::test]
+async fn test_get_all_games_hides_drafts() {
+    let srv = get_test_server().await;
+    let draft = "88888888-8888-8888-8888-888888888888";
+    let mut res = srv.get("/api/games?limit=200").send().await.unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<GameWithTags> = res.json::<Vec<GameWithTags>>().await.unwrap();
+    assert!(games.iter().all(|game| game.published));
+    assert!(!games.iter().any(|game| game.id == draft));
+
+    let mut res = srv
+        .get("/api/games?limit=200&include_drafts=true")
+        .insert_header(("frontend_api_key", "TESTING"))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<GameWithTags> = res.json::<Vec<GameWithTags>>().await.unwrap();
+    assert!(games.iter().any(|game| game.id == draft && !game.published));
+
+    // Nor does any other listing, including those it'd match by its tag
+    for listing in [
+        "/api/games/search?q=TestGameH",
+        "/api/games/popular?limit=50",
+        "/api/games/popular?limit=50&sort=rating",
+        "/api/games/AAAAAAAA-AAAA-AAAA-AAAA-AAAAAAAAAAAA/similar",
+        "/api/tags/TestTag1/games",
+        "/api/users/skyz/games",
+    ] {
+        let mut res = srv.get(listing).send().await.unwrap();
+        assert!(res.status().is_success(), "{listing}");
+        let games: Vec<serde_json::Value> = res.json().await.unwrap();
+        assert!(!games.iter().any(|game| game["id"] == draft), "{listing}");
+    }
+
+    let res = srv
+        .get("/api/games?include_drafts=true")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 401);
+}
+
+#[actix_web::test]
+async fn test_game_versions_and_rollback() {
+    let id = "10101010-1010-1010-1010-101010101010";
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    let versions_req = || {
+        test::TestRequest::get()
+            .uri(&format!("/api/games/{id}/versions"))
+            .to_request()
+    };
+    let versions: Vec<GameVersion> = test::call_and_read_body_json(&app, versions_req()).await;
+    assert!(versions.is_empty());
+
+    // Padding tells the uploads apart once they're downloaded, and the third
+    // is a re-upload of the first
+    let flatpak_ref = format!("app/edu.rit.csh.devcade.game.id-{id}/x86_64/master");
+    let bundles = [
+        sample_bundle(&flatpak_ref, 0),
+        sample_bundle(&flatpak_ref, 64),
+    ];
+    for bundle in bundles.iter().chain([&bundles[0]]) {
+        let mut gamefile = tempfile::tempfile().unwrap();
+        gamefile.write_all(bundle).unwrap();
+        gamefile.rewind().unwrap();
+        let mut fileupload = FileUploadTest { file: gamefile };
+        let payload = fileupload.to_payload(
+            "------------------43123453263245325234",
+            "application/vnd.flatpak",
+        );
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/games/{id}/game"))
+            .append_header(("frontend_api_key", "TESTING"))
+            .append_header((
+                "Content-Type",
+                "mutlipart/form-data; boundary=----------------43123453263245325234",
+            ))
+            .append_header(("Content-Length", payload.len()))
+            .set_payload(payload)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+    }
+    let versions: Vec<GameVersion> = test::call_and_read_body_json(&app, versions_req()).await;
+    assert_eq!(
+        versions.iter().map(|v| v.version).collect::<Vec<_>>(),
+        vec![3, 2, 1]
+    );
+    assert!(versions
+        .iter()
+        .all(|v| v.arch == "x86_64" && v.branch == "master"));
+    // Nothing has been uploaded for any other architecture
+    for endpoint in ["game", "hash", "download-url"] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/games/{id}/{endpoint}?arch=aarch64"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 404);
+    }
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{id}/status"))
+        .to_request();
+    let status: GameStatus = test::call_and_read_body_json(&app, req).await;
+    assert!(status.flatpak.present);
+    assert!(status.hash.is_some());
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{id}/status?arch=aarch64"))
+        .to_request();
+    let status: GameStatus = test::call_and_read_body_json(&app, req).await;
+    assert!(!status.flatpak.present);
+    assert_eq!((status.hash, status.size_bytes), (None, None));
+    let stored: i64 =
+        sqlx::query_scalar("SELECT COUNT(DISTINCT key) FROM game_versions WHERE game_id = $1")
+            .bind(id)
+            .fetch_one(&app_data.db)
+            .await
+            .unwrap();
+    assert_eq!(stored, 2);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/games/{id}/rollback/2"))
+        .append_header(("frontend_api_key", "TESTING"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{id}/game"))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, bundles[1]);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/games/{id}/rollback/1"))
+        .append_header(("frontend_api_key", "TESTING"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{id}/game"))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, bundles[0]);
+
+    // Resuming the download sends only the rest of the file
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{id}/game"))
+        .insert_header(("Range", "bytes=10-"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 206);
+    assert_eq!(resp.headers().get("accept-ranges").unwrap(), "bytes");
+    assert_eq!(
+        resp.headers().get("content-range").unwrap(),
+        &format!("bytes 10-{}/{}", bundles[0].len() - 1, bundles[0].len())
+    );
+    let body = test::read_body(resp).await;
+    assert_eq!(body, bundles[0][10..]);
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{id}/game"))
+        .insert_header(("Range", format!("bytes={}-", bundles[0].len())))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 416);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/games/{id}/rollback/4"))
+        .append_header(("frontend_api_key", "TESTING"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 404);
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/games/{id}/rollback/1"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 401);
+}
+
+/// A multipart body with every field of a published game upload
+fn published_game_payload(boundary: &str, flatpak: Vec<u8>) -> Vec<u8> {
+    new_game_payload(boundary, "Published", Some(flatpak))
+}
+
+/// A new game's form, with a flatpak for publishing it in one go
+fn new_game_payload(boundary: &str, title: &str, flatpak: Option<Vec<u8>>) -> Vec<u8> {
+    let mut payload = Vec::new();
+    for (name, value) in [
+        ("title", title),
+        ("description", "Uploaded with its flatpak"),
+        ("author", "skyz"),
+        ("tags", TEST_TAG_1.name.as_str()),
+    ] {
+        payload.extend(
+            format!(
+                "{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+            )
+            .as_bytes(),
+        );
+    }
+    let data = "TESTING/data/FFFFFFFF-FFFF-FFFF-FFFF-FFFFFFFFFFFF";
+    let game = flatpak.map(|flatpak| ("game", "application/vnd.flatpak", flatpak));
+    for (name, content_type, file) in game.into_iter().chain([
+        (
+            "banner",
+            "image/png",
+            std::fs::read(format!("{data}/banner")).unwrap(),
+        ),
+        (
+            "icon",
+            "image/png",
+            std::fs::read(format!("{data}/icon")).unwrap(),
+        ),
+    ]) {
+        payload.extend(
+            format!(
+                "{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"; filename=\"{name}\"\r\nContent-Type: {content_type}\r\n\r\n"
+            )
+            .as_bytes(),
+        );
+        payload.extend(file);
+        payload.extend(b"\r\n");
+    }
+    payload.extend(format!("{boundary}--").as_bytes());
+    payload
+}
+
+#[actix_web::test]
+async fn test_publish_game() {
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    let id = uuid::Uuid::new_v4().to_string();
+    let flatpak_ref = format!("app/edu.rit.csh.devcade.game.id-{id}/x86_64/master");
+    let publish = |flatpak: Vec<u8>| {
+        let payload = published_game_payload("------------------43123453263245325234", flatpak);
+        test::TestRequest::post()
+            .uri("/api/games/publish")
+            .append_header(("frontend_api_key", "TESTING"))
+            .append_header((
+                "Content-Type",
+                "mutlipart/form-data; boundary=----------------43123453263245325234",
+            ))
+            .append_header(("Content-Length", payload.len()))
+            .set_payload(payload)
+            .to_request()
+    };
+    let bundle = sample_bundle(&flatpak_ref, 0);
+    let size = bundle.len() as i64;
+    let resp = test::call_service(&app, publish(bundle)).await;
+    assert_eq!(resp.status().as_u16(), 201);
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{id}"))
+        .to_request();
+    let game: GameWithTags = test::call_and_read_body_json(&app, req).await;
+    assert!(game.published);
+    assert_eq!(game.hash, Some("ab".repeat(32)));
+    assert_eq!(game.size_bytes, Some(size));
+    assert_eq!(game.tags, vec![TEST_TAG_1.clone()]);
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{id}/versions"))
+        .to_request();
+    let versions: Vec<GameVersion> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(versions.len(), 1);
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{id}/status"))
+        .to_request();
+    let status: GameStatus = test::call_and_read_body_json(&app, req).await;
+    assert!(status.flatpak.present);
+    assert_eq!(status.size_bytes, Some(size));
+    assert!(status.banner.present);
+    assert!(status.icon.present);
+
+    // The flatpak names the game, so it can only be published once
+    let resp = test::call_service(&app, publish(sample_bundle(&flatpak_ref, 64))).await;
+    assert_eq!(resp.status().as_u16(), 409);
+
+    let not_a_game = "app/org.example.Game/x86_64/master";
+    let resp = test::call_service(&app, publish(sample_bundle(not_a_game, 0))).await;
+    assert_eq!(resp.status().as_u16(), 400);
+
+    // A flatpak rejected after the game was saved as a draft leaves nothing
+    let rejected = uuid::Uuid::new_v4().to_string();
+    let unaccepted = format!("app/edu.rit.csh.devcade.game.id-{rejected}/aarch64/master");
+    let payload = new_game_payload(
+        "------------------43123453263245325234",
+        "Rejected",
+        Some(sample_bundle(&unaccepted, 0)),
+    );
+    let req = test::TestRequest::post()
+        .uri("/api/games/publish")
+        .append_header(("frontend_api_key", "TESTING"))
+        .append_header((
+            "Content-Type",
+            "mutlipart/form-data; boundary=----------------43123453263245325234",
+        ))
+        .append_header(("Content-Length", payload.len()))
+        .set_payload(payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 400);
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/games/{rejected}"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn test_add_game_duplicate_name() {
+    let app_data = get_app_data().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(normalize_path())
+            .configure(configure_app)
+            .app_data(app_data.clone()),
+    )
+    .await;
+    let add = |uri: &str| {
+        let payload = new_game_payload(
+            "------------------43123453263245325234",
+            &TEST_GAME_A.name.to_uppercase(),
+            None,
+        );
+        test::TestRequest::post()
+            .uri(uri)
+            .append_header(("frontend_api_key", "TESTING"))
+            .append_header((
+                "Content-Type",
+                "mutlipart/form-data; boundary=----------------43123453263245325234",
+            ))
+            .append_header(("Content-Length", payload.len()))
+            .set_payload(payload)
+            .to_request()
+    };
+    let resp = test::call_service(&app, add("/api/games")).await;
+    assert_eq!(resp.status().as_u16(), 409);
+    let error: ErrorBody = test::read_body_json(resp).await;
+    assert_eq!(error.error, "conflict");
+    assert!(error.detail.contains(&TEST_GAME_A.id));
+
+    let resp = test::call_service(&app, add("/api/games?force=true")).await;
+    assert_eq!(resp.status().as_u16(), 201);
+    let game: Game = test::read_body_json(resp).await;
+    assert_ne!(game.id, TEST_GAME_A.id);
+    assert_eq!(game.name, TEST_GAME_A.name.to_uppercase());
+}
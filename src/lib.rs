@@ -1,8 +1,16 @@
+pub mod admin;
 pub mod app;
+pub mod config;
+pub mod error;
+pub mod events;
+pub mod features;
 pub mod games;
+pub mod logging;
+pub mod metrics;
 pub mod models;
 pub mod security;
 pub mod tags;
 #[cfg(test)]
 pub mod tests;
 pub mod users;
+pub mod validation;
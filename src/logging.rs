@@ -0,0 +1,127 @@
+use actix_web::{
+    body::{BodySize, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::header,
+};
+use futures::future::LocalBoxFuture;
+use std::{
+    future::{self, Ready},
+    time::Instant,
+};
+use tracing::{field, Instrument};
+
+/// Logs one structured event per request, inside a span that any events the
+/// handler logs (e.g. an upload's flatpak hash) are attached to. Requests for
+/// a game carry its id, so a game's whole history can be found by grepping for
+/// `game_id`.
+pub struct RequestLogging;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestLogging
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = RequestLoggingMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        future::ready(Ok(RequestLoggingMiddleware { service }))
+    }
+}
+
+pub struct RequestLoggingMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestLoggingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, actix_web::Error>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut core::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started = Instant::now();
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+        let client = req
+            .connection_info()
+            .realip_remote_addr()
+            .unwrap_or_default()
+            .to_string();
+        let user_agent = req
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|agent| agent.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let span = tracing::info_span!(
+            "request",
+            method = %method,
+            path = %path,
+            game_id = field::Empty,
+        );
+        let future = span.in_scope(|| self.service.call(req));
+        Box::pin(
+            async move {
+                let response = future.await?;
+                let request = response.request();
+                let game_id = request
+                    .match_pattern()
+                    .filter(|pattern| pattern.starts_with("/api/games/{id}"))
+                    .and_then(|_| request.match_info().get("id"))
+                    .unwrap_or_default()
+                    .to_string();
+                if !game_id.is_empty() {
+                    tracing::Span::current().record("game_id", game_id.as_str());
+                }
+                let content_length = match response.response().body().size() {
+                    BodySize::Sized(length) => length,
+                    BodySize::None | BodySize::Stream => 0,
+                };
+                tracing::info!(
+                    method = %method,
+                    path = %path,
+                    status = response.status().as_u16(),
+                    latency_ms = started.elapsed().as_millis() as u64,
+                    content_length,
+                    game_id = %game_id,
+                    client = %client,
+                    user_agent = %user_agent,
+                    "Request finished"
+                );
+                Ok(response)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+#[actix_web::test]
+async fn request_logging_passes_responses_through() {
+    use actix_web::{test, web, App, HttpResponse};
+
+    let app = test::init_service(App::new().wrap(RequestLogging).route(
+        "/api/games/{id}",
+        web::get().to(|| async { HttpResponse::ImATeapot().body("short and stout") }),
+    ))
+    .await;
+    let req = test::TestRequest::get().uri("/api/games/A").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 418);
+    assert_eq!(test::read_body(resp).await, "short and stout");
+}
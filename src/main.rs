@@ -1,31 +1,59 @@
-use actix_cors::Cors;
-use actix_web::{http, middleware::Logger, App, HttpServer};
+use actix_web::{App, HttpServer};
 
-use devcade_api_rs::app::{configure_app, get_app_data};
-
-use std::env;
+use devcade_api_rs::{
+    app::{configure_app, get_app_data, normalize_path, record_connection},
+    config, features,
+    games::{routes::upload_tmp_dir, signature::TRUSTED_KEYRING},
+    logging::RequestLogging,
+    security::{self, CORS_PERMISSIVE},
+};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenvy::dotenv().ok();
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+    let missing = config::missing_env_vars();
+    if !missing.is_empty() {
+        log::error!(
+            "Missing required environment variables: {}",
+            missing.join(", ")
+        );
+        std::process::exit(1);
+    }
+    let invalid = config::invalid_env_vars();
+    if !invalid.is_empty() {
+        log::error!("Invalid environment variables: {}", invalid.join(", "));
+        std::process::exit(1);
+    }
+    log::info!("Enabled features: {:?}", features::enabled_features());
+    if *CORS_PERMISSIVE {
+        log::warn!("CORS_PERMISSIVE is set, so any origin can call the API");
+    }
+    if !upload_tmp_dir().is_dir() {
+        log::error!(
+            "UPLOAD_TMP_DIR {} isn't a directory",
+            upload_tmp_dir().display()
+        );
+        std::process::exit(1);
+    }
+    match TRUSTED_KEYRING.as_deref() {
+        Some(keyring) => log::info!("Verifying flatpak signatures against {}", keyring.display()),
+        None => log::warn!("FLATPAK_TRUSTED_KEYRING is unset, so unsigned flatpaks are accepted"),
+    }
+    let shutdown_timeout = config::shutdown_timeout();
+    log::info!("Waiting up to {shutdown_timeout}s for requests in flight on shutdown");
     let app_data = get_app_data().await;
     HttpServer::new(move || {
-        let cors = Cors::default()
-            .allowed_origin(&env::var("DOMAIN").unwrap())
-            .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
-            .allowed_headers(vec![http::header::AUTHORIZATION, http::header::ACCEPT])
-            .allowed_header(http::header::CONTENT_TYPE)
-            .max_age(3600);
         App::new()
-            .wrap(cors)
-            .wrap(Logger::new(
-                "%a \"%r\" %s %b \"%{Referer}i\" \"%{User-Agent}i\" %T",
-            ))
+            .wrap(security::cors())
+            .wrap(normalize_path())
+            .wrap(RequestLogging)
             .configure(configure_app)
             .app_data(app_data.clone())
     })
+    .on_connect(record_connection)
     .bind(("0.0.0.0", 8080))?
+    .shutdown_timeout(shutdown_timeout)
     .run()
     .await
 }
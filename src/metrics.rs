@@ -0,0 +1,210 @@
+use crate::{error::ApiError, models::AppState};
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    web::Data,
+    HttpResponse, ResponseError,
+};
+use futures::future::LocalBoxFuture;
+use lazy_static::lazy_static;
+use prometheus::{
+    exponential_buckets, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge, Encoder, HistogramVec, IntCounter, IntCounterVec, IntGauge, TextEncoder,
+};
+use sqlx::query_scalar;
+use std::{
+    future::{self, Future, Ready},
+    time::{Duration, Instant},
+};
+
+lazy_static! {
+    static ref HTTP_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "devcade_http_requests_total",
+        "Requests handled, by route pattern, method and status",
+        &["route", "method", "status"]
+    )
+    .unwrap();
+    static ref HTTP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "devcade_http_request_duration_seconds",
+        "Time taken to respond to a request, by route pattern and method",
+        &["route", "method"],
+        // 1ms up to ~30s
+        exponential_buckets(0.001, 2.0, 16).unwrap()
+    )
+    .unwrap();
+    static ref S3_DURATION: HistogramVec = register_histogram_vec!(
+        "devcade_s3_duration_seconds",
+        "Time taken by S3 requests, by operation",
+        &["operation"],
+        // 5ms up to ~80s
+        exponential_buckets(0.005, 2.0, 15).unwrap()
+    )
+    .unwrap();
+    static ref GAME_COUNT: IntGauge =
+        register_int_gauge!("devcade_games", "Games currently in the database").unwrap();
+    static ref FLATPAK_REJECTIONS: IntCounter = register_int_counter!(
+        "devcade_flatpak_rejections_total",
+        "Flatpak uploads rejected as invalid"
+    )
+    .unwrap();
+    static ref UPLOAD_BYTES: HistogramVec = register_histogram_vec!(
+        "devcade_upload_bytes",
+        "Size of uploaded game assets",
+        &["asset"],
+        // 1 KiB up to 4 GiB
+        exponential_buckets(1024.0, 4.0, 12).unwrap()
+    )
+    .unwrap();
+    static ref UPLOAD_DURATION: HistogramVec = register_histogram_vec!(
+        "devcade_upload_duration_seconds",
+        "Time spent validating and storing an uploaded game asset",
+        &["asset"],
+        // 50ms up to ~100s
+        exponential_buckets(0.05, 2.0, 12).unwrap()
+    )
+    .unwrap();
+}
+
+/// Records a successful upload of a `game`, `banner` or `icon`
+pub fn observe_upload(asset: &str, bytes: usize, duration: Duration) {
+    UPLOAD_BYTES
+        .with_label_values(&[asset])
+        .observe(bytes as f64);
+    UPLOAD_DURATION
+        .with_label_values(&[asset])
+        .observe(duration.as_secs_f64());
+}
+
+/// Times an S3 request, e.g. `time_s3("get_object", request.send())`
+pub async fn time_s3<F: Future>(operation: &str, request: F) -> F::Output {
+    let started = Instant::now();
+    let output = request.await;
+    S3_DURATION
+        .with_label_values(&[operation])
+        .observe(started.elapsed().as_secs_f64());
+    output
+}
+
+/// Counts an uploaded flatpak that failed validation, so a spike of bad
+/// uploads can be alerted on
+pub fn record_flatpak_rejection() {
+    FLATPAK_REJECTIONS.inc();
+}
+
+/// The game count is read when scraped rather than tracked on every insert and
+/// delete, so it can't drift from the database
+pub async fn metrics(state: Data<AppState>) -> HttpResponse {
+    match query_scalar::<_, i64>("SELECT COUNT(*) FROM game WHERE deleted_at IS NULL")
+        .fetch_one(&state.db)
+        .await
+    {
+        Ok(count) => GAME_COUNT.set(count),
+        Err(e) => log::warn!("Failed to count games for metrics: {e}"),
+    }
+    let mut buffer = vec![];
+    let encoder = TextEncoder::new();
+    match encoder.encode(&prometheus::gather(), &mut buffer) {
+        Ok(()) => HttpResponse::Ok()
+            .content_type(encoder.format_type())
+            .body(buffer),
+        Err(e) => ApiError::internal(e).error_response(),
+    }
+}
+
+/// Counts and times every request by its route pattern rather than its path,
+/// so ids don't each get a series of their own
+pub struct RecordMetrics;
+
+impl<S, B> Transform<S, ServiceRequest> for RecordMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = RecordMetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        future::ready(Ok(RecordMetricsMiddleware { service }))
+    }
+}
+
+pub struct RecordMetricsMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RecordMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, actix_web::Error>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut core::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started = Instant::now();
+        let method = req.method().to_string();
+        let future = self.service.call(req);
+        Box::pin(async move {
+            let response = future.await?;
+            let route = response
+                .request()
+                .match_pattern()
+                .unwrap_or_else(|| "unmatched".to_string());
+            HTTP_REQUESTS
+                .with_label_values(&[&route, &method, response.status().as_str()])
+                .inc();
+            HTTP_REQUEST_DURATION
+                .with_label_values(&[&route, &method])
+                .observe(started.elapsed().as_secs_f64());
+            Ok(response)
+        })
+    }
+}
+
+#[test]
+fn observed_uploads_are_labelled_by_asset() {
+    observe_upload("banner", 4096, Duration::from_millis(120));
+    let families = prometheus::gather();
+    let bytes = families
+        .iter()
+        .find(|family| family.get_name() == "devcade_upload_bytes")
+        .unwrap();
+    let banner = bytes
+        .get_metric()
+        .iter()
+        .find(|metric| metric.get_label()[0].get_value() == "banner")
+        .unwrap();
+    assert!(banner.get_histogram().get_sample_count() >= 1);
+}
+
+#[actix_web::test]
+async fn requests_are_counted_by_route_pattern() {
+    use actix_web::{test, web, App};
+
+    let app = test::init_service(App::new().wrap(RecordMetrics).route(
+        "/api/games/{id}/hash",
+        web::get().to(HttpResponse::NoContent),
+    ))
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/api/games/A/hash")
+        .to_request();
+    test::call_service(&app, req).await;
+    let count = HTTP_REQUESTS
+        .with_label_values(&["/api/games/{id}/hash", "GET", "204"])
+        .get();
+    assert!(count >= 1);
+}
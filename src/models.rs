@@ -2,11 +2,17 @@ use aws_sdk_s3::Client;
 use serde::{Deserialize, Serialize};
 use sqlx::{
     postgres::{PgHasArrayType, PgTypeInfo},
-    types::chrono::NaiveDate,
+    types::chrono::{DateTime, NaiveDate, Utc},
     FromRow, Pool, Postgres,
 };
 use utoipa::{self, ToSchema};
 
+use crate::{
+    events::EventProducer,
+    games::{flatpak::FlatpakSummaryCache, sprite::IconSpriteCache},
+    security::RateLimiter,
+};
+
 #[derive(Serialize, Deserialize, FromRow, ToSchema, Clone, PartialEq)]
 pub struct Game {
     #[schema(example = "a1c6cef6-d987-4225-8bc4-def387e8b5bf")]
@@ -21,6 +27,28 @@ pub struct Game {
     pub hash: Option<String>,
     #[schema(example = "Break bricks, get points")]
     pub description: String,
+    /// How many times the game's flatpak has been downloaded
+    #[schema(example = 42)]
+    pub downloads: i64,
+    /// How many sessions of the game cabinets have reported starting
+    #[schema(example = 128)]
+    #[serde(default)]
+    pub play_count: i64,
+    /// When a session of the game last started, if ever
+    #[schema(example = "2023-04-02T21:15:00Z")]
+    #[serde(default)]
+    pub last_played_at: Option<DateTime<Utc>>,
+    /// When the game's details or any of its files last changed
+    #[schema(example = "2023-04-02T18:30:00Z")]
+    pub updated_at: DateTime<Utc>,
+    /// Drafts become published once their flatpak is uploaded, and only
+    /// published games are listed by default
+    #[schema(example = true)]
+    pub published: bool,
+    /// Size of the current flatpak in bytes, unknown until it's uploaded (or
+    /// backfilled for games uploaded before sizes were recorded)
+    #[schema(example = 356515840)]
+    pub size_bytes: Option<i64>,
 }
 
 #[derive(Serialize, Deserialize, FromRow, ToSchema, Clone, PartialEq, Debug)]
@@ -37,6 +65,28 @@ pub struct GameWithTags {
     pub hash: Option<String>,
     #[schema(example = "Huh")]
     pub description: String,
+    /// How many times the game's flatpak has been downloaded
+    #[schema(example = 42)]
+    pub downloads: i64,
+    /// How many sessions of the game cabinets have reported starting
+    #[schema(example = 128)]
+    #[serde(default)]
+    pub play_count: i64,
+    /// When a session of the game last started, if ever
+    #[schema(example = "2023-04-02T21:15:00Z")]
+    #[serde(default)]
+    pub last_played_at: Option<DateTime<Utc>>,
+    /// When the game's details or any of its files last changed
+    #[schema(example = "2023-04-02T18:30:00Z")]
+    pub updated_at: DateTime<Utc>,
+    /// Drafts become published once their flatpak is uploaded, and only
+    /// published games are listed by default
+    #[schema(example = true)]
+    pub published: bool,
+    /// Size of the current flatpak in bytes, unknown until it's uploaded (or
+    /// backfilled for games uploaded before sizes were recorded)
+    #[schema(example = 356515840)]
+    pub size_bytes: Option<i64>,
     #[schema(
         example = "[{\"name\": \"authrequired\", \"description\": \"Required CSH Authentication to Access\"}]"
     )]
@@ -53,6 +103,25 @@ pub struct GameWithTags {
     pub user: User,
 }
 
+/// Just what the menu needs to list a game
+#[derive(Serialize, Deserialize, FromRow, ToSchema, Clone, PartialEq, Debug)]
+pub struct GameSummary {
+    #[schema(example = "a1c6cef6-d987-4225-8bc4-def387e8b5bf")]
+    pub id: String,
+    #[schema(example = "BrickBreaker")]
+    pub name: String,
+    #[schema(example = "/api/games/a1c6cef6-d987-4225-8bc4-def387e8b5bf/icon")]
+    pub icon_url: String,
+    /// The author's full name, or their user id if they have no user
+    #[schema(example = "Joe Abbate")]
+    pub author_name: String,
+    #[schema(example = json!(["authrequired"]))]
+    pub tags: Vec<String>,
+    /// Size of the current flatpak in bytes, when known
+    #[schema(example = 356515840)]
+    pub size_bytes: Option<i64>,
+}
+
 impl GameWithTags {
     pub fn new(game: Game, tags: Vec<Tag>, user: User) -> GameWithTags {
         GameWithTags {
@@ -62,18 +131,69 @@ impl GameWithTags {
             name: game.name.clone(),
             hash: game.hash.clone(),
             description: game.description,
+            downloads: game.downloads,
+            play_count: game.play_count,
+            last_played_at: game.last_played_at,
+            updated_at: game.updated_at,
+            published: game.published,
+            size_bytes: game.size_bytes,
             tags,
             user,
         }
     }
 }
 
+/// One flatpak uploaded for a game, kept so a bad release can be rolled back
+#[derive(Serialize, Deserialize, FromRow, ToSchema, Clone, PartialEq, Debug)]
+pub struct GameVersion {
+    #[schema(example = 3)]
+    pub version: i32,
+    #[schema(example = "6485ae710b078ffe0b97083b4cd1719920ed0d5ae5e77d1fbf6a7e3b88c35c78")]
+    pub hash: String,
+    /// Architecture the flatpak was built for
+    #[schema(example = "x86_64")]
+    pub arch: String,
+    /// Branch the flatpak was built on
+    #[schema(example = "stable")]
+    pub branch: String,
+    #[schema(example = "2023-04-02T18:30:00Z")]
+    pub uploaded_at: DateTime<Utc>,
+}
+
 #[derive(sqlx::Type, Serialize, Deserialize, FromRow, ToSchema, Clone, PartialEq, Debug)]
 pub struct Tag {
     #[schema(example = "authrequired")]
     pub name: String,
     #[schema(example = "Required CSH Authentication to Access")]
     pub description: String,
+    /// Display color as `#rrggbb`
+    #[schema(example = "#b0197e")]
+    #[serde(default = "default_tag_color")]
+    pub color: String,
+}
+
+impl Tag {
+    /// Tags are told apart ignoring case and surrounding whitespace, matching
+    /// the unique index on `lower(name)`, so "Action" and " action" are one tag
+    pub fn normalize_name(name: &str) -> String {
+        name.trim().to_lowercase()
+    }
+}
+
+/// A tag along with how many games use it
+#[derive(Serialize, Deserialize, FromRow, ToSchema, Clone, PartialEq, Debug)]
+pub struct TagWithCount {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub tag: Tag,
+    #[schema(example = 4)]
+    pub game_count: i64,
+}
+
+pub const DEFAULT_TAG_COLOR: &str = "#b0197e";
+
+fn default_tag_color() -> String {
+    DEFAULT_TAG_COLOR.to_string()
 }
 
 impl PgHasArrayType for Tag {
@@ -124,4 +244,10 @@ impl User {
 pub struct AppState {
     pub db: Pool<Postgres>,
     pub s3: Client,
+    /// Bucket holding every game's flatpak, banner and icon
+    pub games_bucket: String,
+    pub events: EventProducer,
+    pub icon_sprite: IconSpriteCache,
+    pub flatpak_summaries: FlatpakSummaryCache,
+    pub rate_limiter: RateLimiter,
 }
@@ -1,18 +1,102 @@
+use crate::{config::env_or, error::ApiError, models::AppState};
+use actix_cors::Cors;
 use actix_web::{
     dev::{Service, ServiceRequest, ServiceResponse, Transform},
-    HttpResponse,
+    http::{
+        header::{self, HeaderName, X_FORWARDED_FOR},
+        Method,
+    },
+    web::Data,
+    HttpRequest, HttpResponse, ResponseError,
 };
+use data_encoding::BASE64;
 use futures::future::LocalBoxFuture;
 use lazy_static::lazy_static;
 use std::{
+    collections::HashMap,
     env,
     future::{self, Ready},
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
 };
 
 const API_KEY_NAME: &str = "frontend_api_key";
+const DEFAULT_READS_PER_MINUTE: u32 = 600;
+const DEFAULT_WRITES_PER_MINUTE: u32 = 30;
+/// Past this many tracked clients, buckets that have refilled completely are
+/// dropped, since a fresh bucket would behave the same
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+/// How long browsers may cache a preflight response
+const CORS_MAX_AGE_SECONDS: usize = 3600;
 
 lazy_static! {
     static ref API_KEY: String = env::var("FRONTEND_API_KEY").unwrap();
+    /// Docs are only protected when both `DOCS_USER` and `DOCS_PASS` are set
+    static ref DOCS_CREDENTIALS: Option<(String, String)> =
+        match (env::var("DOCS_USER"), env::var("DOCS_PASS")) {
+            (Ok(user), Ok(pass)) => Some((user, pass)),
+            _ => None,
+        };
+    /// Origins allowed to call the API from a browser, from a comma separated
+    /// `ALLOWED_ORIGINS` env var (e.g. `https://devcade.csh.rit.edu`)
+    static ref ALLOWED_ORIGINS: Vec<String> =
+        parse_origins(&env::var("ALLOWED_ORIGINS").unwrap_or_default());
+    /// Allows every origin, for local development against a frontend dev server
+    pub static ref CORS_PERMISSIVE: bool = env_or("CORS_PERMISSIVE", false);
+    /// Proxies whose `X-Forwarded-For` is believed, from a comma separated
+    /// `TRUSTED_PROXIES` env var of their addresses
+    static ref TRUSTED_PROXIES: Vec<IpAddr> =
+        parse_proxies(&env::var("TRUSTED_PROXIES").unwrap_or_default()).unwrap_or_default();
+}
+
+/// A comma separated list of IP addresses, or `None` if any isn't one
+pub fn parse_proxies(proxies: &str) -> Option<Vec<IpAddr>> {
+    proxies
+        .split(',')
+        .map(str::trim)
+        .filter(|proxy| !proxy.is_empty())
+        .map(|proxy| proxy.parse().ok())
+        .collect()
+}
+
+fn parse_origins(origins: &str) -> Vec<String> {
+    origins
+        .split(',')
+        .map(|origin| origin.trim().trim_end_matches('/').to_string())
+        .filter(|origin| !origin.is_empty())
+        .collect()
+}
+
+/// Cross-origin requests are refused unless their origin is listed in
+/// `ALLOWED_ORIGINS`, or `CORS_PERMISSIVE` is set
+pub fn cors() -> Cors {
+    if *CORS_PERMISSIVE {
+        return Cors::permissive();
+    }
+    cors_for(&ALLOWED_ORIGINS)
+}
+
+fn cors_for(origins: &[String]) -> Cors {
+    origins
+        .iter()
+        .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+        .allowed_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE"])
+        .allowed_headers(vec![
+            header::AUTHORIZATION,
+            header::ACCEPT,
+            header::CONTENT_TYPE,
+            header::IF_MATCH,
+            header::IF_NONE_MATCH,
+            header::IF_UNMODIFIED_SINCE,
+            HeaderName::from_static(API_KEY_NAME),
+        ])
+        .expose_headers(vec![
+            header::ETAG,
+            HeaderName::from_static("x-total-count"),
+            HeaderName::from_static("x-next-cursor"),
+        ])
+        .max_age(CORS_MAX_AGE_SECONDS)
 }
 
 pub struct RequireApiKey;
@@ -75,14 +159,20 @@ where
                 if self.log_only {
                     println!("Incorrect api api provided!!!")
                 } else {
-                    return response(req, HttpResponse::Unauthorized().body("incorrect api key"));
+                    return response(
+                        req,
+                        ApiError::Unauthorized("incorrect api key".to_string()).error_response(),
+                    );
                 }
             }
             None => {
                 if self.log_only {
                     println!("Missing api key!!!")
                 } else {
-                    return response(req, HttpResponse::Unauthorized().body("missing api key"));
+                    return response(
+                        req,
+                        ApiError::Unauthorized("missing api key".to_string()).error_response(),
+                    );
                 }
             }
             _ => (), // just passthrough
@@ -101,3 +191,390 @@ where
         })
     }
 }
+
+/// Whether a request carries the API key, for routes that are public but
+/// reveal more to the frontend
+pub fn has_api_key(req: &HttpRequest) -> bool {
+    env::var("DISABLE_AUTHENTICATION").is_ok()
+        || req
+            .headers()
+            .get(API_KEY_NAME)
+            .is_some_and(|key| key == API_KEY.as_str())
+}
+
+/// Read and write requests are limited separately, so browsing can't use up
+/// the budget for uploads and vice versa
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RequestKind {
+    Read,
+    Write,
+}
+
+impl RequestKind {
+    fn of(method: &Method) -> Self {
+        match *method {
+            Method::GET | Method::HEAD | Method::OPTIONS => Self::Read,
+            _ => Self::Write,
+        }
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Per-client token buckets, refilled continuously at each kind's per-minute
+/// rate and holding at most a minute's worth of requests
+pub struct RateLimiter {
+    reads_per_minute: u32,
+    writes_per_minute: u32,
+    buckets: Mutex<HashMap<(String, RequestKind), TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(reads_per_minute: u32, writes_per_minute: u32) -> Self {
+        RateLimiter {
+            reads_per_minute,
+            writes_per_minute,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Limits come from `RATE_LIMIT_READS_PER_MINUTE` and
+    /// `RATE_LIMIT_WRITES_PER_MINUTE`, per client
+    pub fn from_env() -> Self {
+        Self::new(
+            env_or("RATE_LIMIT_READS_PER_MINUTE", DEFAULT_READS_PER_MINUTE),
+            env_or("RATE_LIMIT_WRITES_PER_MINUTE", DEFAULT_WRITES_PER_MINUTE),
+        )
+    }
+
+    fn capacity(&self, kind: RequestKind) -> f64 {
+        f64::from(match kind {
+            RequestKind::Read => self.reads_per_minute,
+            RequestKind::Write => self.writes_per_minute,
+        })
+    }
+
+    /// Spends one of the client's tokens, or says how long until they'll have
+    /// one again
+    fn take(&self, client: &str, kind: RequestKind, now: Instant) -> Result<(), Duration> {
+        let capacity = self.capacity(kind);
+        let per_second = capacity / 60.0;
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|(_, kind), bucket| {
+                let capacity = self.capacity(*kind);
+                let refilled = now.duration_since(bucket.updated).as_secs_f64() * capacity / 60.0;
+                bucket.tokens + refilled < capacity
+            });
+        }
+        let bucket = buckets
+            .entry((client.to_string(), kind))
+            .or_insert(TokenBucket {
+                tokens: capacity,
+                updated: now,
+            });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_second).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        if per_second == 0.0 {
+            return Err(Duration::from_secs(60));
+        }
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+    }
+}
+
+/// Rate limits each client, identified by the API key they send or failing
+/// that their address
+pub struct RateLimit;
+
+/// Only the real API key gets a bucket of its own. Any other value is a
+/// stranger's to choose, so counting it separately would let them dodge the
+/// limit, and fill the limiter with buckets, by sending a new one each time.
+/// The same goes for addresses, hence [`client_addr`].
+fn rate_limit_client(req: &HttpRequest) -> String {
+    match req.headers().get(API_KEY_NAME) {
+        Some(key) if key == API_KEY.as_str() => "key".to_string(),
+        _ => match client_addr(req, &TRUSTED_PROXIES) {
+            Some(addr) => format!("ip:{addr}"),
+            None => "ip:".to_string(),
+        },
+    }
+}
+
+/// The address a request came from. Proxies append the address they were
+/// sent the request by to `X-Forwarded-For`, while the entries before theirs
+/// are whatever the client sent, so the client is the last entry that wasn't
+/// added by one of the `trusted` proxies.
+fn client_addr(req: &HttpRequest, trusted: &[IpAddr]) -> Option<IpAddr> {
+    let mut addr = req.peer_addr()?.ip();
+    let forwarded: Vec<&str> = req
+        .headers()
+        .get_all(X_FORWARDED_FOR)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+    for hop in forwarded.into_iter().rev() {
+        if !trusted.contains(&addr) {
+            break;
+        }
+        match hop.trim().parse() {
+            Ok(hop) => addr = hop,
+            Err(_) => break,
+        }
+    }
+    Some(addr)
+}
+
+impl<S> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<
+        ServiceRequest,
+        Response = ServiceResponse<actix_web::body::BoxBody>,
+        Error = actix_web::Error,
+    >,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<actix_web::body::BoxBody>;
+    type Error = actix_web::Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        future::ready(Ok(RateLimitMiddleware { service }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: S,
+}
+
+impl<S> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<
+        ServiceRequest,
+        Response = ServiceResponse<actix_web::body::BoxBody>,
+        Error = actix_web::Error,
+    >,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<actix_web::body::BoxBody>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, actix_web::Error>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut core::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if let Some(state) = req.app_data::<Data<AppState>>() {
+            let client = rate_limit_client(req.request());
+            let kind = RequestKind::of(req.method());
+            if let Err(retry_after) = state.rate_limiter.take(&client, kind, Instant::now()) {
+                let response = ApiError::TooManyRequests(retry_after).error_response();
+                return Box::pin(async { Ok(req.into_response(response)) });
+            }
+        }
+        let future = self.service.call(req);
+        Box::pin(future)
+    }
+}
+
+/// Extracts the user and password from an `Authorization: Basic` header value
+fn basic_credentials(authorization: &str) -> Option<(String, String)> {
+    let encoded = authorization.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(BASE64.decode(encoded.trim().as_bytes()).ok()?).ok()?;
+    let (user, pass) = decoded.split_once(':')?;
+    Some((user.to_string(), pass.to_string()))
+}
+
+/// HTTP Basic auth for the OpenAPI docs, enabled by `DOCS_USER`/`DOCS_PASS`
+pub struct RequireDocsAuth;
+
+impl<S> Transform<S, ServiceRequest> for RequireDocsAuth
+where
+    S: Service<
+        ServiceRequest,
+        Response = ServiceResponse<actix_web::body::BoxBody>,
+        Error = actix_web::Error,
+    >,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<actix_web::body::BoxBody>;
+    type Error = actix_web::Error;
+    type Transform = DocsAuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        future::ready(Ok(DocsAuthMiddleware { service }))
+    }
+}
+
+pub struct DocsAuthMiddleware<S> {
+    service: S,
+}
+
+impl<S> Service<ServiceRequest> for DocsAuthMiddleware<S>
+where
+    S: Service<
+        ServiceRequest,
+        Response = ServiceResponse<actix_web::body::BoxBody>,
+        Error = actix_web::Error,
+    >,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<actix_web::body::BoxBody>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, actix_web::Error>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut core::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if let Some(expected) = DOCS_CREDENTIALS.as_ref() {
+            let provided = req
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(basic_credentials);
+            if provided.as_ref() != Some(expected) {
+                let response = HttpResponse::Unauthorized()
+                    .insert_header((header::WWW_AUTHENTICATE, "Basic realm=\"Devcade API docs\""))
+                    .body("docs require authentication");
+                return Box::pin(async { Ok(req.into_response(response)) });
+            }
+        }
+        let future = self.service.call(req);
+        Box::pin(future)
+    }
+}
+
+#[test]
+fn parse_origins_ignores_whitespace_and_trailing_slashes() {
+    let origins = parse_origins(" https://devcade.csh.rit.edu/, http://localhost:3000,,");
+    assert_eq!(
+        origins,
+        vec!["https://devcade.csh.rit.edu", "http://localhost:3000"]
+    );
+    assert!(parse_origins("").is_empty());
+}
+
+#[test]
+fn basic_credentials_decodes_user_and_password() {
+    assert_eq!(
+        basic_credentials("Basic ZGV2Y2FkZTpodW50ZXI6Mg=="),
+        Some(("devcade".to_string(), "hunter:2".to_string()))
+    );
+    assert_eq!(basic_credentials("Bearer ZGV2Y2FkZTpodW50ZXI="), None);
+    assert_eq!(basic_credentials("Basic not-base64"), None);
+}
+
+#[test]
+fn rate_limiter_refills_over_time() {
+    let limiter = RateLimiter::new(120, 2);
+    let start = Instant::now();
+    assert!(limiter.take("a", RequestKind::Write, start).is_ok());
+    assert!(limiter.take("a", RequestKind::Write, start).is_ok());
+    let retry_after = limiter.take("a", RequestKind::Write, start).unwrap_err();
+    assert_eq!(retry_after.as_secs(), 30);
+    // Other clients and reads have buckets of their own
+    assert!(limiter.take("b", RequestKind::Write, start).is_ok());
+    assert!(limiter.take("a", RequestKind::Read, start).is_ok());
+    let later = start + Duration::from_secs(30);
+    assert!(limiter.take("a", RequestKind::Write, later).is_ok());
+    assert!(limiter.take("a", RequestKind::Write, later).is_err());
+}
+
+#[test]
+fn rate_limit_client_ignores_unknown_keys() {
+    use actix_web::test::TestRequest;
+    let peer = "10.0.0.1:4000".parse().unwrap();
+    let with_key = |key: &str| {
+        TestRequest::default()
+            .peer_addr(peer)
+            .insert_header((API_KEY_NAME, key))
+            .to_http_request()
+    };
+    assert_eq!(rate_limit_client(&with_key(&API_KEY)), "key");
+    assert_eq!(rate_limit_client(&with_key("made-up")), "ip:10.0.0.1");
+    assert_eq!(rate_limit_client(&with_key("another")), "ip:10.0.0.1");
+    let without_key = TestRequest::default().peer_addr(peer).to_http_request();
+    assert_eq!(rate_limit_client(&without_key), "ip:10.0.0.1");
+}
+
+#[test]
+fn client_addr_only_believes_trusted_proxies() {
+    use actix_web::test::TestRequest;
+    let proxy: IpAddr = "10.0.0.2".parse().unwrap();
+    let request = |peer: &str, forwarded: &str| {
+        TestRequest::default()
+            .peer_addr(format!("{peer}:4000").parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, forwarded))
+            .to_http_request()
+    };
+    let addr = |req| client_addr(&req, &[proxy]).unwrap().to_string();
+    assert_eq!(addr(request("10.0.0.9", "1.2.3.4")), "10.0.0.9");
+    assert_eq!(addr(request("10.0.0.2", "1.2.3.4")), "1.2.3.4");
+    // Entries before the proxy's own are the client's to make up
+    assert_eq!(addr(request("10.0.0.2", "6.6.6.6, 1.2.3.4")), "1.2.3.4");
+    assert_eq!(addr(request("10.0.0.2", "1.2.3.4, 10.0.0.2")), "1.2.3.4");
+    assert_eq!(addr(request("10.0.0.2", "garbage")), "10.0.0.2");
+}
+
+#[actix_web::test]
+async fn rotating_forwarded_for_is_still_limited() {
+    use crate::app::{configure_app, get_app_data};
+    use actix_web::{test, App};
+    let app_data = get_app_data().await;
+    let app = test::init_service(App::new().configure(configure_app).app_data(app_data)).await;
+    let mut statuses = Vec::new();
+    for n in 0..=DEFAULT_WRITES_PER_MINUTE {
+        let req = test::TestRequest::post()
+            .uri("/api/games/00000000-0000-0000-0000-000000000000/played")
+            .peer_addr("10.0.0.9:4000".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, format!("192.0.2.{n}")))
+            .to_request();
+        statuses.push(test::call_service(&app, req).await.status().as_u16());
+    }
+    assert_eq!(statuses.last(), Some(&429));
+}
+
+#[actix_web::test]
+async fn cors_allows_patch_from_allowed_origins() {
+    use actix_web::{test, web, App};
+    let app = test::init_service(
+        App::new()
+            .wrap(cors_for(&["https://devcade.csh.rit.edu".to_string()]))
+            .route("/", web::patch().to(HttpResponse::Ok)),
+    )
+    .await;
+    let req = test::TestRequest::default()
+        .method(Method::OPTIONS)
+        .insert_header((header::ORIGIN, "https://devcade.csh.rit.edu"))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "PATCH"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let allowed = resp
+        .headers()
+        .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(allowed.contains("PATCH"), "{allowed}");
+}
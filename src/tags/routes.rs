@@ -1,30 +1,148 @@
 use crate::{
-    models::{AppState, Game, Tag},
+    error::ApiError,
+    models::{AppState, Game, Tag, TagWithCount},
     security::RequireApiKey,
+    validation::ValidationErrors,
 };
 use actix_web::{
     delete, get, post, put,
     web::{Data, Json, Path},
-    HttpResponse, Responder,
+    HttpResponse,
 };
-use sqlx::{query, query_as};
+use serde::{Deserialize, Serialize};
+use sqlx::{query, query_as, query_scalar, FromRow};
+use std::collections::HashSet;
+use utoipa::ToSchema;
+
+const MAX_TAG_NAME_LENGTH: usize = 32;
+/// Finds a tag whatever case or padding its name is given in
+const TAG_BY_NAME: &str = "SELECT * FROM tags WHERE lower(name) = $1";
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct BulkTagResult {
+    pub created: u64,
+    pub updated: u64,
+    pub unchanged: u64,
+}
+
+/// How many listed games use a tag, for browsing tags by popularity
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema, PartialEq)]
+pub struct TagStats {
+    #[schema(example = "authrequired")]
+    pub name: String,
+    #[schema(example = 4)]
+    pub game_count: i64,
+    /// The most recently uploaded game with this tag, if any
+    #[schema(example = "AAAAAAAA-AAAA-AAAA-AAAA-AAAAAAAAAAAA")]
+    pub latest_game: Option<String>,
+}
+
+fn is_hex_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Another tag already has this name, ignoring case
+fn is_duplicate_name(error: &sqlx::Error) -> bool {
+    matches!(error, sqlx::Error::Database(e) if e.code().as_deref() == Some("23505"))
+}
+
+fn tag_error(error: sqlx::Error, name: &str) -> ApiError {
+    if is_duplicate_name(&error) {
+        ApiError::Conflict(format!("Tag {name} already exists"))
+    } else {
+        error.into()
+    }
+}
+
+fn tag_lookup_error(error: sqlx::Error) -> ApiError {
+    match error {
+        sqlx::Error::RowNotFound => ApiError::BadRequest("Tag Does Not Exist".to_string()),
+        error => error.into(),
+    }
+}
+
+/// Looks a tag up by name, whatever its case or padding
+async fn find_tag(state: &AppState, name: &str) -> Result<Tag, ApiError> {
+    query_as(TAG_BY_NAME)
+        .bind(Tag::normalize_name(name))
+        .fetch_one(&state.db)
+        .await
+        .map_err(tag_lookup_error)
+}
+
+fn validate_bulk_tags(tags: &[Tag]) -> ValidationErrors {
+    let mut errors = ValidationErrors::default();
+    let mut seen = HashSet::new();
+    for (index, tag) in tags.iter().enumerate() {
+        let length = tag.name.trim().chars().count();
+        if length == 0 || length > MAX_TAG_NAME_LENGTH {
+            errors.add(
+                &format!("[{index}].name"),
+                format!("Tag names must be between 1 and {MAX_TAG_NAME_LENGTH} characters"),
+            );
+        } else if !seen.insert(Tag::normalize_name(&tag.name)) {
+            errors.add(
+                &format!("[{index}].name"),
+                format!("Tag {} appears more than once", tag.name),
+            );
+        }
+        if !is_hex_color(&tag.color) {
+            errors.add(
+                &format!("[{index}].color"),
+                "Color must be formatted as #rrggbb",
+            );
+        }
+    }
+    errors
+}
 
 #[utoipa::path(
     context_path = "/tags",
     responses(
-        (status = 200, description = "List all tags", body = [Tag]),
-        (status = 500, description = "Error Created by Query"),
+        (status = 200, description = "List all tags with how many games use each", body = [TagWithCount]),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     )
 )]
-#[get("/")]
-pub async fn get_all_tags(state: Data<AppState>) -> impl Responder {
-    match query_as::<_, Tag>("SELECT * FROM tags ORDER BY name ASC")
-        .fetch_all(&state.db)
-        .await
-    {
-        Ok(tags) => HttpResponse::Ok().json(tags),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-    }
+#[get("")]
+pub async fn get_all_tags(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let tags: Vec<TagWithCount> = query_as(
+        "
+        SELECT tags.*, COUNT(game_tags.game_id) AS game_count
+        FROM tags
+        LEFT JOIN game_tags ON game_tags.tag_name = tags.name
+        GROUP BY tags.name ORDER BY tags.name ASC
+        ",
+    )
+    .fetch_all(&state.db)
+    .await?;
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+#[utoipa::path(
+    context_path = "/tags",
+    responses(
+        (status = 200, description = "Every tag with how many listed games use it, most used first", body = [TagStats]),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    )
+)]
+#[get("/stats")]
+pub async fn get_tag_stats(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+    // Drafts and deleted games aren't listed, so they don't count either
+    let stats: Vec<TagStats> = query_as(
+        "
+        SELECT tags.name, COUNT(game.id) AS game_count,
+            (array_agg(game.id ORDER BY game.upload_date DESC, game.updated_at DESC)
+                FILTER (WHERE game.id IS NOT NULL))[1] AS latest_game
+        FROM tags
+        LEFT JOIN game_tags ON game_tags.tag_name = tags.name
+        LEFT JOIN game ON game.id = game_tags.game_id
+            AND game.published AND game.deleted_at IS NULL
+        GROUP BY tags.name ORDER BY game_count DESC, tags.name ASC
+        ",
+    )
+    .fetch_all(&state.db)
+    .await?;
+    Ok(HttpResponse::Ok().json(stats))
 }
 
 #[utoipa::path(
@@ -32,56 +150,103 @@ pub async fn get_all_tags(state: Data<AppState>) -> impl Responder {
     request_body(content=Tag, content_type="application/json", description="Tag Information"),
     responses(
         (status = 201, description = "Created new tag"),
-        (status = 401, description = "Invalid/Missing API Key"),
-        (status = 500, description = "Error Created by Query"),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 409, description = "A tag with this name already exists, ignoring case", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     ),
     security(
         ("api_key" = [])
     )
 )]
-#[post("/", wrap = "RequireApiKey")]
-pub async fn add_tag(state: Data<AppState>, tag: Json<Tag>) -> impl Responder {
-    match query("INSERT INTO tags VALUES ($1, $2)")
+#[post("", wrap = "RequireApiKey")]
+pub async fn add_tag(state: Data<AppState>, tag: Json<Tag>) -> Result<HttpResponse, ApiError> {
+    let mut tag = tag.into_inner();
+    tag.name = tag.name.trim().to_string();
+    query("INSERT INTO tags VALUES ($1, $2, $3)")
         .bind(&tag.name)
         .bind(&tag.description)
+        .bind(&tag.color)
         .execute(&state.db)
         .await
-    {
-        Ok(_) => HttpResponse::Created().json(Tag {
-            name: tag.name.clone(),
-            description: tag.description.clone(),
-        }),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        .map_err(|e| tag_error(e, &tag.name))?;
+    Ok(HttpResponse::Created().json(tag))
+}
+
+#[utoipa::path(
+    context_path = "/tags",
+    request_body(content=[Tag], content_type="application/json", description="Canonical tag set"),
+    responses(
+        (status = 200, description = "Upserted every tag", body = BulkTagResult),
+        (status = 400, description = "Invalid tags", body = ValidationErrors),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[post("/bulk", wrap = "RequireApiKey")]
+pub async fn bulk_upsert_tags(
+    state: Data<AppState>,
+    tags: Json<Vec<Tag>>,
+) -> Result<HttpResponse, ApiError> {
+    let errors = validate_bulk_tags(&tags);
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+    // Dropping the transaction on an error rolls it back
+    let mut transaction = state.db.begin().await?;
+    let mut result = BulkTagResult::default();
+    for tag in tags.iter() {
+        // Existing tags are only rewritten when something changed, keeping
+        // their spelling, and xmax = 0 only holds for freshly inserted rows
+        match query_scalar::<_, bool>(
+            "INSERT INTO tags VALUES ($1, $2, $3)
+            ON CONFLICT ((lower(name))) DO UPDATE
+                SET description = EXCLUDED.description, color = EXCLUDED.color
+                WHERE tags.description IS DISTINCT FROM EXCLUDED.description
+                    OR tags.color <> EXCLUDED.color
+            RETURNING xmax = 0",
+        )
+        .bind(tag.name.trim())
+        .bind(&tag.description)
+        .bind(&tag.color)
+        .fetch_optional(&mut transaction)
+        .await?
+        {
+            Some(true) => result.created += 1,
+            Some(false) => result.updated += 1,
+            None => result.unchanged += 1,
+        }
     }
+    transaction.commit().await?;
+    Ok(HttpResponse::Ok().json(result))
 }
 
 #[utoipa::path(
     context_path = "/tags",
     responses(
         (status = 200, description = "Get specified tag", body = Tag),
-        (status = 500, description = "Error Created by Query"),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     )
 )]
 #[get("/{tag}")]
-pub async fn get_tag(state: Data<AppState>, path: Path<(String,)>) -> impl Responder {
+pub async fn get_tag(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
     let (name,) = path.into_inner();
-    match query_as::<_, Tag>("SELECT * FROM tags WHERE name = $1")
-        .bind(name)
-        .fetch_one(&state.db)
-        .await
-    {
-        Ok(tags) => HttpResponse::Ok().json(tags),
-        Err(e) => HttpResponse::BadRequest().body(e.to_string()),
-    }
+    let tag = find_tag(&state, &name).await?;
+    Ok(HttpResponse::Ok().json(tag))
 }
 
 #[utoipa::path(
     context_path = "/tags",
     responses(
-        (status = 200, description = "Delete tag"),
-        (status = 400, description = "Missing tag"),
-        (status = 401, description = "Invalid/Missing API Key"),
-        (status = 500, description = "Error Created by Deletion"),
+        (status = 200, description = "Delete tag, removing it from every game"),
+        (status = 400, description = "Missing tag", body = ErrorBody),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Deletion", body = ErrorBody),
     ),
     params(
         ("tag", description = "Tag to delete")
@@ -91,24 +256,17 @@ pub async fn get_tag(state: Data<AppState>, path: Path<(String,)>) -> impl Respo
     )
 )]
 #[delete("/{tag}", wrap = "RequireApiKey")]
-pub async fn delete_tag(state: Data<AppState>, path: Path<(String,)>) -> impl Responder {
+pub async fn delete_tag(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
     let (name,) = path.into_inner();
-    if query_as::<_, Tag>("SELECT * FROM tags WHERE name = $1")
-        .bind(&name)
-        .fetch_one(&state.db)
-        .await
-        .is_err()
-    {
-        return HttpResponse::BadRequest().body("Tag Does Not Exist");
-    }
-    match query("DELETE FROM tags WHERE name = $1")
+    let name = find_tag(&state, &name).await?.name;
+    query("DELETE FROM tags WHERE name = $1")
         .bind(&name)
         .execute(&state.db)
-        .await
-    {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-    }
+        .await?;
+    Ok(HttpResponse::Ok().finish())
 }
 
 #[utoipa::path(
@@ -116,8 +274,9 @@ pub async fn delete_tag(state: Data<AppState>, path: Path<(String,)>) -> impl Re
     request_body(content=Tag, content_type="application/json", description="Tag Information"),
     responses(
         (status = 201, description = "Updated tag"),
-        (status = 401, description = "Invalid/Missing API Key"),
-        (status = 500, description = "Error Created by Query"),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 409, description = "Another tag already has the new name, ignoring case", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     ),
     security(
         ("api_key" = [])
@@ -128,55 +287,39 @@ pub async fn edit_tag(
     state: Data<AppState>,
     path: Path<(String,)>,
     tag: Json<Tag>,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let (name,) = path.into_inner();
-    if query_as::<_, Tag>("SELECT * FROM tags WHERE name = $1")
-        .bind(&name)
-        .fetch_one(&state.db)
-        .await
-        .is_err()
-    {
-        return HttpResponse::BadRequest().body("Tag Does Not Exist");
-    }
-    match query("UPDATE tags SET name = $1, description = $2 WHERE name = $3")
+    let name = find_tag(&state, &name).await?.name;
+    let mut tag = tag.into_inner();
+    tag.name = tag.name.trim().to_string();
+    query("UPDATE tags SET name = $1, description = $2, color = $3 WHERE name = $4")
         .bind(&tag.name)
         .bind(&tag.description)
+        .bind(&tag.color)
         .bind(&name)
         .execute(&state.db)
         .await
-    {
-        Ok(_) => HttpResponse::Created().json(Tag {
-            name: tag.name.clone(),
-            description: tag.description.clone(),
-        }),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-    }
+        .map_err(|e| tag_error(e, &tag.name))?;
+    Ok(HttpResponse::Created().json(tag))
 }
 
 #[utoipa::path(
     context_path = "/tags",
     responses(
         (status = 200, description = "Get games with tag", body = [Game]),
-        (status = 500, description = "Error Created by Query"),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     )
 )]
 #[get("/{tag}/games")]
-pub async fn get_tag_games(state: Data<AppState>, path: Path<(String,)>) -> impl Responder {
+pub async fn get_tag_games(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
     let (name,) = path.into_inner();
-    if query_as::<_, Tag>("SELECT * FROM tags WHERE name = $1")
-        .bind(&name)
-        .fetch_one(&state.db)
-        .await
-        .is_err()
-    {
-        return HttpResponse::BadRequest().body("Tag Does Not Exist");
-    }
-    match query_as::<_, Game>("SELECT game.* FROM game LEFT JOIN game_tags ON game_tags.game_id = game.id LEFT JOIN tags ON tags.name = game_tags.tag_name WHERE game_tags.tag_name = $1 GROUP BY game.id ORDER BY name ASC")
+    let name = find_tag(&state, &name).await?.name;
+    let games: Vec<Game> = query_as("SELECT game.* FROM game LEFT JOIN game_tags ON game_tags.game_id = game.id LEFT JOIN tags ON tags.name = game_tags.tag_name WHERE game_tags.tag_name = $1 AND game.published AND game.deleted_at IS NULL GROUP BY game.id ORDER BY name ASC")
         .bind(name)
         .fetch_all(&state.db)
-        .await
-    {
-        Ok(games) => HttpResponse::Ok().json(games),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-    }
+        .await?;
+    Ok(HttpResponse::Ok().json(games))
 }
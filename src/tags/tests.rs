@@ -1,24 +1,47 @@
-use crate::models::Tag;
 #[cfg(test)]
-use crate::tests::{get_test_server, TEST_TAG_1, TEST_TAG_2, TEST_TAG_3, TEST_TAG_4, TEST_TAG_6};
+use crate::tests::{
+    get_test_server, TEST_TAG_1, TEST_TAG_2, TEST_TAG_3, TEST_TAG_4, TEST_TAG_5, TEST_TAG_6,
+    TEST_TAG_7,
+};
+use crate::{
+    models::{Tag, TagWithCount},
+    tags::routes::{BulkTagResult, TagStats},
+};
 
 #[actix_web::test]
 async fn test_get_all_tags() {
     let srv = get_test_server().await;
-    let req = srv.get("/tags/");
+    let req = srv.get("/api/tags/");
     let mut res = req.send().await.unwrap();
-    println!(
-        "{} | {}",
-        res.status(),
-        std::str::from_utf8(&res.body().await.unwrap()).unwrap_or("Unreadable")
-    );
     assert!(res.status().is_success());
+    let tags: Vec<TagWithCount> = res.json().await.unwrap();
+    let tag_1 = tags.iter().find(|t| t.tag.name == TEST_TAG_1.name).unwrap();
+    assert!(tag_1.game_count >= 1);
+    let tag_7 = tags.iter().find(|t| t.tag.name == TEST_TAG_7.name).unwrap();
+    assert_eq!(tag_7.game_count, 0);
+}
+
+#[actix_web::test]
+async fn test_get_tag_stats() {
+    let srv = get_test_server().await;
+    let mut res = srv.get("/api/tags/stats").send().await.unwrap();
+    assert!(res.status().is_success());
+    let stats: Vec<TagStats> = res.json().await.unwrap();
+    assert!(stats
+        .windows(2)
+        .all(|pair| pair[0].game_count >= pair[1].game_count));
+    let tag_1 = stats.iter().find(|t| t.name == TEST_TAG_1.name).unwrap();
+    assert!(tag_1.game_count >= 1);
+    assert!(tag_1.latest_game.is_some());
+    let tag_7 = stats.iter().find(|t| t.name == TEST_TAG_7.name).unwrap();
+    assert_eq!(tag_7.game_count, 0);
+    assert_eq!(tag_7.latest_game, None);
 }
 
 #[actix_web::test]
 async fn test_get_tag() {
     let srv = get_test_server().await;
-    let req = srv.get(format!("/tags/{}", TEST_TAG_1.name));
+    let req = srv.get(format!("/api/tags/{}", TEST_TAG_1.name));
     let mut res = req.send().await.unwrap();
     println!(
         "{} | {}",
@@ -35,7 +58,7 @@ async fn test_edit_tag() {
     edited_tag.name = "NEWNAME2".to_string();
     edited_tag.description = "I changed the description!".to_string();
     let req = srv
-        .put(format!("/tags/{}", TEST_TAG_2.name))
+        .put(format!("/api/tags/{}", TEST_TAG_2.name))
         .insert_header(("frontend_api_key", "TESTING"));
     let mut res = req.send_json(&edited_tag).await.unwrap();
     println!(
@@ -52,7 +75,7 @@ async fn test_edit_tag_unauthorized() {
     let mut edited_tag = TEST_TAG_2.clone();
     edited_tag.name = "NEWNAME2".to_string();
     edited_tag.description = "I changed the description!".to_string();
-    let req = srv.put(format!("/tags/{}", TEST_TAG_2.name));
+    let req = srv.put(format!("/api/tags/{}", TEST_TAG_2.name));
     let mut res = req.send_json(&edited_tag).await.unwrap();
     println!(
         "{} | {}",
@@ -66,7 +89,7 @@ async fn test_edit_tag_unauthorized() {
 async fn test_delete_tag() {
     let srv = get_test_server().await;
     let req = srv
-        .delete(format!("/tags/{}", TEST_TAG_3.name))
+        .delete(format!("/api/tags/{}", TEST_TAG_3.name))
         .insert_header(("frontend_api_key", "TESTING"));
     let mut res = req.send().await.unwrap();
     println!(
@@ -80,7 +103,7 @@ async fn test_delete_tag() {
 #[actix_web::test]
 async fn test_delete_tag_unauthorized() {
     let srv = get_test_server().await;
-    let req = srv.delete(format!("/tags/{}", TEST_TAG_3.name));
+    let req = srv.delete(format!("/api/tags/{}", TEST_TAG_3.name));
     let mut res = req.send().await.unwrap();
     println!(
         "{} | {}",
@@ -94,11 +117,12 @@ async fn test_delete_tag_unauthorized() {
 async fn test_add_tag() {
     let srv = get_test_server().await;
     let req = srv
-        .post("/tags/")
+        .post("/api/tags/")
         .insert_header(("frontend_api_key", "TESTING"));
     let new_tag = Tag {
         name: "NEW_TAG".to_string(),
         description: "THIS IS A NEW TAG".to_string(),
+        color: "#b0197e".to_string(),
     };
     let mut res = req.send_json(&new_tag).await.unwrap();
     println!(
@@ -112,7 +136,7 @@ async fn test_add_tag() {
 #[actix_web::test]
 async fn test_add_tag_unauthorized() {
     let srv = get_test_server().await;
-    let req = srv.post("/tags/");
+    let req = srv.post("/api/tags/");
     let res = req.send_json(&TEST_TAG_6.clone()).await.unwrap();
     assert_eq!(res.status().as_u16(), 401);
 }
@@ -120,7 +144,7 @@ async fn test_add_tag_unauthorized() {
 #[actix_web::test]
 async fn test_get_tag_games_none() {
     let srv = get_test_server().await;
-    let req = srv.get(format!("/tags/{}/games", TEST_TAG_4.name));
+    let req = srv.get(format!("/api/tags/{}/games", TEST_TAG_4.name));
     let mut res = req.send().await.unwrap();
     println!(
         "{} | {}",
@@ -133,7 +157,7 @@ async fn test_get_tag_games_none() {
 #[actix_web::test]
 async fn test_get_tag_games_some() {
     let srv = get_test_server().await;
-    let req = srv.get(format!("/tags/{}/games", TEST_TAG_1.name));
+    let req = srv.get(format!("/api/tags/{}/games", TEST_TAG_1.name));
     let mut res = req.send().await.unwrap();
     println!(
         "{} | {}",
@@ -142,3 +166,65 @@ async fn test_get_tag_games_some() {
     );
     assert!(res.status().is_success());
 }
+
+#[actix_web::test]
+async fn test_bulk_upsert_tags() {
+    let srv = get_test_server().await;
+    let mut recolored = TEST_TAG_5.clone();
+    recolored.color = "#e11c52".to_string();
+    let new_tag = Tag {
+        name: "BULK_TAG".to_string(),
+        description: "Added in bulk".to_string(),
+        color: "#3b7fc4".to_string(),
+    };
+    let req = srv
+        .post("/api/tags/bulk")
+        .insert_header(("frontend_api_key", "TESTING"));
+    let mut res = req
+        .send_json(&vec![recolored, TEST_TAG_1.clone(), new_tag])
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let result: BulkTagResult = res.json().await.unwrap();
+    assert_eq!(
+        result,
+        BulkTagResult {
+            created: 1,
+            updated: 1,
+            unchanged: 1,
+        }
+    );
+}
+
+#[actix_web::test]
+async fn test_bulk_upsert_tags_invalid() {
+    let srv = get_test_server().await;
+    let mut bad_color = TEST_TAG_1.clone();
+    bad_color.color = "purple".to_string();
+    let req = srv
+        .post("/api/tags/bulk")
+        .insert_header(("frontend_api_key", "TESTING"));
+    let res = req
+        .send_json(&vec![bad_color, TEST_TAG_1.clone()])
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 400);
+}
+
+#[actix_web::test]
+async fn test_tag_names_ignore_case() {
+    let srv = get_test_server().await;
+    let req = srv.get(format!("/api/tags/{}", TEST_TAG_1.name.to_uppercase()));
+    let mut res = req.send().await.unwrap();
+    assert!(res.status().is_success());
+    let tag: Tag = res.json().await.unwrap();
+    assert_eq!(tag, TEST_TAG_1.clone());
+
+    let mut duplicate = TEST_TAG_1.clone();
+    duplicate.name = format!(" {} ", TEST_TAG_1.name.to_lowercase());
+    let req = srv
+        .post("/api/tags/")
+        .insert_header(("frontend_api_key", "TESTING"));
+    let res = req.send_json(&duplicate).await.unwrap();
+    assert_eq!(res.status().as_u16(), 409);
+}
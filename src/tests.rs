@@ -1,11 +1,11 @@
 use crate::{
-    app::{configure_app, get_app_data},
-    models::{Game, GameWithTags, Tag, User},
+    app::{configure_app, get_app_data, normalize_path},
+    models::{Game, GameWithTags, Tag, User, DEFAULT_TAG_COLOR},
 };
 use actix_test::TestServer;
 use actix_web::App;
 
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveTime};
 use lazy_static::lazy_static;
 
 lazy_static! {
@@ -47,82 +47,102 @@ lazy_static! {
     pub static ref TEST_TAG_1: Tag = Tag {
         name: "TestTag1".to_string(),
         description: "TestTag1 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_2: Tag = Tag {
         name: "TestTag2".to_string(),
         description: "TestTag2 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_3: Tag = Tag {
         name: "TestTag3".to_string(),
         description: "TestTag3 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_4: Tag = Tag {
         name: "TestTag4".to_string(),
         description: "TestTag4 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_5: Tag = Tag {
         name: "TestTag5".to_string(),
         description: "TestTag5 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_6: Tag = Tag {
         name: "TestTag6".to_string(),
         description: "TestTag6 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_7: Tag = Tag {
         name: "TestTag7".to_string(),
         description: "TestTag7 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_8: Tag = Tag {
         name: "TestTag8".to_string(),
         description: "TestTag8 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_9: Tag = Tag {
         name: "TestTag9".to_string(),
         description: "TestTag9 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_10: Tag = Tag {
         name: "TestTag10".to_string(),
         description: "TestTag10 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_11: Tag = Tag {
         name: "TestTag11".to_string(),
         description: "TestTag11 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_12: Tag = Tag {
         name: "TestTag12".to_string(),
         description: "TestTag12 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_13: Tag = Tag {
         name: "TestTag13".to_string(),
         description: "TestTag13 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_14: Tag = Tag {
         name: "TestTag14".to_string(),
         description: "TestTag14 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_15: Tag = Tag {
         name: "TestTag15".to_string(),
         description: "TestTag15 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_16: Tag = Tag {
         name: "TestTag16".to_string(),
         description: "TestTag16 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_17: Tag = Tag {
         name: "TestTag17".to_string(),
         description: "TestTag17 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_18: Tag = Tag {
         name: "TestTag18".to_string(),
         description: "TestTag18 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_19: Tag = Tag {
         name: "TestTag19".to_string(),
         description: "TestTag19 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref TEST_TAG_20: Tag = Tag {
         name: "TestTag20".to_string(),
         description: "TestTag20 Description".to_string(),
+        color: DEFAULT_TAG_COLOR.to_string(),
     };
     pub static ref SKYZ_USER: User = User::from_csh("skyz", "Joe", "Abbate", true);
     pub static ref QEL_USER: User = User::from_csh("qel", "Jeremy", "Smart", false);
@@ -177,6 +197,12 @@ fn make_test_game(id_letter: &str, author: &str, hash: &str, date: NaiveDate, na
         name: name.to_string(),
         hash: Some(hash.to_string()),
         description: format!("{} Description", name),
+        downloads: 0,
+        play_count: 0,
+        last_played_at: None,
+        updated_at: date.and_time(NaiveTime::MIN).and_utc(),
+        published: true,
+        size_bytes: None,
     }
 }
 
@@ -184,6 +210,7 @@ pub async fn get_test_server() -> TestServer {
     let app_data = get_app_data().await;
     actix_test::start(move || {
         App::new()
+            .wrap(normalize_path())
             .configure(configure_app)
             .app_data(app_data.clone())
     })
@@ -192,7 +219,7 @@ pub async fn get_test_server() -> TestServer {
 #[actix_web::test]
 async fn test_docs_reachable() {
     let srv = get_test_server().await;
-    let req = srv.get("/docs/");
+    let req = srv.get("/api/docs/");
     let mut res = req.send().await.unwrap();
     println!(
         "{} | {}",
@@ -205,7 +232,7 @@ async fn test_docs_reachable() {
 #[actix_web::test]
 async fn test_openapi_reachable() {
     let srv = get_test_server().await;
-    let req = srv.get("/api-doc/openapi.json");
+    let req = srv.get("/api/openapi.json");
     let mut res = req.send().await.unwrap();
     println!(
         "{} | {}",
@@ -214,3 +241,10 @@ async fn test_openapi_reachable() {
     );
     assert!(res.status().is_success());
 }
+
+#[actix_web::test]
+async fn test_metrics_reachable() {
+    let srv = get_test_server().await;
+    let res = srv.get("/metrics").send().await.unwrap();
+    assert!(res.status().is_success());
+}
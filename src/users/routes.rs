@@ -1,29 +1,30 @@
 use crate::{
-    models::{AppState, User},
+    error::ApiError,
+    models::{AppState, GameWithTags, User},
     security::RequireApiKey,
 };
 use actix_web::{
     get, post, put,
     web::{Data, Json, Path},
-    HttpResponse, Responder,
+    HttpResponse,
 };
-use sqlx::{query, query_as};
+use sqlx::{query, query_as, query_scalar};
 
 #[utoipa::path(
     context_path = "/users",
     request_body(content=User, content_type="application/json", description="User Information"),
     responses(
         (status = 201, description = "Created new user"),
-        (status = 401, description = "Invalid/Missing API Key"),
-        (status = 500, description = "Error Created by Query"),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     ),
     security(
         ("api_key" = [])
     )
 )]
-#[post("/", wrap = "RequireApiKey")]
-pub async fn add_user(state: Data<AppState>, user: Json<User>) -> impl Responder {
-    match query("INSERT INTO users VALUES ($1, $2, $3, $4, $5, $6, $7)")
+#[post("", wrap = "RequireApiKey")]
+pub async fn add_user(state: Data<AppState>, user: Json<User>) -> Result<HttpResponse, ApiError> {
+    query("INSERT INTO users VALUES ($1, $2, $3, $4, $5, $6, $7)")
         .bind(&user.id)
         .bind(&user.user_type)
         .bind(&user.first_name)
@@ -32,39 +33,92 @@ pub async fn add_user(state: Data<AppState>, user: Json<User>) -> impl Responder
         .bind(user.admin)
         .bind(&user.email)
         .execute(&state.db)
-        .await
-    {
-        Ok(_) => HttpResponse::Created().json(User {
-            id: user.id.clone(),
-            user_type: user.user_type.clone(),
-            first_name: user.first_name.clone(),
-            last_name: user.last_name.clone(),
-            picture: user.picture.clone(),
-            admin: user.admin,
-            email: user.email.clone(),
-        }),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-    }
+        .await?;
+    Ok(HttpResponse::Created().json(user.into_inner()))
+}
+
+#[utoipa::path(
+    context_path = "/users",
+    responses(
+        (status = 200, description = "List every user who has uploaded a game", body = [User]),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    )
+)]
+#[get("")]
+pub async fn get_all_authors(state: Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let users = query_as::<_, User>(
+        "SELECT * FROM users WHERE EXISTS(
+            SELECT 1 FROM game WHERE game.author = users.id AND game.deleted_at IS NULL
+        ) ORDER BY id",
+    )
+    .fetch_all(&state.db)
+    .await?;
+    Ok(HttpResponse::Ok().json(users))
 }
 
 #[utoipa::path(
     context_path = "/users",
     responses(
         (status = 200, description = "Get specified user", body = User),
-        (status = 500, description = "Error Created by Query"),
+        (status = 404, description = "User does not exist", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     )
 )]
 #[get("/{uid}")]
-pub async fn get_user(state: Data<AppState>, path: Path<(String,)>) -> impl Responder {
+pub async fn get_user(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
     let (uid,) = path.into_inner();
-    match query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+    let user: User = query_as("SELECT * FROM users WHERE id = $1")
         .bind(uid)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("User Does Not Exist".to_string()))?;
+    Ok(HttpResponse::Ok().json(user))
+}
+
+#[utoipa::path(
+    context_path = "/users",
+    responses(
+        (status = 200, description = "Every game by the user, newest first", body = [GameWithTags]),
+        (status = 404, description = "User does not exist", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
+    ),
+    params(
+        ("uid", description = "Unique id of user")
+    ),
+)]
+#[get("/{uid}/games")]
+pub async fn get_user_games(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+) -> Result<HttpResponse, ApiError> {
+    let (uid,) = path.into_inner();
+    if !query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)")
+        .bind(&uid)
         .fetch_one(&state.db)
-        .await
+        .await?
     {
-        Ok(user) => HttpResponse::Ok().json(user),
-        Err(_) => HttpResponse::BadRequest().body("User Does Not Exist"),
+        return Err(ApiError::NotFound("User Does Not Exist".to_string()));
     }
+    let games = query_as::<_, GameWithTags>(
+        "
+        SELECT game.*,
+            ROW(users.*)::users AS \"user\",
+            array_remove(ARRAY_AGG(tags.*), NULL) AS \"tags\"
+        FROM game
+        LEFT JOIN game_tags ON game_tags.game_id = game.id
+        LEFT JOIN tags ON tags.name = game_tags.tag_name
+        LEFT JOIN users ON game.author = users.id
+        WHERE game.author = $1 AND game.published AND game.deleted_at IS NULL
+        GROUP BY game.id, users.id ORDER BY upload_date DESC, name ASC, game.id ASC
+        ",
+    )
+    .bind(&uid)
+    .fetch_all(&state.db)
+    .await?;
+    Ok(HttpResponse::Ok().json(games))
 }
 
 #[utoipa::path(
@@ -72,8 +126,8 @@ pub async fn get_user(state: Data<AppState>, path: Path<(String,)>) -> impl Resp
     request_body(content=User, content_type="application/json", description="User Information"),
     responses(
         (status = 201, description = "Updated user"),
-        (status = 401, description = "Invalid/Missing API Key"),
-        (status = 500, description = "Error Created by Query"),
+        (status = 401, description = "Invalid/Missing API Key", body = ErrorBody),
+        (status = 500, description = "Error Created by Query", body = ErrorBody),
     ),
     security(
         ("api_key" = [])
@@ -84,7 +138,7 @@ pub async fn edit_user(
     state: Data<AppState>,
     path: Path<(String,)>,
     user: Json<User>,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let (uid,) = path.into_inner();
     if query_as::<_, User>("SELECT * FROM users WHERE id = $1")
         .bind(&uid)
@@ -92,9 +146,9 @@ pub async fn edit_user(
         .await
         .is_err()
     {
-        return HttpResponse::BadRequest().body("Tag Does Not Exist");
+        return Err(ApiError::BadRequest("User Does Not Exist".to_string()));
     }
-    match query("UPDATE users SET first_name = $1, last_name = $2, picture = $3, admin = $4, email = $5 WHERE id = $6")
+    query("UPDATE users SET first_name = $1, last_name = $2, picture = $3, admin = $4, email = $5 WHERE id = $6")
         .bind(&user.first_name)
         .bind(&user.last_name)
         .bind(&user.picture)
@@ -102,17 +156,6 @@ pub async fn edit_user(
         .bind(&user.email)
         .bind(uid)
         .execute(&state.db)
-        .await
-    {
-        Ok(_) => HttpResponse::Created().json(User {
-            id: user.id.clone(),
-            user_type: user.user_type.clone(),
-            first_name: user.first_name.clone(),
-            last_name: user.last_name.clone(),
-            picture: user.picture.clone(),
-            admin: user.admin,
-            email: user.email.clone(),
-        }),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-    }
+        .await?;
+    Ok(HttpResponse::Created().json(user.into_inner()))
 }
@@ -1,10 +1,13 @@
 #[cfg(test)]
-use crate::tests::{get_test_server, MCDADE_USER, MTFT_USER};
+use crate::{
+    models::{GameWithTags, User},
+    tests::{get_test_server, GOD_USER, MCDADE_USER, MTFT_USER, TEST_GAME_B},
+};
 
 #[actix_web::test]
 async fn test_get_user() {
     let srv = get_test_server().await;
-    let req = srv.get("/users/skyz");
+    let req = srv.get("/api/users/skyz");
     let mut res = req.send().await.unwrap();
     println!(
         "{} | {}",
@@ -18,7 +21,7 @@ async fn test_get_user() {
 async fn test_add_user() {
     let srv = get_test_server().await;
     let req = srv
-        .post("/users/")
+        .post("/api/users/")
         .insert_header(("frontend_api_key", "TESTING"));
     let mut res = req.send_json(&MCDADE_USER.clone()).await.unwrap();
     println!(
@@ -32,7 +35,7 @@ async fn test_add_user() {
 #[actix_web::test]
 async fn test_add_user_unauthorized() {
     let srv = get_test_server().await;
-    let req = srv.post("/users/");
+    let req = srv.post("/api/users/");
     let mut res = req.send_json(&MCDADE_USER.clone()).await.unwrap();
     println!(
         "{} | {}",
@@ -46,7 +49,7 @@ async fn test_add_user_unauthorized() {
 async fn test_edit_user() {
     let srv = get_test_server().await;
     let req = srv
-        .put(format!("/users/{}", MTFT_USER.id))
+        .put(format!("/api/users/{}", MTFT_USER.id))
         .insert_header(("frontend_api_key", "TESTING"));
     let mut edited_user = MTFT_USER.clone();
     edited_user.picture = "CHANGE PICTURE".to_string();
@@ -62,7 +65,7 @@ async fn test_edit_user() {
 #[actix_web::test]
 async fn test_edit_user_unauthorized() {
     let srv = get_test_server().await;
-    let req = srv.put(format!("/users/{}", MTFT_USER.id));
+    let req = srv.put(format!("/api/users/{}", MTFT_USER.id));
     let mut edited_user = MTFT_USER.clone();
     edited_user.picture = "CHANGE PICTURE".to_string();
     let mut res = req.send_json(&edited_user).await.unwrap();
@@ -73,3 +76,42 @@ async fn test_edit_user_unauthorized() {
     );
     assert_eq!(res.status().as_u16(), 401);
 }
+
+#[actix_web::test]
+async fn test_get_user_games() {
+    let srv = get_test_server().await;
+    let mut res = srv.get("/api/users/qel/games").send().await.unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<GameWithTags> = res.json().await.unwrap();
+    assert!(games.iter().any(|game| game.id == TEST_GAME_B.id));
+    assert!(games.iter().all(|game| game.author == "qel"));
+
+    let mut res = srv
+        .get(format!("/api/users/{}/games", GOD_USER.id))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let games: Vec<GameWithTags> = res.json().await.unwrap();
+    assert!(games.is_empty());
+
+    let res = srv.get("/api/users/nobody/games").send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn test_get_all_authors() {
+    let srv = get_test_server().await;
+    let mut res = srv.get("/api/users/").send().await.unwrap();
+    assert!(res.status().is_success());
+    let users: Vec<User> = res.json().await.unwrap();
+    assert!(users.iter().any(|user| user.id == "qel"));
+    assert!(!users.iter().any(|user| user.id == GOD_USER.id));
+}
+
+#[actix_web::test]
+async fn test_get_user_missing() {
+    let srv = get_test_server().await;
+    let res = srv.get("/api/users/nobody").send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 404);
+}
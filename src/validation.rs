@@ -0,0 +1,30 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, ToSchema, PartialEq)]
+pub struct FieldError {
+    #[schema(example = "name")]
+    pub field: String,
+    #[schema(example = "Name must not be empty")]
+    pub message: String,
+}
+
+/// Accumulates every problem with a request body so clients can report them
+/// all at once rather than fixing one field per round trip
+#[derive(Debug, Clone, Default, Serialize, ToSchema, PartialEq)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+}